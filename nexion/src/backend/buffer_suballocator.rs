@@ -0,0 +1,269 @@
+use ash::vk;
+
+use crate::{BufferDescription, BufferId, BufferSlice, BufferSliceDescription, MemoryType, SharingMode, backend::device::InnerDevice};
+
+/// Size of a regular backing buffer, bigger slices get a backing buffer of their own
+const BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlockKey {
+    usage: vk::BufferUsageFlags,
+    memory_type: MemoryType,
+    create_mapped: bool,
+    sharing_mode: SharingMode,
+}
+
+pub(crate) struct BufferBlock {
+    key: BlockKey,
+    buffer: BufferId,
+    address: vk::DeviceAddress,
+    size: vk::DeviceSize,
+    // Sorted by offset and never adjacent, neighbours get merged on free
+    free_ranges: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    dedicated: bool,
+}
+
+impl BufferBlock {
+    fn allocate(&mut self, size: vk::DeviceSize, alignment: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        for i in 0..self.free_ranges.len() {
+            let (start, end) = self.free_ranges[i];
+            let offset = start.next_multiple_of(alignment);
+
+            if offset + size > end {
+                continue;
+            }
+
+            self.free_ranges.remove(i);
+            if offset + size < end {
+                self.free_ranges.insert(i, (offset + size, end));
+            }
+            // Padding in front stays free for smaller alignments
+            if start < offset {
+                self.free_ranges.insert(i, (start, offset));
+            }
+
+            return Some(offset);
+        }
+
+        return None;
+    }
+
+    fn free(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let mut start = offset;
+        let mut end = offset + size;
+
+        let i = self.free_ranges.partition_point(|range| range.0 < start);
+
+        if i < self.free_ranges.len() && self.free_ranges[i].0 == end {
+            end = self.free_ranges.remove(i).1;
+        }
+
+        if i > 0 && self.free_ranges[i - 1].1 == start {
+            start = self.free_ranges.remove(i - 1).0;
+            self.free_ranges.insert(i - 1, (start, end));
+        } else {
+            self.free_ranges.insert(i, (start, end));
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        return self.free_ranges.len() == 1 && self.free_ranges[0] == (0, self.size);
+    }
+}
+
+pub(crate) struct BufferSuballocator {
+    pub(crate) blocks: Vec<BufferBlock>,
+}
+
+// Buffer slices //
+impl InnerDevice {
+    fn slice_alignment(&self, usage: vk::BufferUsageFlags, extra: vk::DeviceSize) -> vk::DeviceSize {
+        let limits = &self.physical_device.properties.limits;
+
+        let mut alignment = 16.max(extra);
+
+        if usage.contains(vk::BufferUsageFlags::STORAGE_BUFFER) {
+            alignment = alignment.max(limits.min_storage_buffer_offset_alignment);
+        }
+        if usage.contains(vk::BufferUsageFlags::UNIFORM_BUFFER) {
+            alignment = alignment.max(limits.min_uniform_buffer_offset_alignment);
+        }
+        if usage.intersects(vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER | vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER) {
+            alignment = alignment.max(limits.min_texel_buffer_offset_alignment);
+        }
+
+        return alignment;
+    }
+
+    pub(crate) fn create_buffer_slice(&self, slice_desc: &BufferSliceDescription) -> BufferSlice {
+        assert!(slice_desc.size > 0, "Buffer slices cant be empty");
+        assert!(slice_desc.alignment == 0 || slice_desc.alignment.is_power_of_two(), "Buffer slice alignment must be a power of two");
+
+        let key = BlockKey {
+            usage: slice_desc.usage.to_vk_flag(),
+            memory_type: slice_desc.memory_type,
+            create_mapped: slice_desc.create_mapped,
+            sharing_mode: slice_desc.sharing_mode,
+        };
+        let alignment = self.slice_alignment(key.usage, slice_desc.alignment);
+        let size = slice_desc.size.next_multiple_of(4);
+
+        let suballocator = unsafe { &mut *self.suballocator.get() };
+
+        for block in suballocator.blocks.iter_mut().filter(|block| block.key == key && !block.dedicated) {
+            if let Some(offset) = block.allocate(size, alignment) {
+                return BufferSlice {
+                    buffer: block.buffer,
+                    offset: offset,
+                    size: slice_desc.size,
+                    address: block.address + offset,
+                };
+            }
+        }
+
+        let dedicated = size > BLOCK_SIZE;
+        let block_size = if dedicated { size } else { BLOCK_SIZE };
+
+        let buffer = self.create_buffer(&BufferDescription {
+            usage: slice_desc.usage,
+            size: block_size,
+            memory_type: slice_desc.memory_type,
+            create_mapped: slice_desc.create_mapped,
+            external_memory: None,
+            sharing_mode: slice_desc.sharing_mode,
+        });
+
+        let mut block = BufferBlock {
+            key: key,
+            buffer: buffer,
+            address: self.get_device_address(buffer),
+            size: block_size,
+            free_ranges: vec![(0, block_size)],
+            dedicated: dedicated,
+        };
+
+        // Buffer base addresses satisfy every offset alignment, so this starts at 0
+        let offset = block.allocate(size, alignment).unwrap();
+        let slice = BufferSlice {
+            buffer: buffer,
+            offset: offset,
+            size: slice_desc.size,
+            address: block.address + offset,
+        };
+
+        suballocator.blocks.push(block);
+
+        return slice;
+    }
+
+    pub(crate) fn destroy_buffer_slice(&self, slice: BufferSlice) {
+        let suballocator = unsafe { &mut *self.suballocator.get() };
+
+        let index = suballocator.blocks.iter().position(|block| block.buffer == slice.buffer).expect("Tried to destroy a buffer slice that doesnt exist");
+
+        let block = &mut suballocator.blocks[index];
+        block.free(slice.offset, slice.size.next_multiple_of(4));
+
+        // Regular blocks are kept around for later slices
+        if block.dedicated && block.is_empty() {
+            let block = suballocator.blocks.swap_remove(index);
+            self.destroy_buffer(block.buffer);
+        }
+    }
+
+    pub(crate) fn write_data_to_buffer_slice<T: Copy>(&self, slice: BufferSlice, data: &[T]) {
+        assert!(size_of_val(data) as u64 <= slice.size, "Data doesnt fit in the buffer slice");
+
+        unsafe {
+            let ptr = self.get_raw_ptr(slice.buffer).add(slice.offset as usize) as *mut T;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(size: vk::DeviceSize) -> BufferBlock {
+        return BufferBlock {
+            key: BlockKey {
+                usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+                memory_type: MemoryType::DeviceLocal,
+                create_mapped: false,
+                sharing_mode: SharingMode::Exclusive,
+            },
+            buffer: BufferId::null(),
+            address: 0,
+            size: size,
+            free_ranges: vec![(0, size)],
+            dedicated: false,
+        };
+    }
+
+    #[test]
+    fn allocate_splits_free_range() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(256, 16), Some(0));
+        assert_eq!(block.allocate(128, 16), Some(256));
+        assert_eq!(block.free_ranges, vec![(384, 1024)]);
+    }
+
+    #[test]
+    fn allocate_keeps_alignment_padding_free() {
+        let mut block = block(1024);
+
+        assert_eq!(block.allocate(4, 4), Some(0));
+        assert_eq!(block.allocate(64, 256), Some(256));
+        assert_eq!(block.free_ranges, vec![(4, 256), (320, 1024)]);
+
+        // The padding is reused by an allocation that fits in it
+        assert_eq!(block.allocate(16, 16), Some(16));
+        assert_eq!(block.free_ranges, vec![(4, 16), (32, 256), (320, 1024)]);
+    }
+
+    #[test]
+    fn allocate_fails_when_nothing_fits() {
+        let mut block = block(256);
+
+        assert_eq!(block.allocate(200, 16), Some(0));
+        assert_eq!(block.allocate(64, 16), None);
+        // Enough space in total, but not past the alignment
+        assert_eq!(block.allocate(48, 64), None);
+        assert_eq!(block.free_ranges, vec![(200, 256)]);
+    }
+
+    #[test]
+    fn free_coalesces_with_both_neighbours() {
+        let mut block = block(1024);
+
+        let a = block.allocate(256, 16).unwrap();
+        let b = block.allocate(256, 16).unwrap();
+        let c = block.allocate(256, 16).unwrap();
+
+        block.free(a, 256);
+        block.free(c, 256);
+        assert_eq!(block.free_ranges, vec![(0, 256), (512, 1024)]);
+
+        block.free(b, 256);
+        assert_eq!(block.free_ranges, vec![(0, 1024)]);
+        assert!(block.is_empty());
+    }
+
+    #[test]
+    fn free_out_of_order_stays_sorted() {
+        let mut block = block(1024);
+
+        let offsets: Vec<_> = (0..4).map(|_| block.allocate(128, 16).unwrap()).collect();
+
+        block.free(offsets[2], 128);
+        block.free(offsets[0], 128);
+        assert_eq!(block.free_ranges, vec![(0, 128), (256, 384), (512, 1024)]);
+        assert!(!block.is_empty());
+
+        block.free(offsets[3], 128);
+        block.free(offsets[1], 128);
+        assert_eq!(block.free_ranges, vec![(0, 1024)]);
+    }
+}
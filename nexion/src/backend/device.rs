@@ -1,11 +1,23 @@
 use crate::{
-    backend::{gpu_resources::*, instance::InnerInstance},
+    backend::{
+        buffer_suballocator::BufferSuballocator,
+        resource_tracker::ResourceStateTracker,
+        external_memory::{DedicatedResource, ExternalAllocation},
+        gpu_profiler::GpuProfiler,
+        gpu_resources::*,
+        instance::InnerInstance,
+    },
     *,
 };
 
 use ash::vk;
 use gpu_allocator::{vulkan::*, *};
-use std::{cell::UnsafeCell, sync::Arc};
+use smallvec::{SmallVec, smallvec};
+use std::{
+    cell::UnsafeCell,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 pub(crate) struct QueueFamilyIndices {
     pub graphics_family: Option<u32>,
@@ -22,6 +34,10 @@ impl QueueFamilyIndices {
 pub(crate) struct PhysicalDevice {
     pub handle: vk::PhysicalDevice,
     pub queue_families: QueueFamilyIndices,
+    pub properties: vk::PhysicalDeviceProperties,
+    pub features: vk::PhysicalDeviceFeatures,
+    // Only queried when DeviceDescription::custom_border_colors is set
+    pub custom_border_color_without_format: bool,
 }
 
 // TODO: Should i use an unsafe cell instead of RwLock?
@@ -31,13 +47,27 @@ pub(crate) struct InnerDevice {
     pub(crate) handle: ash::Device,
     pub(crate) physical_device: PhysicalDevice,
     pub(crate) instance: Arc<InnerInstance>,
+    pub(crate) desc: DeviceDescription,
 
     //Pools for various gpu resources
     pub(crate) bindless_descriptors: GpuBindlessDescriptorPool,
     pub(crate) buffer_pool: UnsafeCell<ResourcePool<BufferSlot>>,
+    pub(crate) buffer_view_pool: UnsafeCell<ResourcePool<BufferViewSlot>>,
     pub(crate) image_pool: UnsafeCell<ResourcePool<ImageSlot>>,
     pub(crate) image_view_pool: UnsafeCell<ResourcePool<ImageViewSlot>>,
     pub(crate) sampler_pool: UnsafeCell<ResourcePool<SamplerSlot>>,
+    pub(crate) sampler_cache: UnsafeCell<SamplerCache>,
+    // Backing buffers of buffer slices, they live in buffer_pool as well
+    pub(crate) suballocator: UnsafeCell<BufferSuballocator>,
+    // Last submitted layout and access of resources used through CommandRecorder::use_image and use_buffer
+    pub(crate) resource_tracker: Mutex<ResourceStateTracker>,
+    // Timestamp query pools per frame in flight, see CommandRecorder::begin_gpu_scope
+    pub(crate) gpu_profiler: Mutex<GpuProfiler>,
+
+    // Extension loaders
+    pub(crate) external_memory_fd: Option<ash::khr::external_memory_fd::Device>,
+    pub(crate) external_semaphore_fd: Option<ash::khr::external_semaphore_fd::Device>,
+    pub(crate) mesh_shader: Option<ash::ext::mesh_shader::Device>,
 
     //Queues
     pub(crate) graphics_queue: vk::Queue,
@@ -63,6 +93,25 @@ impl InnerDevice {
             device_extensions.push(ash::ext::shader_atomic_float::NAME.as_ptr());
         }
 
+        if device_desc.custom_border_colors {
+            device_extensions.push(ash::ext::custom_border_color::NAME.as_ptr());
+        }
+
+        if device_desc.external_memory {
+            device_extensions.push(ash::khr::external_memory_fd::NAME.as_ptr());
+            device_extensions.push(ash::khr::external_semaphore_fd::NAME.as_ptr());
+        }
+
+        if device_desc.external_memory_dma_buf {
+            assert!(device_desc.external_memory, "external_memory_dma_buf needs external_memory as well");
+            device_extensions.push(ash::ext::external_memory_dma_buf::NAME.as_ptr());
+        }
+
+        // Enabling the promoted extension also enables the samplerFilterMinmax feature
+        if device_desc.sampler_filter_minmax {
+            device_extensions.push(ash::ext::sampler_filter_minmax::NAME.as_ptr());
+        }
+
         if device_desc.mesh_shaders {
             device_extensions.push(ash::ext::mesh_shader::NAME.as_ptr());
             device_extensions.push(ash::khr::shader_float_controls::NAME.as_ptr());
@@ -71,7 +120,7 @@ impl InnerDevice {
             }
         }
 
-        let physical_device = {
+        let mut physical_device = {
             let dev = Self::select_physical_device(&instance, &device_extensions);
             if dev.is_none() {
                 panic!("Failed to find vulkan compatible device")
@@ -80,6 +129,14 @@ impl InnerDevice {
             dev.unwrap()
         };
 
+        if device_desc.custom_border_colors {
+            let mut supported = vk::PhysicalDeviceCustomBorderColorFeaturesEXT::default();
+            let mut features2 = vk::PhysicalDeviceFeatures2::default().push_next(&mut supported);
+            unsafe { instance.handle.get_physical_device_features2(physical_device.handle, &mut features2) };
+
+            physical_device.custom_border_color_without_format = supported.custom_border_color_without_format == vk::TRUE;
+        }
+
         let unique_families: Vec<u32> = {
             let mut v = vec![
                 physical_device.queue_families.graphics_family.unwrap(),
@@ -95,8 +152,23 @@ impl InnerDevice {
         let priorities = [1.0_f32];
         let queue_infos: Vec<_> = unique_families.iter().map(|&family| vk::DeviceQueueCreateInfo::default().queue_family_index(family).queue_priorities(&priorities)).collect();
 
+        assert!(
+            !device_desc.pipeline_statistics_queries || physical_device.features.pipeline_statistics_query == vk::TRUE,
+            "DeviceDescription::pipeline_statistics_queries is set but the device doesnt support pipeline statistics queries"
+        );
+
         // Existing common features
-        let features = vk::PhysicalDeviceFeatures::default().shader_int64(true).multi_draw_indirect(true).sampler_anisotropy(true);
+        let features = vk::PhysicalDeviceFeatures::default()
+            .shader_int64(true)
+            .multi_draw_indirect(true)
+            .sampler_anisotropy(true)
+            // Optional, only needed by pipelines with MultisampleState::sample_shading
+            .sample_rate_shading(physical_device.features.sample_rate_shading == vk::TRUE)
+            // Optional, only used by the compute fallback of CommandRecorder::generate_mipmaps
+            .shader_storage_image_read_without_format(physical_device.features.shader_storage_image_read_without_format == vk::TRUE)
+            .shader_storage_image_write_without_format(physical_device.features.shader_storage_image_write_without_format == vk::TRUE)
+            .occlusion_query_precise(physical_device.features.occlusion_query_precise == vk::TRUE)
+            .pipeline_statistics_query(device_desc.pipeline_statistics_queries);
         let mut float_atomic_features = vk::PhysicalDeviceShaderAtomicFloatFeaturesEXT::default().shader_buffer_float32_atomic_add(true);
 
         let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
@@ -116,6 +188,7 @@ impl InnerDevice {
         let mut sync2 = vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
         let mut timeline_sem = vk::PhysicalDeviceTimelineSemaphoreFeatures::default().timeline_semaphore(true);
         let mut buffer_device_address = vk::PhysicalDeviceBufferDeviceAddressFeatures::default().buffer_device_address(true);
+        let mut host_query_reset = vk::PhysicalDeviceHostQueryResetFeatures::default().host_query_reset(true);
         let mut vk_features_11 = vk::PhysicalDeviceVulkan11Features::default().shader_draw_parameters(true).variable_pointers(true).variable_pointers_storage_buffer(true);
 
         // Ray tracing
@@ -136,6 +209,9 @@ impl InnerDevice {
             mesh_shader_features = mesh_shader_features.mesh_shader(true).task_shader(true);
         }
 
+        // custom border colors
+        let mut custom_border_color_features = vk::PhysicalDeviceCustomBorderColorFeaturesEXT::default().custom_border_colors(true).custom_border_color_without_format(physical_device.custom_border_color_without_format);
+
         let mut features2 = vk::PhysicalDeviceFeatures2::default()
             .push_next(&mut indexing_features)
             .push_next(&mut dynamic_rendering_features)
@@ -143,6 +219,7 @@ impl InnerDevice {
             .push_next(&mut timeline_sem)
             .push_next(&mut buffer_device_address)
             .push_next(&mut vk_features_11)
+            .push_next(&mut host_query_reset)
             .push_next(&mut mesh_shader_features)
             .features(features);
 
@@ -154,6 +231,10 @@ impl InnerDevice {
             features2 = features2.push_next(&mut float_atomic_features);
         }
 
+        if device_desc.custom_border_colors {
+            features2 = features2.push_next(&mut custom_border_color_features);
+        }
+
         let create_info = vk::DeviceCreateInfo::default().queue_create_infos(&queue_infos).enabled_extension_names(&device_extensions).push_next(&mut features2);
 
         let dev = unsafe { instance.handle.create_device(physical_device.handle, &create_info, None).expect("Failed to create logical device") };
@@ -207,23 +288,50 @@ impl InnerDevice {
                 handle: buffer,
                 allocation: allocation,
                 address: buffer_address,
+                size: 100 * 64,
+                usage: buffer_create_info.usage,
+                sharing_mode: vk::SharingMode::CONCURRENT,
+                external_memory: None,
             }
         };
 
-        let bindless_desc = GpuBindlessDescriptorPool::new(&dev, device_address_buffer, 100, 100, 100);
+        let bindless_desc = GpuBindlessDescriptorPool::new(&dev, device_address_buffer, 100, 100, 100, 100);
+
+        let mesh_shader = device_desc.mesh_shaders.then(|| ash::ext::mesh_shader::Device::new(&instance.handle, &dev));
+
+        let (external_memory_fd, external_semaphore_fd) = if device_desc.external_memory {
+            (Some(ash::khr::external_memory_fd::Device::new(&instance.handle, &dev)), Some(ash::khr::external_semaphore_fd::Device::new(&instance.handle, &dev)))
+        } else {
+            (None, None)
+        };
+
+        let sampler_cache = SamplerCache {
+            entries: HashMap::new(),
+            free_indices: (0..100).collect(),
+        };
 
         return InnerDevice {
             handle: dev,
             physical_device: physical_device,
             allocator: UnsafeCell::new(allocator),
             instance: instance,
+            desc: *device_desc,
 
             //Resource Pools
             bindless_descriptors: bindless_desc,
             buffer_pool: UnsafeCell::new(ResourcePool::new()),
+            buffer_view_pool: UnsafeCell::new(ResourcePool::new()),
             image_pool: UnsafeCell::new(ResourcePool::new()),
             image_view_pool: UnsafeCell::new(ResourcePool::new()),
             sampler_pool: UnsafeCell::new(ResourcePool::new()),
+            sampler_cache: UnsafeCell::new(sampler_cache),
+            suballocator: UnsafeCell::new(BufferSuballocator { blocks: Vec::new() }),
+            resource_tracker: Mutex::new(ResourceStateTracker::default()),
+            gpu_profiler: Mutex::new(GpuProfiler::default()),
+
+            external_memory_fd: external_memory_fd,
+            mesh_shader: mesh_shader,
+            external_semaphore_fd: external_semaphore_fd,
 
             //Queues
             graphics_queue: graphics_queue,
@@ -309,7 +417,15 @@ impl InnerDevice {
                 // Prefer larger max image dimension as tiebreaker
                 let score = score + props.properties.limits.max_image_dimension2_d as i32;
 
-                let candidate = PhysicalDevice { handle: device, queue_families: qf };
+                let features = unsafe { instance.handle.get_physical_device_features(device) };
+
+                let candidate = PhysicalDevice {
+                    handle: device,
+                    queue_families: qf,
+                    properties: props.properties,
+                    features: features,
+                    custom_border_color_without_format: false,
+                };
 
                 if let Some((best_score, _)) = &best_device {
                     if score > *best_score {
@@ -328,34 +444,67 @@ impl InnerDevice {
 // Buffer //
 impl InnerDevice {
     pub(crate) fn create_buffer(&self, buffer_desc: &BufferDescription) -> BufferId {
-        let indices = [
-            self.physical_device.queue_families.compute_family.unwrap(),
-            self.physical_device.queue_families.graphics_family.unwrap(),
-            self.physical_device.queue_families.transfer_family.unwrap(),
-        ];
+        return self.create_buffer_with_memory(buffer_desc, buffer_desc.external_memory.map(ExternalAllocation::Export));
+    }
 
-        let buffer_create_info = vk::BufferCreateInfo::default()
+    /// External buffers get a dedicated allocation outside of gpu-allocator, either exportable or imported from an fd
+    pub(crate) fn create_buffer_with_memory(&self, buffer_desc: &BufferDescription, external: Option<ExternalAllocation>) -> BufferId {
+        let queue_families = self.unique_queue_families();
+
+        // Same as images, concurrent sharing with a single family is exclusive anyway
+        let sharing_mode = match buffer_desc.sharing_mode {
+            SharingMode::Concurrent if queue_families.len() > 1 => vk::SharingMode::CONCURRENT,
+            _ => vk::SharingMode::EXCLUSIVE,
+        };
+
+        let mut external_info = vk::ExternalMemoryBufferCreateInfo::default();
+
+        let mut buffer_create_info = vk::BufferCreateInfo::default()
             .usage(buffer_desc.usage.to_vk_flag() | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS)
             .size(buffer_desc.size)
-            .sharing_mode(vk::SharingMode::CONCURRENT)
-            .queue_family_indices(&indices);
+            .sharing_mode(sharing_mode);
+
+        if sharing_mode == vk::SharingMode::CONCURRENT {
+            buffer_create_info = buffer_create_info.queue_family_indices(&queue_families);
+        }
+
+        if let Some(external) = &external {
+            external_info.handle_types = external.handle_type().to_vk();
+            buffer_create_info = buffer_create_info.push_next(&mut external_info);
+        }
 
         let buffer = unsafe { self.handle.create_buffer(&buffer_create_info, None).expect("Failed to create buffer ") };
         let memory_requirements = unsafe { self.handle.get_buffer_memory_requirements(buffer) };
 
-        let allocation_create_info = AllocationCreateDesc {
-            name: "o",
-            requirements: memory_requirements,
-            location: buffer_desc.memory_type.to_vk_flag(),
-            linear: true,
-            allocation_scheme: AllocationScheme::GpuAllocatorManaged,
-        };
+        let (allocation, external_memory) = match &external {
+            Some(external) => {
+                let memory = self.allocate_external_memory(memory_requirements, buffer_desc.memory_type, DedicatedResource::Buffer(buffer), external);
 
-        let allocation = unsafe { self.allocator.get().as_mut().unwrap().allocate(&allocation_create_info).expect("Failed to allocate memory on device") };
+                unsafe {
+                    self.handle.bind_buffer_memory(buffer, memory.memory, 0).expect("Failed to bind buffer memory");
+                }
+
+                (Allocation::default(), Some(memory))
+            }
+            None => {
+                let allocation_create_info = AllocationCreateDesc {
+                    name: "o",
+                    requirements: memory_requirements,
+                    location: buffer_desc.memory_type.to_vk_flag(),
+                    linear: true,
+                    allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+                };
+
+                let allocation = unsafe { self.allocator.get().as_mut().unwrap().allocate(&allocation_create_info).expect("Failed to allocate memory on device") };
+
+                unsafe {
+                    self.handle.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()).expect("Failed to bind buffer memory");
+                }
+
+                (allocation, None)
+            }
+        };
 
-        unsafe {
-            self.handle.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()).expect("Failed to bind buffer memory");
-        }
         let buffer_address = unsafe { self.handle.get_buffer_device_address(&vk::BufferDeviceAddressInfo::default().buffer(buffer)) };
 
         let raw_id = unsafe {
@@ -363,6 +512,10 @@ impl InnerDevice {
                 handle: buffer,
                 address: buffer_address,
                 allocation: allocation,
+                size: buffer_desc.size,
+                usage: buffer_create_info.usage,
+                sharing_mode: sharing_mode,
+                external_memory: external_memory,
             })
         };
 
@@ -371,10 +524,14 @@ impl InnerDevice {
 
     pub(crate) fn destroy_buffer(&self, id: BufferId) {
         let res = unsafe { (&mut *self.buffer_pool.get()).delete(id.id) };
+        self.forget_buffer_state(id);
 
         unsafe {
-            self.allocator.get().as_mut().unwrap().free(res.allocation).expect("Failed to deallocate buffer");
             self.handle.destroy_buffer(res.handle, None);
+            match res.external_memory {
+                Some(external) => self.handle.free_memory(external.memory, None),
+                None => self.allocator.get().as_mut().unwrap().free(res.allocation).expect("Failed to deallocate buffer"),
+            }
         }
     }
 
@@ -400,57 +557,328 @@ impl InnerDevice {
     }
 }
 
+// Buffer View //
+impl InnerDevice {
+    pub(crate) fn create_buffer_view(&self, buffer_id: BufferId, format: Format, range: std::ops::Range<u64>) -> BufferViewId {
+        let buffer = unsafe { (&*self.buffer_pool.get()).get_ref(buffer_id.id) };
+        let limits = &self.physical_device.properties.limits;
+
+        let texel_usage = vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER | vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER;
+        assert!(buffer.usage.intersects(texel_usage), "Buffer views need a buffer created with UNIFORM_TEXEL or STORAGE_TEXEL usage");
+
+        let (block_width, block_height, texel_size) = format.texel_block_info();
+        assert!(block_width == 1 && block_height == 1, "Block compressed formats cant be used for texel buffers");
+
+        assert!(range.start < range.end, "Buffer view range must not be empty");
+        assert!(range.end <= buffer.size, "Buffer view range is out of bounds of the buffer");
+        assert!(range.start.is_multiple_of(limits.min_texel_buffer_offset_alignment), "Buffer view offset must be a multiple of {}", limits.min_texel_buffer_offset_alignment);
+
+        let size = range.end - range.start;
+        assert!(size.is_multiple_of(texel_size as u64), "Buffer view size must be a multiple of the texel size");
+        assert!(size / texel_size as u64 <= limits.max_texel_buffer_elements as u64, "Buffer view has more than {} texels", limits.max_texel_buffer_elements);
+
+        let vk_format = format.to_vk_format();
+        let features = unsafe { self.instance.handle.get_physical_device_format_properties(self.physical_device.handle, vk_format).buffer_features };
+
+        if buffer.usage.contains(vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER) {
+            assert!(features.contains(vk::FormatFeatureFlags::UNIFORM_TEXEL_BUFFER), "{:?} cant be used for uniform texel buffers on this device", format);
+        }
+        if buffer.usage.contains(vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER) {
+            assert!(features.contains(vk::FormatFeatureFlags::STORAGE_TEXEL_BUFFER), "{:?} cant be used for storage texel buffers on this device", format);
+        }
+
+        let create_info = vk::BufferViewCreateInfo::default().buffer(buffer.handle).format(vk_format).offset(range.start).range(size);
+
+        let view = unsafe { self.handle.create_buffer_view(&create_info, None).expect("Failed to create buffer view") };
+
+        let id = unsafe { (&mut *self.buffer_view_pool.get()).add(BufferViewSlot { handle: view }) };
+
+        return BufferViewId { id: id };
+    }
+
+    pub(crate) fn destroy_buffer_view(&self, buffer_view_id: BufferViewId) {
+        let view = unsafe { (&mut *self.buffer_view_pool.get()).delete(buffer_view_id.id) };
+
+        unsafe {
+            self.handle.destroy_buffer_view(view.handle, None);
+        }
+    }
+}
+
 // Image //
 impl InnerDevice {
     pub(crate) fn create_image(&self, image_desc: &ImageDescription) -> ImageId {
-        let image_create_info = vk::ImageCreateInfo::default()
-            .usage(image_desc.usage.to_vk_flag())
-            .extent(image_desc.extent.to_vk())
-            .format(image_desc.format.to_vk_format())
-            .array_layers(image_desc.array_layers)
-            .mip_levels(image_desc.mip_levels)
-            .initial_layout(vk::ImageLayout::UNDEFINED)
-            .image_type(image_desc.image_type.to_vk())
-            .samples(image_desc.samples.to_vk_flags())
-            .tiling(vk::ImageTiling::OPTIMAL);
+        return self.create_image_with_memory(image_desc, image_desc.external_memory.map(ExternalAllocation::Export));
+    }
 
-        let image = unsafe { self.handle.create_image(&image_create_info, None).expect("Failed to create Image") };
+    pub(crate) fn create_image_with_memory(&self, image_desc: &ImageDescription, external: Option<ExternalAllocation>) -> ImageId {
+        let (image, sharing_mode) = self.create_image_handle(image_desc, external.as_ref());
+
+        let memory_requirements = unsafe { self.handle.get_image_memory_requirements(image) };
+
+        let (allocation, external_memory) = match &external {
+            Some(external) => {
+                let memory = self.allocate_external_memory(memory_requirements, image_desc.memory_type, DedicatedResource::Image(image), external);
+
+                unsafe {
+                    self.handle.bind_image_memory(image, memory.memory, 0).expect("Failed to bind image memory");
+                }
+
+                (Allocation::default(), Some(memory))
+            }
+            None => {
+                let allocation_create_info = AllocationCreateDesc {
+                    name: "o",
+                    requirements: memory_requirements,
+                    location: image_desc.memory_type.to_vk_flag(),
+                    linear: image_desc.tiling == ImageTiling::Linear,
+                    allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+                };
+
+                let allocation = unsafe { self.allocator.get().as_mut().unwrap().allocate(&allocation_create_info).expect("Failed to allocate memory on device") };
+
+                unsafe {
+                    self.handle.bind_image_memory(image, allocation.memory(), allocation.offset()).expect("Failed to bind image memory");
+                }
+
+                (allocation, None)
+            }
+        };
+
+        return self.add_image_slot(image_desc, image, sharing_mode, allocation, external_memory);
+    }
 
+    /// Image without memory, it has to be bound with `bind_image_memory` before use.
+    /// Used for transient images that alias each other inside one allocation.
+    pub(crate) fn create_unbound_image(&self, image_desc: &ImageDescription) -> (ImageId, vk::MemoryRequirements) {
+        let (image, sharing_mode) = self.create_image_handle(image_desc, None);
         let memory_requirements = unsafe { self.handle.get_image_memory_requirements(image) };
 
+        return (self.add_image_slot(image_desc, image, sharing_mode, Allocation::default(), None), memory_requirements);
+    }
+
+    /// Memory shared by several unbound images, freed with `free_aliased_memory` after all of them are destroyed
+    pub(crate) fn allocate_aliased_memory(&self, requirements: vk::MemoryRequirements) -> Allocation {
         let allocation_create_info = AllocationCreateDesc {
-            name: "o",
-            requirements: memory_requirements,
-            location: image_desc.memory_type.to_vk_flag(),
-            linear: true,
+            name: "aliased",
+            requirements: requirements,
+            location: MemoryLocation::GpuOnly,
+            linear: false,
             allocation_scheme: AllocationScheme::GpuAllocatorManaged,
         };
 
-        let allocation = unsafe { self.allocator.get().as_mut().unwrap().allocate(&allocation_create_info).expect("Failed to allocate memory on device") };
+        return unsafe { self.allocator.get().as_mut().unwrap().allocate(&allocation_create_info).expect("Failed to allocate memory on device") };
+    }
 
+    pub(crate) fn free_aliased_memory(&self, allocation: Allocation) {
         unsafe {
-            self.handle.bind_image_memory(image, allocation.memory(), allocation.offset()).expect("Failed to bind image memory");
+            self.allocator.get().as_mut().unwrap().free(allocation).expect("Failed to deallocate aliased memory");
         }
+    }
 
+    pub(crate) fn bind_image_memory(&self, image_id: ImageId, memory: &Allocation, offset: vk::DeviceSize) {
+        let img = unsafe { (&*self.image_pool.get()).get_ref(image_id.id) };
+
+        unsafe {
+            self.handle.bind_image_memory(img.handle, memory.memory(), memory.offset() + offset).expect("Failed to bind image memory");
+        }
+    }
+
+    fn create_image_handle(&self, image_desc: &ImageDescription, external: Option<&ExternalAllocation>) -> (vk::Image, vk::SharingMode) {
+        let flags = image_desc.flags.to_vk_flag();
+        let image_type = image_desc.image_type.to_vk();
+
+        if flags.contains(vk::ImageCreateFlags::CUBE_COMPATIBLE) {
+            assert!(image_type == vk::ImageType::TYPE_2D, "CUBE_COMPATIBLE images must be 2D");
+            assert!(image_desc.extent.width == image_desc.extent.height, "CUBE_COMPATIBLE images must be square");
+            assert!(image_desc.array_layers >= 6, "CUBE_COMPATIBLE images need at least 6 array layers");
+        }
+
+        if flags.contains(vk::ImageCreateFlags::EXTENDED_USAGE) {
+            assert!(flags.contains(vk::ImageCreateFlags::MUTABLE_FORMAT), "EXTENDED_USAGE images must also be MUTABLE_FORMAT");
+        }
+
+        if flags.contains(vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE) {
+            assert!(image_type == vk::ImageType::TYPE_3D, "TYPE_2D_ARRAY_COMPATIBLE images must be 3D");
+        }
+
+        if image_desc.tiling == ImageTiling::Linear {
+            assert!(
+                image_type == vk::ImageType::TYPE_2D && image_desc.mip_levels == 1 && image_desc.array_layers == 1 && image_desc.samples.to_vk_flags() == vk::SampleCountFlags::TYPE_1,
+                "Linear images must be 2D with a single mip level, array layer and sample"
+            );
+        }
+
+        let queue_families = self.unique_queue_families();
+
+        // Concurrent sharing needs at least two distinct families, with one family the image is effectively exclusive anyway
+        let sharing_mode = match image_desc.sharing_mode {
+            SharingMode::Concurrent if queue_families.len() > 1 => vk::SharingMode::CONCURRENT,
+            _ => vk::SharingMode::EXCLUSIVE,
+        };
+
+        let mut image_create_info = vk::ImageCreateInfo::default()
+            .flags(flags)
+            .usage(image_desc.usage.to_vk_flag())
+            .extent(image_desc.extent.to_vk())
+            .format(image_desc.format.to_vk_format())
+            .array_layers(image_desc.array_layers)
+            .mip_levels(image_desc.mip_levels)
+            .initial_layout(image_desc.tiling.initial_layout())
+            .image_type(image_type)
+            .samples(image_desc.samples.to_vk_flags())
+            .tiling(image_desc.tiling.to_vk())
+            .sharing_mode(sharing_mode);
+
+        if sharing_mode == vk::SharingMode::CONCURRENT {
+            image_create_info = image_create_info.queue_family_indices(&queue_families);
+        }
+
+        let mut external_info = vk::ExternalMemoryImageCreateInfo::default();
+        if let Some(external) = external {
+            external_info.handle_types = external.handle_type().to_vk();
+            image_create_info = image_create_info.push_next(&mut external_info);
+        }
+
+        let image = unsafe { self.handle.create_image(&image_create_info, None).expect("Failed to create Image") };
+
+        return (image, sharing_mode);
+    }
+
+    fn add_image_slot(&self, image_desc: &ImageDescription, image: vk::Image, sharing_mode: vk::SharingMode, allocation: Allocation, external_memory: Option<ExternalMemory>) -> ImageId {
         let id = unsafe {
             (&mut *self.image_pool.get()).add(ImageSlot {
                 handle: image,
                 allocation: allocation,
                 format: image_desc.format.to_vk_format(),
+                extent: image_desc.extent.to_vk(),
+                mip_levels: image_desc.mip_levels,
+                array_layers: image_desc.array_layers,
+                usage: image_desc.usage.to_vk_flag(),
+                flags: image_desc.flags.to_vk_flag(),
+                image_type: image_desc.image_type.to_vk(),
+                tiling: image_desc.tiling.to_vk(),
+                samples: image_desc.samples.to_vk_flags(),
+                sharing_mode: sharing_mode,
+                external_memory: external_memory,
             })
         };
 
+        if image_desc.tiling == ImageTiling::Linear {
+            self.track_preinitialized_image(ImageId { id: id });
+        }
+
         return ImageId { id: id };
     }
 
     pub(crate) fn destroy_image(&self, id: ImageId) {
         let img = unsafe { (&mut *self.image_pool.get()).delete(id.id) };
+        self.forget_image_state(id);
 
         unsafe {
-            self.allocator.get().as_mut().unwrap().free(img.allocation).expect("Failed to deallocate image");
             self.handle.destroy_image(img.handle, None);
+            match img.external_memory {
+                Some(external) => self.handle.free_memory(external.memory, None),
+                None => self.allocator.get().as_mut().unwrap().free(img.allocation).expect("Failed to deallocate image"),
+            }
         };
     }
+
+    pub(crate) fn get_image_raw_ptr(&self, image_id: ImageId) -> *mut u8 {
+        let img = unsafe { (&*self.image_pool.get()).get_ref(image_id.id) };
+
+        assert!(img.tiling == vk::ImageTiling::LINEAR, "Only linear images can be accessed from the CPU");
+
+        return img.allocation.mapped_ptr().expect("Tried to map an image that isnt host visible").as_ptr() as *mut u8;
+    }
+
+    pub(crate) fn get_image_subresource_layout(&self, image_id: ImageId, aspect: ImageAspect, mip_level: u32, array_layer: u32) -> SubresourceLayout {
+        let img = unsafe { (&*self.image_pool.get()).get_ref(image_id.id) };
+
+        let subresource = vk::ImageSubresource {
+            aspect_mask: aspect.to_vk_aspect(),
+            mip_level: mip_level,
+            array_layer: array_layer,
+        };
+
+        let layout = unsafe { self.handle.get_image_subresource_layout(img.handle, subresource) };
+
+        return SubresourceLayout {
+            offset: layout.offset,
+            size: layout.size,
+            row_pitch: layout.row_pitch,
+            array_pitch: layout.array_pitch,
+            depth_pitch: layout.depth_pitch,
+        };
+    }
+
+    /// Distinct queue families used by the graphics, compute and transfer queues
+    /// `QUEUE_FAMILY_IGNORED` for `QueueType::None`
+    pub(crate) fn queue_family_index(&self, queue_type: QueueType) -> u32 {
+        let families = &self.physical_device.queue_families;
+
+        return match queue_type {
+            QueueType::Graphics => families.graphics_family.unwrap(),
+            QueueType::Compute => families.compute_family.unwrap(),
+            QueueType::Transfer => families.transfer_family.unwrap(),
+            QueueType::None => vk::QUEUE_FAMILY_IGNORED,
+        };
+    }
+
+    pub(crate) fn unique_queue_families(&self) -> SmallVec<[u32; 3]> {
+        let mut families: SmallVec<[u32; 3]> = smallvec![
+            self.physical_device.queue_families.graphics_family.unwrap(),
+            self.physical_device.queue_families.compute_family.unwrap(),
+            self.physical_device.queue_families.transfer_family.unwrap(),
+        ];
+        families.sort();
+        families.dedup();
+
+        return families;
+    }
+}
+
+impl InnerDevice {
+    pub(crate) fn get_format_features(&self, format: vk::Format) -> vk::FormatFeatureFlags {
+        let properties = unsafe { self.instance.handle.get_physical_device_format_properties(self.physical_device.handle, format) };
+
+        return properties.optimal_tiling_features;
+    }
+
+    /// Sample counts a 2D optimal image with this format and usage supports
+    pub(crate) fn supported_sample_counts(&self, format: vk::Format, usage: vk::ImageUsageFlags) -> vk::SampleCountFlags {
+        let properties = unsafe {
+            self.instance
+                .handle
+                .get_physical_device_image_format_properties(self.physical_device.handle, format, vk::ImageType::TYPE_2D, vk::ImageTiling::OPTIMAL, usage, vk::ImageCreateFlags::empty())
+        };
+
+        return properties.map_or(vk::SampleCountFlags::empty(), |properties| properties.sample_counts);
+    }
+
+    /// Image usages allowed by the optimal tiling features of a format
+    pub(crate) fn get_format_usages(&self, format: vk::Format) -> vk::ImageUsageFlags {
+        let features = self.get_format_features(format);
+        let mut usage = vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST;
+
+        if features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+            usage |= vk::ImageUsageFlags::SAMPLED;
+        }
+        if features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE) {
+            usage |= vk::ImageUsageFlags::STORAGE;
+        }
+        if features.contains(vk::FormatFeatureFlags::COLOR_ATTACHMENT) {
+            usage |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        }
+        if features.contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT) {
+            usage |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
+        }
+        if features.intersects(vk::FormatFeatureFlags::COLOR_ATTACHMENT | vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT) {
+            usage |= vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT;
+        }
+
+        return usage;
+    }
 }
 
 // Image View //
@@ -458,21 +886,66 @@ impl InnerDevice {
     pub(crate) fn create_image_view(&self, image_id: ImageId, image_view_description: &ImageViewDescription) -> ImageViewId {
         let img = unsafe { (&mut *self.image_pool.get()).get_ref(image_id.id) };
 
-        let image_view_create_info = vk::ImageViewCreateInfo::default()
+        let subresources = &image_view_description.subresources;
+        let layer_count = subresources.layer_count;
+
+        match image_view_description.view_type {
+            ImageViewType::Type1D | ImageViewType::Type1DArray => assert!(img.image_type == vk::ImageType::TYPE_1D, "1D views need a 1D image"),
+            ImageViewType::Type2D | ImageViewType::Type2DArray => assert!(
+                img.image_type == vk::ImageType::TYPE_2D || (img.image_type == vk::ImageType::TYPE_3D && img.flags.contains(vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE)),
+                "2D views need a 2D image, or a 3D image created with ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE"
+            ),
+            ImageViewType::Type3D => assert!(img.image_type == vk::ImageType::TYPE_3D, "3D views need a 3D image"),
+            ImageViewType::Cube | ImageViewType::CubeArray => {
+                assert!(img.flags.contains(vk::ImageCreateFlags::CUBE_COMPATIBLE), "Cube views need an image created with ImageCreateFlags::CUBE_COMPATIBLE");
+                assert!(layer_count.is_multiple_of(6), "Cube views need a multiple of 6 array layers");
+            }
+        }
+
+        match image_view_description.view_type {
+            ImageViewType::Type1D | ImageViewType::Type2D | ImageViewType::Type3D => assert!(layer_count == 1, "Non array views must have a single layer"),
+            ImageViewType::Cube => assert!(layer_count == 6, "Cube views must have exactly 6 layers"),
+            _ => {}
+        }
+
+        // 2D views of a 3D image index its depth slices as layers
+        let image_layers = if img.image_type == vk::ImageType::TYPE_3D && matches!(image_view_description.view_type, ImageViewType::Type2D | ImageViewType::Type2DArray) {
+            img.extent.depth
+        } else {
+            img.array_layers
+        };
+
+        assert!(subresources.base_array_layer + layer_count <= image_layers, "Image view layers are out of range of the image");
+        assert!(subresources.mip_level + subresources.level_count <= img.mip_levels, "Image view mip levels are out of range of the image");
+
+        let format = match image_view_description.format {
+            Some(format) => format.to_vk_format(),
+            None => img.format,
+        };
+
+        let mut image_view_create_info = vk::ImageViewCreateInfo::default()
             .image(img.handle)
             .view_type(image_view_description.view_type.to_vk_type())
-            .format(img.format)
-            .components(vk::ComponentMapping {
-                r: vk::ComponentSwizzle::IDENTITY,
-                g: vk::ComponentSwizzle::IDENTITY,
-                b: vk::ComponentSwizzle::IDENTITY,
-                a: vk::ComponentSwizzle::IDENTITY,
-            })
+            .format(format)
+            .components(image_view_description.components.to_vk())
             .subresource_range(image_view_description.subresources.to_vk_subresource_range());
 
+        // A reinterpreting view only gets the usages its own format supports, the image may have more through EXTENDED_USAGE
+        let mut view_usage_info = vk::ImageViewUsageCreateInfo::default();
+        if format != img.format {
+            assert!(img.flags.contains(vk::ImageCreateFlags::MUTABLE_FORMAT), "Image views with a different format need an image created with ImageCreateFlags::MUTABLE_FORMAT");
+
+            let image_format = Format::from_vk_format(img.format).expect("Image format cant be reinterpreted by views");
+            assert!(image_format.is_view_compatible(image_view_description.format.unwrap()), "Image view format is not in the same compatibility class as the image format");
+
+            view_usage_info.usage = img.usage & self.get_format_usages(format);
+            assert!(!view_usage_info.usage.is_empty(), "None of the image usages are supported by the image view format");
+            image_view_create_info = image_view_create_info.push_next(&mut view_usage_info);
+        }
+
         let image_view = unsafe { self.handle.create_image_view(&image_view_create_info, None).expect("Failed to create Image view") };
 
-        let id = unsafe { (&mut *self.image_view_pool.get()).add(ImageViewSlot { handle: image_view }) };
+        let id = unsafe { (&mut *self.image_view_pool.get()).add(ImageViewSlot { handle: image_view, samples: img.samples }) };
 
         return ImageViewId { id: id };
     }
@@ -488,8 +961,32 @@ impl InnerDevice {
 
 // Sampler //
 impl InnerDevice {
+    /// Returns the cached sampler for an identical description, or creates a new one
     pub(crate) fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId {
-        let create_info = vk::SamplerCreateInfo::default()
+        let key = sampler_key(sampler_desc);
+        let cache = unsafe { &mut *self.sampler_cache.get() };
+
+        if let Some(cached) = cache.entries.get_mut(&key) {
+            cached.ref_count += 1;
+            return SamplerId { id: cached.id };
+        }
+
+        if let Some(max_anisotropy) = sampler_desc.max_anisotropy {
+            let limit = self.physical_device.properties.limits.max_sampler_anisotropy;
+            assert!(max_anisotropy <= limit, "Sampler max_anisotropy {} is above the device limit of {}", max_anisotropy, limit);
+        }
+
+        let custom_border_color = sampler_desc.border_color.custom_color();
+        if custom_border_color.is_some() {
+            assert!(self.desc.custom_border_colors, "Custom border colors need DeviceDescription::custom_border_colors");
+            assert!(self.physical_device.custom_border_color_without_format, "Device doesnt support custom border colors without a format");
+        }
+
+        if !matches!(sampler_desc.reduction_mode, SamplerReductionMode::WeightedAverage) {
+            assert!(self.desc.sampler_filter_minmax, "Min and Max reduction modes need DeviceDescription::sampler_filter_minmax");
+        }
+
+        let mut create_info = vk::SamplerCreateInfo::default()
             .mag_filter(sampler_desc.mag_filter.to_vk())
             .min_filter(sampler_desc.min_filter.to_vk())
             .mipmap_mode(sampler_desc.mipmap_mode.to_vk())
@@ -506,14 +1003,74 @@ impl InnerDevice {
             .border_color(sampler_desc.border_color.to_vk())
             .unnormalized_coordinates(sampler_desc.unnormalized_coordinates);
 
+        let mut reduction_info = vk::SamplerReductionModeCreateInfo::default().reduction_mode(sampler_desc.reduction_mode.to_vk());
+        if self.desc.sampler_filter_minmax {
+            create_info = create_info.push_next(&mut reduction_info);
+        }
+
+        let mut border_color_info = vk::SamplerCustomBorderColorCreateInfoEXT::default();
+        if let Some(color) = custom_border_color {
+            border_color_info = border_color_info.custom_border_color(color).format(vk::Format::UNDEFINED);
+            create_info = create_info.push_next(&mut border_color_info);
+        }
+
         let sampler = unsafe { self.handle.create_sampler(&create_info, None).expect("Failed to create sampler") };
 
-        let id = unsafe { (&mut *self.sampler_pool.get()).add(SamplerSlot { handle: sampler }) };
+        let id = unsafe { (&mut *self.sampler_pool.get()).add(SamplerSlot { handle: sampler, key: key }) };
+
+        cache.entries.insert(
+            key,
+            CachedSampler {
+                id: id,
+                ref_count: 1,
+                bindless_index: None,
+            },
+        );
 
         return SamplerId { id: id };
     }
 
+    /// Same as create_sampler, but also writes the sampler to a bindless slot the first time it is seen
+    pub(crate) fn create_bindless_sampler(&self, sampler_desc: &SamplerDescription) -> BindlessSampler {
+        let sampler = self.create_sampler(sampler_desc);
+        let key = sampler_key(sampler_desc);
+
+        let cache = unsafe { &mut *self.sampler_cache.get() };
+        let cached = cache.entries.get_mut(&key).unwrap();
+
+        let index = match cached.bindless_index {
+            Some(index) => index,
+            None => {
+                let index = cache.free_indices.pop().expect("Ran out of bindless sampler slots");
+                cached.bindless_index = Some(index);
+
+                let slot = unsafe { (&*self.sampler_pool.get()).get_ref(sampler.id) };
+                self.bindless_descriptors.write_sampler(&self.handle, slot.handle, index);
+
+                index
+            }
+        };
+
+        return BindlessSampler { sampler, index };
+    }
+
+    /// Drops one reference, the vulkan sampler is destroyed once nothing uses it anymore
     pub(crate) fn destroy_sampler(&self, sampler_id: SamplerId) {
+        let key = unsafe { (&*self.sampler_pool.get()).get_ref(sampler_id.id).key };
+        let cache = unsafe { &mut *self.sampler_cache.get() };
+
+        let cached = cache.entries.get_mut(&key).expect("Tried to destroy a sampler that doesnt exist");
+        cached.ref_count -= 1;
+
+        if cached.ref_count > 0 {
+            return;
+        }
+
+        if let Some(index) = cached.bindless_index {
+            cache.free_indices.push(index);
+        }
+        cache.entries.remove(&key);
+
         let sampler = unsafe { (&mut *self.sampler_pool.get()).delete(sampler_id.id) };
 
         unsafe {
@@ -522,6 +1079,35 @@ impl InnerDevice {
     }
 }
 
+fn sampler_key(desc: &SamplerDescription) -> SamplerKey {
+    let (border_tag, border_values) = match desc.border_color {
+        BorderColor::FloatCustom(c) => (u32::MAX - 1, c.map(f32::to_bits)),
+        BorderColor::IntCustom(c) => (u32::MAX, c.map(|v| v as u32)),
+        other => (other.to_vk().as_raw() as u32, [0; 4]),
+    };
+
+    return [
+        desc.mag_filter.to_vk().as_raw() as u32,
+        desc.min_filter.to_vk().as_raw() as u32,
+        desc.mipmap_mode.to_vk().as_raw() as u32,
+        desc.address_mode_u.to_vk().as_raw() as u32,
+        desc.address_mode_v.to_vk().as_raw() as u32,
+        desc.address_mode_w.to_vk().as_raw() as u32,
+        desc.mip_lod_bias.to_bits(),
+        desc.max_anisotropy.map_or(u32::MAX, f32::to_bits),
+        desc.compare_op.map_or(u32::MAX, |c| c.to_vk().as_raw() as u32),
+        desc.min_lod.to_bits(),
+        desc.max_lod.to_bits(),
+        desc.unnormalized_coordinates as u32,
+        desc.reduction_mode.to_vk().as_raw() as u32,
+        border_tag,
+        border_values[0],
+        border_values[1],
+        border_values[2],
+        border_values[3],
+    ];
+}
+
 // Descriptor //
 impl InnerDevice {
     pub(crate) fn write_buffer(&self, buffer_write_info: &BufferWriteInfo) {
@@ -539,6 +1125,17 @@ impl InnerDevice {
         }
     }
 
+    pub(crate) fn write_buffer_view(&self, buffer_view_write_info: &BufferViewWriteInfo) {
+        let view = unsafe { (&*self.buffer_view_pool.get()).get_ref(buffer_view_write_info.view.id) };
+
+        let descriptor_type = match buffer_view_write_info.texel_buffer_descriptor_type {
+            TexelBufferDescriptorType::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+            TexelBufferDescriptorType::StorageTexelBuffer => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+        };
+
+        self.bindless_descriptors.write_texel_buffer(&self.handle, view.handle, descriptor_type, buffer_view_write_info.index);
+    }
+
     pub(crate) fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo) {
         let sampler = unsafe { (&mut *self.sampler_pool.get()).get_ref(sampler_write_info.sampler.id) };
 
@@ -549,12 +1146,10 @@ impl InnerDevice {
 //// Command buffers ////
 impl InnerDevice {
     pub(crate) fn create_cmd_recorder_data(&self, queue_type: QueueType) -> vk::CommandPool {
-        let cmd_pool_info = vk::CommandPoolCreateInfo::default().flags(vk::CommandPoolCreateFlags::empty()).queue_family_index(match queue_type {
-            QueueType::Compute => self.physical_device.queue_families.compute_family.unwrap(),
-            QueueType::Transfer => self.physical_device.queue_families.transfer_family.unwrap(),
-            QueueType::Graphics => self.physical_device.queue_families.graphics_family.unwrap(),
-            QueueType::None => panic!("Please dont pass a None queue for command pool"),
-        });
+        assert!(queue_type != QueueType::None, "Please dont pass a None queue for command pool");
+
+        // Prologues of tracked command buffers are re-recorded on every submission
+        let cmd_pool_info = vk::CommandPoolCreateInfo::default().flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER).queue_family_index(self.queue_family_index(queue_type));
 
         let pool = unsafe { self.handle.create_command_pool(&cmd_pool_info, None).expect("Failed to create command pool") };
 
@@ -562,6 +1157,86 @@ impl InnerDevice {
     }
 }
 
+//// Queries ////
+impl InnerDevice {
+    pub(crate) fn create_query_pool(&self, kind: QueryKind, count: u32) -> vk::QueryPool {
+        assert!(count > 0, "Query pools cant be empty");
+
+        let mut create_info = vk::QueryPoolCreateInfo::default().query_type(kind.to_vk()).query_count(count);
+
+        if let QueryKind::PipelineStatistics(statistics) = kind {
+            assert!(self.desc.pipeline_statistics_queries, "Pipeline statistics queries need DeviceDescription::pipeline_statistics_queries");
+            create_info = create_info.pipeline_statistics(statistics.to_vk_flag());
+        }
+
+        let pool = unsafe { self.handle.create_query_pool(&create_info, None).expect("Failed to create query pool") };
+
+        // Queries have to be reset before their first use
+        unsafe {
+            self.handle.reset_query_pool(pool, 0, count);
+        }
+
+        return pool;
+    }
+
+    pub(crate) fn destroy_query_pool(&self, pool: vk::QueryPool) {
+        unsafe {
+            self.handle.destroy_query_pool(pool, None);
+        }
+    }
+
+    pub(crate) fn reset_query_pool(&self, pool: &QueryPool, first_query: u32, query_count: u32) {
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        unsafe {
+            self.handle.reset_query_pool(pool.handle, first_query, query_count);
+        }
+    }
+
+    pub(crate) fn get_query_results(&self, pool: &QueryPool, first_query: u32, query_count: u32, wait: bool) -> QueryResults {
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        let values_per_query = pool.kind.values_per_query();
+        let stride = values_per_query as usize + 1;
+        let mut data = vec![0u64; stride * query_count as usize];
+
+        let mut flags = vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY;
+        if wait {
+            flags |= vk::QueryResultFlags::WAIT;
+        }
+
+        // Every query is stride u64s wide, ash reads the stride from the element type so go through the raw call
+        let result = unsafe {
+            (self.handle.fp_v1_0().get_query_pool_results)(
+                self.handle.handle(),
+                pool.handle,
+                first_query,
+                query_count,
+                size_of_val(data.as_slice()),
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+                (stride * size_of::<u64>()) as vk::DeviceSize,
+                flags,
+            )
+        };
+
+        assert!(result == vk::Result::SUCCESS || result == vk::Result::NOT_READY, "Failed to get query results: {:?}", result);
+
+        let mut values = Vec::with_capacity(values_per_query as usize * query_count as usize);
+        let mut available = Vec::with_capacity(query_count as usize);
+
+        for query in data.chunks_exact(stride) {
+            values.extend_from_slice(&query[..stride - 1]);
+            available.push(query[stride - 1] != 0);
+        }
+
+        return QueryResults {
+            values_per_query: values_per_query,
+            values: values,
+            available: available,
+        };
+    }
+}
+
 //// Sync ////
 impl InnerDevice {
     pub(crate) fn create_fence(&self, signaled: bool) -> vk::Fence {
@@ -627,15 +1302,18 @@ impl InnerDevice {
 
         let cmd_type = submit_info.command_buffers[0].queue_type;
 
-        let cmd_infos: Vec<vk::CommandBufferSubmitInfo> = submit_info
-            .command_buffers
-            .iter()
-            .map(|cb| {
-                assert!(cb.queue_type == cmd_type);
+        let mut cmd_infos: Vec<vk::CommandBufferSubmitInfo> = Vec::with_capacity(submit_info.command_buffers.len());
 
-                vk::CommandBufferSubmitInfo::default().command_buffer(cb.handle).device_mask(0)
-            })
-            .collect();
+        for cb in submit_info.command_buffers {
+            assert!(cb.queue_type == cmd_type);
+
+            // Tracked command buffers get their first barriers from the state left by earlier submissions
+            if cb.prologue != vk::CommandBuffer::null() && self.reconcile_recorded_usage(cb.prologue, cmd_type, !submit_info.wait_semaphores.is_empty()) {
+                cmd_infos.push(vk::CommandBufferSubmitInfo::default().command_buffer(cb.prologue).device_mask(0));
+            }
+
+            cmd_infos.push(vk::CommandBufferSubmitInfo::default().command_buffer(cb.handle).device_mask(0));
+        }
 
         let submit = vk::SubmitInfo2::default()
             .wait_semaphore_infos(wait_infos.as_slice())
@@ -683,16 +1361,30 @@ impl InnerDevice {
 impl Drop for InnerDevice {
     fn drop(&mut self) {
         let buffer_pool = unsafe { &mut (*self.buffer_pool.get()) };
+        let buffer_view_pool = unsafe { &mut (*self.buffer_view_pool.get()) };
         let image_pool = unsafe { &mut (*self.image_pool.get()) };
         let image_view_pool = unsafe { &mut (*self.image_view_pool.get()) };
         let sampler_pool = unsafe { &mut (*self.sampler_pool.get()) };
 
+        for page in &mut buffer_view_pool.data {
+            for (res, _) in page {
+                if let Some(slot) = res.take() {
+                    unsafe {
+                        self.handle.destroy_buffer_view(slot.handle, None);
+                    }
+                }
+            }
+        }
+
         for page in &mut buffer_pool.data {
             for (res, _) in page {
                 if let Some(slot) = res.take() {
                     unsafe {
-                        self.allocator.get().as_mut().unwrap().free(slot.allocation).expect("Failed to deallocate buffer");
                         self.handle.destroy_buffer(slot.handle, None);
+                        match slot.external_memory {
+                            Some(external) => self.handle.free_memory(external.memory, None),
+                            None => self.allocator.get().as_mut().unwrap().free(slot.allocation).expect("Failed to deallocate buffer"),
+                        }
                     }
                 }
             }
@@ -702,8 +1394,11 @@ impl Drop for InnerDevice {
             for (res, _) in page {
                 if let Some(slot) = res.take() {
                     unsafe {
-                        self.allocator.get().as_mut().unwrap().free(slot.allocation).expect("Failed to deallocate buffer");
                         self.handle.destroy_image(slot.handle, None);
+                        match slot.external_memory {
+                            Some(external) => self.handle.free_memory(external.memory, None),
+                            None => self.allocator.get().as_mut().unwrap().free(slot.allocation).expect("Failed to deallocate image"),
+                        }
                     }
                 }
             }
@@ -729,6 +1424,8 @@ impl Drop for InnerDevice {
             }
         }
 
+        self.destroy_gpu_profiler();
+
         unsafe {
             self.bindless_descriptors.cleanup(&self.handle, &mut (*self.allocator.get()));
             std::ptr::drop_in_place(&mut self.allocator);
@@ -0,0 +1,178 @@
+use ash::vk;
+
+use crate::{
+    ExternalMemoryHandleType, MemoryType,
+    backend::{device::InnerDevice, gpu_resources::ExternalMemory},
+};
+
+/// Where the memory of an external resource comes from
+pub(crate) enum ExternalAllocation {
+    Export(ExternalMemoryHandleType),
+    /// Ownership of the fd moves to vulkan once the allocation succeeds
+    Import { handle_type: ExternalMemoryHandleType, fd: i32, size: vk::DeviceSize },
+}
+
+impl ExternalAllocation {
+    pub(crate) fn handle_type(&self) -> ExternalMemoryHandleType {
+        return match self {
+            Self::Export(handle_type) => *handle_type,
+            Self::Import { handle_type, .. } => *handle_type,
+        };
+    }
+}
+
+/// Vulkan only takes ownership of an imported fd when the import succeeds, on failure it is still ours to close
+fn close_fd(fd: i32) {
+    #[cfg(unix)]
+    drop(unsafe { <std::os::fd::OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd) });
+    #[cfg(not(unix))]
+    let _ = fd;
+}
+
+/// External memory is always a dedicated allocation of exactly one resource
+pub(crate) enum DedicatedResource {
+    Buffer(vk::Buffer),
+    Image(vk::Image),
+}
+
+// External memory //
+impl InnerDevice {
+    fn find_memory_type(&self, type_bits: u32, memory_type: MemoryType) -> Option<u32> {
+        let properties = unsafe { self.instance.handle.get_physical_device_memory_properties(self.physical_device.handle) };
+
+        let wanted = match memory_type {
+            MemoryType::DeviceLocal => vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            MemoryType::PreferHost => vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            MemoryType::Auto => vk::MemoryPropertyFlags::empty(),
+        };
+
+        let allowed = |i: u32| type_bits & (1 << i) != 0;
+
+        // Fall back to any allowed type, imported dma-bufs often only allow a few
+        return (0..properties.memory_type_count)
+            .find(|&i| allowed(i) && properties.memory_types[i as usize].property_flags.contains(wanted))
+            .or_else(|| (0..properties.memory_type_count).find(|&i| allowed(i)));
+    }
+
+    pub(crate) fn allocate_external_memory(&self, requirements: vk::MemoryRequirements, memory_type: MemoryType, resource: DedicatedResource, external: &ExternalAllocation) -> ExternalMemory {
+        let loader = self.external_memory_fd.as_ref().expect("External memory needs DeviceDescription::external_memory");
+
+        let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::default();
+        let mut flags_info = vk::MemoryAllocateFlagsInfo::default();
+
+        match resource {
+            DedicatedResource::Buffer(buffer) => {
+                dedicated_info.buffer = buffer;
+                // Every nexion buffer has a device address
+                flags_info.flags = vk::MemoryAllocateFlags::DEVICE_ADDRESS;
+            }
+            DedicatedResource::Image(image) => dedicated_info.image = image,
+        }
+
+        let mut export_info = vk::ExportMemoryAllocateInfo::default();
+        let mut import_info = vk::ImportMemoryFdInfoKHR::default();
+
+        let mut allocate_info = vk::MemoryAllocateInfo::default().push_next(&mut dedicated_info).push_next(&mut flags_info);
+
+        match external {
+            ExternalAllocation::Export(handle_type) => {
+                export_info.handle_types = handle_type.to_vk();
+
+                allocate_info = allocate_info
+                    .allocation_size(requirements.size)
+                    .memory_type_index(self.find_memory_type(requirements.memory_type_bits, memory_type).expect("No memory type can hold this external allocation"))
+                    .push_next(&mut export_info);
+            }
+            ExternalAllocation::Import { handle_type, fd, size } => {
+                let handle_type = handle_type.to_vk();
+
+                // The fd is owned from here on, close it before any panic
+                if *size < requirements.size {
+                    close_fd(*fd);
+                    panic!("Imported memory is smaller than the resource it backs");
+                }
+
+                // Opaque fds carry no type information, dma-bufs can be queried for the types they allow
+                let mut type_bits = requirements.memory_type_bits;
+                if handle_type == vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT {
+                    let mut fd_properties = vk::MemoryFdPropertiesKHR::default();
+                    if let Err(error) = unsafe { loader.get_memory_fd_properties(handle_type, *fd, &mut fd_properties) } {
+                        close_fd(*fd);
+                        panic!("Failed to query dma-buf properties: {:?}", error);
+                    }
+                    type_bits &= fd_properties.memory_type_bits;
+                }
+
+                let Some(type_index) = self.find_memory_type(type_bits, memory_type) else {
+                    close_fd(*fd);
+                    panic!("No memory type can hold this external allocation");
+                };
+
+                import_info.handle_type = handle_type;
+                import_info.fd = *fd;
+
+                allocate_info = allocate_info.allocation_size(*size).memory_type_index(type_index).push_next(&mut import_info);
+            }
+        }
+
+        let memory = match unsafe { self.handle.allocate_memory(&allocate_info, None) } {
+            Ok(memory) => memory,
+            Err(error) => {
+                if let ExternalAllocation::Import { fd, .. } = external {
+                    close_fd(*fd);
+                }
+                panic!("Failed to allocate external memory: {:?}", error);
+            }
+        };
+
+        return ExternalMemory {
+            memory: memory,
+            handle_type: external.handle_type(),
+            size: allocate_info.allocation_size,
+        };
+    }
+
+    /// Returns a new fd referencing the memory, the caller owns it
+    pub(crate) fn export_memory_fd(&self, memory: &ExternalMemory) -> i32 {
+        let loader = self.external_memory_fd.as_ref().expect("External memory needs DeviceDescription::external_memory");
+
+        let get_fd_info = vk::MemoryGetFdInfoKHR::default().memory(memory.memory).handle_type(memory.handle_type.to_vk());
+
+        return unsafe { loader.get_memory_fd(&get_fd_info).expect("Failed to export memory fd") };
+    }
+}
+
+// External semaphores //
+impl InnerDevice {
+    pub(crate) fn create_exportable_semaphore(&self, timeline: bool) -> vk::Semaphore {
+        let mut export_info = vk::ExportSemaphoreCreateInfo::default().handle_types(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD);
+        let mut type_info = vk::SemaphoreTypeCreateInfo::default().semaphore_type(vk::SemaphoreType::TIMELINE).initial_value(0);
+
+        let mut create_info = vk::SemaphoreCreateInfo::default().push_next(&mut export_info);
+        if timeline {
+            create_info = create_info.push_next(&mut type_info);
+        }
+
+        return unsafe { self.handle.create_semaphore(&create_info, None).expect("Failed to create exportable semaphore") };
+    }
+
+    pub(crate) fn export_semaphore_fd(&self, semaphore: vk::Semaphore) -> i32 {
+        let loader = self.external_semaphore_fd.as_ref().expect("External semaphores need DeviceDescription::external_memory");
+
+        let get_fd_info = vk::SemaphoreGetFdInfoKHR::default().semaphore(semaphore).handle_type(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD);
+
+        return unsafe { loader.get_semaphore_fd(&get_fd_info).expect("Failed to export semaphore fd") };
+    }
+
+    /// Replaces the payload of the semaphore permanently, ownership of the fd moves to vulkan on success
+    pub(crate) fn import_semaphore_fd(&self, semaphore: vk::Semaphore, fd: i32) {
+        let loader = self.external_semaphore_fd.as_ref().expect("External semaphores need DeviceDescription::external_memory");
+
+        let import_info = vk::ImportSemaphoreFdInfoKHR::default().semaphore(semaphore).handle_type(vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD).fd(fd);
+
+        if let Err(error) = unsafe { loader.import_semaphore_fd(&import_info) } {
+            close_fd(fd);
+            panic!("Failed to import semaphore fd: {:?}", error);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use ash::vk;
+
+use crate::{GpuScope, backend::device::InnerDevice};
+
+/// Timestamps per frame, every scope takes two
+const QUERIES_PER_FRAME: u32 = 1024;
+
+struct ScopeRecord {
+    name: String,
+    parent: Option<usize>,
+    ended: bool,
+}
+
+pub(crate) struct ProfilerFrame {
+    pool: vk::QueryPool,
+    /// Scope `i` writes queries `2 * i` and `2 * i + 1`
+    scopes: Vec<ScopeRecord>,
+}
+
+/// Scope opened by a recorder, remembers its frame so it ends there even if the profiler moved on
+#[derive(Clone, Copy)]
+pub(crate) struct OpenGpuScope {
+    frame: usize,
+    pub(crate) pool: vk::QueryPool,
+    index: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct GpuProfiler {
+    pub(crate) frames: Vec<ProfilerFrame>,
+    current: usize,
+    results: Vec<GpuScope>,
+}
+
+// Gpu profiler //
+impl InnerDevice {
+    /// Reads back the scopes recorded the last time `frame` was in flight and starts recording into it again.
+    /// The GPU has to be done with that frame.
+    pub(crate) fn begin_profiler_frame(&self, frame: usize) {
+        let mut profiler = self.gpu_profiler.lock().expect("Failed to lock the gpu profiler");
+        let profiler = &mut *profiler;
+
+        while profiler.frames.len() <= frame {
+            profiler.frames.push(self.create_profiler_frame());
+        }
+
+        let finished = &mut profiler.frames[frame];
+
+        if !finished.scopes.is_empty() {
+            let mut timestamps = vec![0u64; finished.scopes.len() * 2];
+
+            // Not ready means some command buffer with scopes never got submitted, the frame is dropped then
+            let read = unsafe { self.handle.get_query_pool_results(finished.pool, 0, &mut timestamps, vk::QueryResultFlags::TYPE_64) };
+
+            if read.is_ok() {
+                profiler.results = self.build_scope_tree(&finished.scopes, &timestamps);
+            }
+
+            unsafe {
+                self.handle.reset_query_pool(finished.pool, 0, finished.scopes.len() as u32 * 2);
+            }
+            finished.scopes.clear();
+        }
+
+        profiler.current = frame;
+    }
+
+    /// Returns the scope and the query its start timestamp goes to
+    pub(crate) fn begin_gpu_scope(&self, name: &str, parent: Option<OpenGpuScope>) -> (OpenGpuScope, u32) {
+        let mut profiler = self.gpu_profiler.lock().expect("Failed to lock the gpu profiler");
+        let profiler = &mut *profiler;
+
+        if profiler.frames.is_empty() {
+            profiler.frames.push(self.create_profiler_frame());
+        }
+
+        let current = profiler.current;
+        let frame = &mut profiler.frames[current];
+        let index = frame.scopes.len();
+
+        assert!((index as u32 + 1) * 2 <= QUERIES_PER_FRAME, "Too many gpu scopes in one frame");
+
+        // A parent opened before the frame changed lives in another query pool
+        frame.scopes.push(ScopeRecord {
+            name: name.to_string(),
+            parent: parent.filter(|parent| parent.frame == current).map(|parent| parent.index),
+            ended: false,
+        });
+
+        let scope = OpenGpuScope { frame: current, pool: frame.pool, index: index };
+        return (scope, index as u32 * 2);
+    }
+
+    /// Returns the query the end timestamp of `scope` goes to
+    pub(crate) fn end_gpu_scope(&self, scope: OpenGpuScope) -> u32 {
+        let mut profiler = self.gpu_profiler.lock().expect("Failed to lock the gpu profiler");
+
+        // The frame may have been read back already, the scope is dropped from the results then
+        if let Some(record) = profiler.frames[scope.frame].scopes.get_mut(scope.index) {
+            record.ended = true;
+        }
+
+        return scope.index as u32 * 2 + 1;
+    }
+
+    pub(crate) fn gpu_profile_results(&self) -> Vec<GpuScope> {
+        let profiler = self.gpu_profiler.lock().expect("Failed to lock the gpu profiler");
+        return profiler.results.clone();
+    }
+
+    pub(crate) fn destroy_gpu_profiler(&self) {
+        let mut profiler = self.gpu_profiler.lock().expect("Failed to lock the gpu profiler");
+        let profiler = &mut *profiler;
+
+        for frame in profiler.frames.drain(..) {
+            unsafe {
+                self.handle.destroy_query_pool(frame.pool, None);
+            }
+        }
+    }
+
+    fn create_profiler_frame(&self) -> ProfilerFrame {
+        assert!(self.physical_device.properties.limits.timestamp_compute_and_graphics == vk::TRUE, "Device doesnt support timestamps on graphics and compute queues");
+
+        let create_info = vk::QueryPoolCreateInfo::default().query_type(vk::QueryType::TIMESTAMP).query_count(QUERIES_PER_FRAME);
+
+        let pool = unsafe { self.handle.create_query_pool(&create_info, None).expect("Failed to create timestamp query pool") };
+
+        unsafe {
+            self.handle.reset_query_pool(pool, 0, QUERIES_PER_FRAME);
+        }
+
+        return ProfilerFrame { pool: pool, scopes: Vec::new() };
+    }
+
+    fn build_scope_tree(&self, scopes: &[ScopeRecord], timestamps: &[u64]) -> Vec<GpuScope> {
+        let period = self.physical_device.properties.limits.timestamp_period as f64;
+        let to_ms = |ticks: u64| ticks as f64 * period / 1_000_000.0;
+
+        let frame_start = scopes.iter().enumerate().filter(|(_, scope)| scope.ended).map(|(i, _)| timestamps[i * 2]).min().unwrap_or(0);
+
+        let mut nodes: Vec<Option<GpuScope>> = scopes
+            .iter()
+            .enumerate()
+            .map(|(i, scope)| {
+                scope.ended.then(|| GpuScope {
+                    name: scope.name.clone(),
+                    start_ms: to_ms(timestamps[i * 2].saturating_sub(frame_start)),
+                    duration_ms: to_ms(timestamps[i * 2 + 1].saturating_sub(timestamps[i * 2])),
+                    children: Vec::new(),
+                })
+            })
+            .collect();
+
+        // Children always come after their parent, so attaching back to front moves finished subtrees up
+        let mut roots = Vec::new();
+
+        for i in (0..scopes.len()).rev() {
+            let Some(node) = nodes[i].take() else {
+                continue;
+            };
+
+            match scopes[i].parent.and_then(|parent| nodes[parent].as_mut()) {
+                Some(parent) => parent.children.insert(0, node),
+                None => roots.insert(0, node),
+            }
+        }
+
+        return roots;
+    }
+}
@@ -1,28 +1,75 @@
-use std::cell::UnsafeCell;
+use std::{cell::UnsafeCell, collections::HashMap};
 
 use ash::vk;
+
+use crate::ExternalMemoryHandleType;
 use gpu_allocator::vulkan::*;
 
 pub(crate) struct BufferSlot {
     pub(crate) handle: vk::Buffer,
     pub(crate) address: vk::DeviceAddress,
     pub(crate) allocation: Allocation,
+    pub(crate) size: vk::DeviceSize,
+    pub(crate) usage: vk::BufferUsageFlags,
+    pub(crate) sharing_mode: vk::SharingMode,
+    // Exported or imported memory, owned by the slot instead of gpu-allocator
+    pub(crate) external_memory: Option<ExternalMemory>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ExternalMemory {
+    pub(crate) memory: vk::DeviceMemory,
+    pub(crate) handle_type: ExternalMemoryHandleType,
+    pub(crate) size: vk::DeviceSize,
+}
+
+#[derive(Clone)]
+pub(crate) struct BufferViewSlot {
+    pub(crate) handle: vk::BufferView,
 }
 
 pub(crate) struct ImageSlot {
     pub(crate) handle: vk::Image,
     pub(crate) allocation: Allocation,
     pub(crate) format: vk::Format,
+    pub(crate) extent: vk::Extent3D,
+    pub(crate) mip_levels: u32,
+    pub(crate) array_layers: u32,
+    pub(crate) usage: vk::ImageUsageFlags,
+    pub(crate) flags: vk::ImageCreateFlags,
+    pub(crate) image_type: vk::ImageType,
+    pub(crate) tiling: vk::ImageTiling,
+    pub(crate) samples: vk::SampleCountFlags,
+    pub(crate) sharing_mode: vk::SharingMode,
+    pub(crate) external_memory: Option<ExternalMemory>,
 }
 
 #[derive(Clone)]
 pub(crate) struct ImageViewSlot {
     pub(crate) handle: vk::ImageView,
+    // Of the viewed image, checked against pipelines when rendering
+    pub(crate) samples: vk::SampleCountFlags,
 }
 
 #[derive(Clone)]
 pub(crate) struct SamplerSlot {
     pub(crate) handle: vk::Sampler,
+    pub(crate) key: SamplerKey,
+}
+
+/// Every field of a SamplerDescription as raw bits, floats included, so it can be hashed
+pub(crate) type SamplerKey = [u32; 18];
+
+pub(crate) struct CachedSampler {
+    pub(crate) id: u64,
+    pub(crate) ref_count: u32,
+    pub(crate) bindless_index: Option<u32>,
+}
+
+pub(crate) struct SamplerCache {
+    pub(crate) entries: HashMap<SamplerKey, CachedSampler>,
+    // Popped from the back, so indices are handed out from the top of the sampler array down
+    pub(crate) free_indices: Vec<u32>,
 }
 
 const MASK: u64 = 0xFFFF;
@@ -126,13 +173,31 @@ impl<Resource> ResourcePool<Resource> {
             }
         }
     }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn get_mut(&mut self, id: u64) -> &mut Resource {
+        let (page, index, version) = decode_as_usize(id);
+
+        let (res_opt, res_version) = &mut self.data[page][index];
+
+        match res_opt {
+            Some(res) if *res_version == version => {
+                return res;
+            }
+            _ => {
+                panic!("Attempted acess with invalid ID")
+            }
+        }
+    }
 }
 
-/// Provides 4 resource types
+/// Provides 6 resource types
 /// Device Address Buffer        -> binding 0
 /// Sampled Image                -> binding 1
 /// Storage image                -> binding 2
 /// Sampler                      -> binding 3
+/// Uniform texel buffer         -> binding 4
+/// Storage texel buffer         -> binding 5
 pub(crate) struct GpuBindlessDescriptorPool {
     pub(crate) pool: vk::DescriptorPool,
     pub(crate) set: vk::DescriptorSet,
@@ -144,7 +209,7 @@ pub(crate) struct GpuBindlessDescriptorPool {
 }
 
 impl GpuBindlessDescriptorPool {
-    pub(crate) fn new(device: &ash::Device, buffer: BufferSlot, max_storage_images: u32, max_sampled_images: u32, max_samplers: u32) -> GpuBindlessDescriptorPool {
+    pub(crate) fn new(device: &ash::Device, buffer: BufferSlot, max_storage_images: u32, max_sampled_images: u32, max_samplers: u32, max_texel_buffers: u32) -> GpuBindlessDescriptorPool {
         let pool_sizes = [
             vk::DescriptorPoolSize {
                 ty: vk::DescriptorType::STORAGE_BUFFER,
@@ -162,6 +227,14 @@ impl GpuBindlessDescriptorPool {
                 ty: vk::DescriptorType::SAMPLER,
                 descriptor_count: max_samplers,
             },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                descriptor_count: max_texel_buffers,
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                descriptor_count: max_texel_buffers,
+            },
         ];
 
         let pool_create_info = vk::DescriptorPoolCreateInfo::default()
@@ -192,12 +265,25 @@ impl GpuBindlessDescriptorPool {
                 .descriptor_type(vk::DescriptorType::SAMPLER)
                 .descriptor_count(max_samplers)
                 .stage_flags(vk::ShaderStageFlags::ALL),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(4)
+                .descriptor_type(vk::DescriptorType::UNIFORM_TEXEL_BUFFER)
+                .descriptor_count(max_texel_buffers)
+                .stage_flags(vk::ShaderStageFlags::ALL),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(5)
+                .descriptor_type(vk::DescriptorType::STORAGE_TEXEL_BUFFER)
+                .descriptor_count(max_texel_buffers)
+                .stage_flags(vk::ShaderStageFlags::ALL),
         ];
 
         let binding_flags = [
             vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
             vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
             vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+            // Only the last binding can have a variable count
             vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT,
         ];
 
@@ -210,7 +296,7 @@ impl GpuBindlessDescriptorPool {
 
         let bindless_set_layout = unsafe { device.create_descriptor_set_layout(&layout_info, None).expect("Failed to create bindless descriptor set layout") };
 
-        let variable_counts = [max_samplers];
+        let variable_counts = [max_texel_buffers];
         let mut variable_count_info = vk::DescriptorSetVariableDescriptorCountAllocateInfo::default().descriptor_counts(&variable_counts);
 
         let alloc_info = vk::DescriptorSetAllocateInfo::default()
@@ -321,6 +407,24 @@ impl GpuBindlessDescriptorPool {
         }
     }
 
+    pub(crate) fn write_texel_buffer(&self, device: &ash::Device, buffer_view: vk::BufferView, descriptor_type: vk::DescriptorType, index: u32) {
+        let texel_buffer_views = [buffer_view];
+
+        let write_info = [vk::WriteDescriptorSet::default()
+            .texel_buffer_view(&texel_buffer_views)
+            .dst_set(self.set)
+            .dst_binding(if descriptor_type == vk::DescriptorType::UNIFORM_TEXEL_BUFFER { 4 } else { 5 })
+            .dst_array_element(index)
+            .descriptor_count(1)
+            .descriptor_type(descriptor_type)];
+
+        let copy_sets = [];
+
+        unsafe {
+            device.update_descriptor_sets(&write_info, &copy_sets);
+        }
+    }
+
     pub(crate) fn get_addresses(&self) -> &[u8] {
         unsafe {
             let ptr = *(&(*self.pending_buffers.get()).as_ptr()) as *const u8;
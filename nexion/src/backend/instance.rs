@@ -13,7 +13,10 @@ pub(crate) struct InnerInstance {
 
 impl InnerInstance {
     pub(crate) fn new<W: HasDisplayHandle + HasWindowHandle>(window: &W, instance_create_info: &InstanceDescription) -> InnerInstance {
+        #[cfg(feature = "linked")]
         let entry = ash::Entry::linked();
+        #[cfg(not(feature = "linked"))]
+        let entry = unsafe { ash::Entry::load().expect("Failed to load the vulkan library") };
 
         let mut required_extensions = vec![ash::khr::surface::NAME.as_ptr()];
         let supported_exts = unsafe { entry.enumerate_instance_extension_properties(None).unwrap() };
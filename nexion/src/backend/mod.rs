@@ -1,5 +1,10 @@
+pub(crate) mod buffer_suballocator;
 pub(crate) mod device;
+pub(crate) mod external_memory;
+pub(crate) mod gpu_profiler;
 pub(crate) mod gpu_resources;
 pub(crate) mod instance;
 pub(crate) mod pipelines;
+pub(crate) mod recording_validator;
+pub(crate) mod resource_tracker;
 pub(crate) mod swapchain;
@@ -1,5 +1,6 @@
 use ash::vk;
-use std::{cell::UnsafeCell, path::PathBuf};
+use smallvec::SmallVec;
+use std::{cell::UnsafeCell, path::PathBuf, sync::OnceLock};
 
 use crate::{
     Pipeline, PushConstantsDescription,
@@ -7,20 +8,40 @@ use crate::{
 };
 
 
-use crate::{ComputePipelineDescription, GeometryStage, RasterizationPipelineDescription};
+use crate::{ComputePipelineDescription, DynamicStates, GeometryStage, RasterizationPipelineDescription};
 use std::{fs, path::Path, process::Command, sync::Arc};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub(crate) struct PipelineSlot {
     pub(crate) pipeline: vk::Pipeline,
     pub(crate) layout: vk::PipelineLayout,
     pub(crate) bind_point: vk::PipelineBindPoint,
-    pub(crate) push_constants_info: PushConstantsDescription,
+    /// Non empty ranges of the layout, the first one is the pipeline's `push_constants` if that isnt empty
+    pub(crate) push_constant_ranges: SmallVec<[PushConstantsDescription; 2]>,
+    /// Task and mesh stages instead of vertex input
+    pub(crate) mesh_shading: bool,
+    pub(crate) samples: vk::SampleCountFlags,
+    /// Vertex bindings the pipeline reads, for the recording validator
+    pub(crate) vertex_bindings: SmallVec<[u32; 2]>,
+    /// States the setters are allowed to change while the pipeline is bound
+    pub(crate) dynamic_states: DynamicStates,
+}
+
+/// Compute pipeline used internally by nexion, it has its own descriptor set layout
+/// and does not go through the bindless set.
+#[derive(Clone, Copy)]
+pub(crate) struct InternalPipeline {
+    pub(crate) pipeline: vk::Pipeline,
+    pub(crate) layout: vk::PipelineLayout,
+    pub(crate) set_layout: vk::DescriptorSetLayout,
 }
 
+const MIPMAP_SHADER: &str = include_str!("../shaders/mipmap.slang");
+
 pub(crate) struct InnerPipelineManager {
     pub(crate) desc_layout: vk::DescriptorSetLayout,
     pub(crate) pipelines: UnsafeCell<ResourcePool<PipelineSlot>>,
+    pub(crate) mipmap_pipeline: OnceLock<InternalPipeline>,
     pub(crate) device: Arc<InnerDevice>,
 }
 
@@ -36,6 +57,7 @@ impl InnerPipelineManager {
         return InnerPipelineManager {
             desc_layout: device.bindless_descriptors.layout,
             pipelines: UnsafeCell::new(ResourcePool::new()),
+            mipmap_pipeline: OnceLock::new(),
             device: device,
         };
     }
@@ -74,22 +96,63 @@ impl InnerPipelineManager {
     }
 }
 
+// Push constants //
+
+/// Stages of the ranges that push constant bytes `offset..offset + size` land in.
+/// The bytes have to lie fully inside every range they touch and inside at least one.
+#[track_caller]
+pub(crate) fn push_constant_stages(ranges: &[PushConstantsDescription], offset: u32, size: u32) -> vk::ShaderStageFlags {
+    assert!(offset.is_multiple_of(4) && size.is_multiple_of(4), "Push constant offset and size must be multiples of 4");
+
+    let mut stages = vk::ShaderStageFlags::empty();
+
+    for range in ranges {
+        if range.offset < offset + size && offset < range.offset + range.size {
+            assert!(
+                range.offset <= offset && offset + size <= range.offset + range.size,
+                "Push constants {}..{} only partly overlap the pipeline range {}..{}",
+                offset,
+                offset + size,
+                range.offset,
+                range.offset + range.size
+            );
+            stages |= range.stage_flags.to_vk();
+        }
+    }
+
+    assert!(!stages.is_empty(), "Push constants {}..{} are outside every range of the pipeline", offset, offset + size);
+
+    return stages;
+}
+
 //// Pipeline creation ////
 impl InnerPipelineManager {
+    /// Checks the ranges against the device limit and each other, empty ranges are dropped
+    fn push_constant_ranges(&self, primary: &PushConstantsDescription, extra: &[PushConstantsDescription]) -> SmallVec<[PushConstantsDescription; 2]> {
+        let max_size = self.device.physical_device.properties.limits.max_push_constants_size;
+        let ranges: SmallVec<[PushConstantsDescription; 2]> = std::iter::once(primary).chain(extra).filter(|range| range.size > 0).copied().collect();
+
+        let mut used_stages = vk::ShaderStageFlags::empty();
+
+        for range in &ranges {
+            assert!(range.offset.is_multiple_of(4) && range.size.is_multiple_of(4), "Push constant offset and size must be multiples of 4");
+            assert!(range.offset + range.size <= max_size, "Push constants end at byte {} but the device only supports {}", range.offset + range.size, max_size);
+            assert!(!used_stages.intersects(range.stage_flags.to_vk()), "A shader stage can only be part of one push constant range");
+
+            used_stages |= range.stage_flags.to_vk();
+        }
+
+        return ranges;
+    }
+
     pub(crate) fn create_raster_pipeline_data(&self, desc: &RasterizationPipelineDescription) -> Pipeline {
         let entry = std::ffi::CString::new("main").unwrap();
 
         let layouts = [self.desc_layout];
-        let push_ranges = [vk::PushConstantRange::default()
-            .offset(desc.push_constants.offset)
-            .size(desc.push_constants.size)
-            .stage_flags(desc.push_constants.stage_flags.to_vk())];
+        let push_constant_ranges = self.push_constant_ranges(&desc.push_constants, desc.extra_push_constants);
+        let push_ranges: SmallVec<[vk::PushConstantRange; 2]> = push_constant_ranges.iter().map(|range| range.to_vk()).collect();
 
-        let layout_info = if desc.push_constants.size > 0 {
-            vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts).push_constant_ranges(&push_ranges)
-        } else {
-            vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts)
-        };
+        let layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts).push_constant_ranges(&push_ranges);
 
         let pipeline_layout = unsafe { self.device.handle.create_pipeline_layout(&layout_info, None).unwrap() };
 
@@ -143,7 +206,15 @@ impl InnerPipelineManager {
             .depth_bias_enable(false)
             .line_width(1.0);
 
-        let multisampling = vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(vk::SampleCountFlags::TYPE_1).sample_shading_enable(false);
+        assert!(
+            !desc.multisample.sample_shading || self.device.physical_device.features.sample_rate_shading == vk::TRUE,
+            "Sample shading is not supported by this device"
+        );
+        let multisampling = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(desc.multisample.samples.to_vk_flags())
+            .sample_shading_enable(desc.multisample.sample_shading)
+            .min_sample_shading(desc.multisample.min_sample_shading)
+            .alpha_to_coverage_enable(desc.multisample.alpha_to_coverage);
 
         let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
             .depth_test_enable(desc.depth_stencil.depth_test_enable)
@@ -180,7 +251,7 @@ impl InnerPipelineManager {
 
         let color_blend_state = vk::PipelineColorBlendStateCreateInfo::default().logic_op_enable(false).attachments(&arr);
 
-        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_states = desc.dynamic_states.to_vk();
         let dynamic_state = vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
 
         let color_formats = desc.outputs.color.iter().map(|f| f.to_vk_format()).collect::<Vec<vk::Format>>();
@@ -226,7 +297,14 @@ impl InnerPipelineManager {
                 pipeline: pipeline,
                 layout: pipeline_layout,
                 bind_point: vk::PipelineBindPoint::GRAPHICS,
-                push_constants_info: desc.push_constants,
+                push_constant_ranges: push_constant_ranges,
+                mesh_shading: matches!(desc.geometry, GeometryStage::Mesh { .. }),
+                samples: desc.multisample.samples.to_vk_flags(),
+                vertex_bindings: match &desc.geometry {
+                    GeometryStage::Classic { vertex_input, .. } => vertex_input.bindings.iter().map(|binding| binding.binding).collect(),
+                    GeometryStage::Mesh { .. } => SmallVec::new(),
+                },
+                dynamic_states: desc.dynamic_states,
             })
         };
 
@@ -237,16 +315,10 @@ impl InnerPipelineManager {
         let shader_module = self.create_shader_module(compute_pipeline_desc.shader_path);
 
         // pipeline layout
-        let push_constant_ranges = [vk::PushConstantRange::default()
-            .offset(compute_pipeline_desc.push_constants.offset)
-            .size(compute_pipeline_desc.push_constants.size)
-            .stage_flags(compute_pipeline_desc.push_constants.stage_flags.to_vk())];
+        let push_constant_ranges = self.push_constant_ranges(&compute_pipeline_desc.push_constants, compute_pipeline_desc.extra_push_constants);
+        let push_ranges: SmallVec<[vk::PushConstantRange; 2]> = push_constant_ranges.iter().map(|range| range.to_vk()).collect();
         let layouts = [self.desc_layout];
-        let layout_info = if compute_pipeline_desc.push_constants.size == 0 {
-            vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts)
-        } else {
-            vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts).push_constant_ranges(&push_constant_ranges)
-        };
+        let layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts).push_constant_ranges(&push_ranges);
 
         let pipeline_layout = unsafe { self.device.handle.create_pipeline_layout(&layout_info, None).expect("Failed to create pipeline layout") };
 
@@ -267,13 +339,67 @@ impl InnerPipelineManager {
                 pipeline: pipeline,
                 layout: pipeline_layout,
                 bind_point: vk::PipelineBindPoint::COMPUTE,
-                push_constants_info: compute_pipeline_desc.push_constants,
+                push_constant_ranges: push_constant_ranges,
+                mesh_shading: false,
+                samples: vk::SampleCountFlags::TYPE_1,
+                vertex_bindings: SmallVec::new(),
+                dynamic_states: DynamicStates::NONE,
             })
         };
 
         return Pipeline::Compute(raw_id);
     }
 
+    /// Lazily builds the compute pipeline used as the mipmap fallback for formats that cant be blitted
+    pub(crate) fn get_mipmap_pipeline(&self) -> InternalPipeline {
+        return *self.mipmap_pipeline.get_or_init(|| self.create_mipmap_pipeline());
+    }
+
+    fn create_mipmap_pipeline(&self) -> InternalPipeline {
+        let shader_path = Path::new(".cache").join("nexion_mipmap.slang");
+        fs::write(&shader_path, MIPMAP_SHADER).expect("Failed to write the mipmap shader to the cache directory");
+        let shader_module = self.create_shader_module(shader_path.to_str().unwrap());
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE),
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(1)
+                .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::COMPUTE),
+        ];
+
+        let set_layout = unsafe {
+            self.device
+                .handle
+                .create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings), None)
+                .expect("Failed to create mipmap descriptor set layout")
+        };
+
+        // src extent + dst extent + nearest
+        let push_constant_ranges = [vk::PushConstantRange::default().offset(0).size(20).stage_flags(vk::ShaderStageFlags::COMPUTE)];
+        let layouts = [set_layout];
+        let layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts).push_constant_ranges(&push_constant_ranges);
+
+        let layout = unsafe { self.device.handle.create_pipeline_layout(&layout_info, None).expect("Failed to create mipmap pipeline layout") };
+
+        let entry_point = std::ffi::CString::new("main").unwrap();
+        let shader_stage_info = vk::PipelineShaderStageCreateInfo::default().stage(vk::ShaderStageFlags::COMPUTE).module(shader_module).name(&entry_point);
+        let pipeline_info = [vk::ComputePipelineCreateInfo::default().layout(layout).stage(shader_stage_info)];
+
+        let pipeline = unsafe { self.device.handle.create_compute_pipelines(vk::PipelineCache::null(), &pipeline_info, None).expect("Failed to create mipmap pipeline") }[0];
+
+        unsafe {
+            self.device.handle.destroy_shader_module(shader_module, None);
+        }
+
+        return InternalPipeline { pipeline, layout, set_layout };
+    }
+
     pub(crate) fn destroy_pipeline(&self, pipeline: Pipeline) {
         let slot = unsafe { (&mut *self.pipelines.get()).delete(pipeline.get_raw()) };
 
@@ -290,10 +416,20 @@ impl Drop for InnerPipelineManager {
 
         for page in pipelines.data.iter() {
             for (res, _) in page {
-                res.map(|slot| unsafe {
-                    self.device.handle.destroy_pipeline_layout(slot.layout, None);
-                    self.device.handle.destroy_pipeline(slot.pipeline, None);
-                });
+                if let Some(slot) = res {
+                    unsafe {
+                        self.device.handle.destroy_pipeline_layout(slot.layout, None);
+                        self.device.handle.destroy_pipeline(slot.pipeline, None);
+                    }
+                }
+            }
+        }
+
+        if let Some(internal) = self.mipmap_pipeline.take() {
+            unsafe {
+                self.device.handle.destroy_pipeline(internal.pipeline, None);
+                self.device.handle.destroy_pipeline_layout(internal.layout, None);
+                self.device.handle.destroy_descriptor_set_layout(internal.set_layout, None);
             }
         }
     }
@@ -0,0 +1,348 @@
+use smallvec::SmallVec;
+use std::{
+    cell::{Cell, RefCell},
+    panic::Location,
+};
+
+use crate::Pipeline;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum RecorderState {
+    #[default]
+    Initial,
+    Recording,
+    Executable,
+}
+
+/// Rust side state machine of a `CommandRecorder`, see `DeviceDescription::validate_recording`.
+/// Every check panics at the location of the recorder call that broke it.
+/// Pipeline state is in cells since binding a pipeline only needs a shared recorder.
+#[derive(Default)]
+pub(crate) struct RecordingValidator {
+    enabled: bool,
+    state: RecorderState,
+    rendering: Cell<bool>,
+    // Secondary command buffers that continue a rendering of the primary one
+    inherited_rendering: bool,
+    graphics_pipeline: Cell<Option<Pipeline>>,
+    compute_pipeline: Cell<Option<Pipeline>>,
+    // Whether the last pipeline bound was a compute one, to point out draws after binding one
+    compute_bound_last: Cell<bool>,
+    // Vertex bindings the bound graphics pipeline reads
+    pipeline_vertex_bindings: RefCell<SmallVec<[u32; 2]>>,
+    vertex_bindings: SmallVec<[u32; 4]>,
+    index_buffer: bool,
+}
+
+impl RecordingValidator {
+    pub(crate) fn new(enabled: bool) -> Self {
+        return Self { enabled: enabled, ..Default::default() };
+    }
+
+    #[track_caller]
+    fn fail(&self, message: &str) -> ! {
+        panic!("Invalid command recording at {}: {}", Location::caller(), message);
+    }
+
+    #[track_caller]
+    pub(crate) fn begin(&mut self, inherited_rendering: bool) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.state == RecorderState::Recording {
+            self.fail("recording began while the recorder was already recording");
+        }
+
+        *self = RecordingValidator {
+            enabled: true,
+            state: RecorderState::Recording,
+            rendering: Cell::new(inherited_rendering),
+            inherited_rendering: inherited_rendering,
+            ..Default::default()
+        };
+    }
+
+    #[track_caller]
+    pub(crate) fn end(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("end_recording");
+
+        if self.rendering.get() && !self.inherited_rendering {
+            self.fail("recording ended inside an open begin_rendering, call end_rendering first");
+        }
+
+        self.state = RecorderState::Executable;
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.state = RecorderState::Initial;
+    }
+
+    #[track_caller]
+    pub(crate) fn command(&self, name: &str) {
+        if self.enabled && self.state != RecorderState::Recording {
+            self.fail(&format!("{} recorded outside of begin_recording and end_recording", name));
+        }
+    }
+
+    /// Transfer and compute work can't be recorded inside a rendering
+    #[track_caller]
+    pub(crate) fn outside_rendering(&self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command(name);
+
+        if self.rendering.get() {
+            self.fail(&format!("{} recorded inside begin_rendering", name));
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn begin_rendering(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("begin_rendering");
+
+        if self.rendering.get() {
+            self.fail("begin_rendering inside another begin_rendering");
+        }
+
+        self.rendering.set(true);
+    }
+
+    #[track_caller]
+    pub(crate) fn end_rendering(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("end_rendering");
+
+        if !self.rendering.get() || self.inherited_rendering {
+            self.fail("end_rendering without a matching begin_rendering");
+        }
+
+        self.rendering.set(false);
+    }
+
+    #[track_caller]
+    pub(crate) fn bind_pipeline(&self, pipeline: Pipeline, vertex_bindings: &[u32]) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("bind_pipeline");
+
+        match pipeline {
+            Pipeline::Rasterization(_) => {
+                self.graphics_pipeline.set(Some(pipeline));
+                *self.pipeline_vertex_bindings.borrow_mut() = vertex_bindings.iter().copied().collect();
+                self.compute_bound_last.set(false);
+            }
+            Pipeline::Compute(_) => {
+                self.compute_pipeline.set(Some(pipeline));
+                self.compute_bound_last.set(true);
+            }
+        }
+    }
+
+    /// Internal compute work like mipmap generation binds its own pipeline
+    pub(crate) fn unbind_compute_pipeline(&self) {
+        self.compute_pipeline.set(None);
+        self.compute_bound_last.set(false);
+    }
+
+    #[track_caller]
+    pub(crate) fn push_constants(&self, pipeline: Pipeline) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("set_push_constants");
+
+        if self.graphics_pipeline.get() != Some(pipeline) && self.compute_pipeline.get() != Some(pipeline) {
+            self.fail(&format!("push constants for {:?} which isnt the bound pipeline", pipeline));
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn bind_vertex_buffers(&mut self, first_binding: u32, count: u32) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("bind_vertex_buffers");
+
+        for binding in first_binding..first_binding + count {
+            if !self.vertex_bindings.contains(&binding) {
+                self.vertex_bindings.push(binding);
+            }
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn bind_index_buffer(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command("bind_index_buffer");
+        self.index_buffer = true;
+    }
+
+    #[track_caller]
+    pub(crate) fn draw(&self, name: &str, indexed: bool) {
+        if !self.enabled {
+            return;
+        }
+
+        self.graphics_work(name);
+
+        if let Some(binding) = self.pipeline_vertex_bindings.borrow().iter().find(|binding| !self.vertex_bindings.contains(binding)) {
+            self.fail(&format!("{} without a vertex buffer at binding {} which the pipeline reads", name, binding));
+        }
+
+        if indexed && !self.index_buffer {
+            self.fail(&format!("{} without a bound index buffer", name));
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn inside_rendering(&self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        self.command(name);
+
+        if !self.rendering.get() {
+            self.fail(&format!("{} outside of begin_rendering", name));
+        }
+    }
+
+    /// Mesh task draws, they dont read vertex or index buffers
+    #[track_caller]
+    pub(crate) fn graphics_work(&self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        self.inside_rendering(name);
+
+        if self.graphics_pipeline.get().is_none() {
+            match self.compute_bound_last.get() {
+                true => self.fail(&format!("{} with only a compute pipeline bound, draws need a rasterization pipeline", name)),
+                false => self.fail(&format!("{} without a bound pipeline", name)),
+            }
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn dispatch(&self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        self.outside_rendering(name);
+
+        if self.compute_pipeline.get().is_none() {
+            self.fail(&format!("{} without a bound compute pipeline", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    fn recording() -> RecordingValidator {
+        let mut validator = RecordingValidator::new(true);
+        validator.begin(false);
+        return validator;
+    }
+
+    /// Runs `record` and returns its panic message, which has to point at `line` of this file
+    fn rejected(line: u32, record: impl FnOnce()) -> String {
+        let payload = catch_unwind(AssertUnwindSafe(record)).expect_err("Recording should have been rejected");
+        let message = payload.downcast_ref::<String>().expect("Validator panics with a formatted message").clone();
+
+        let prefix = format!("Invalid command recording at {}:{}:", file!(), line);
+        assert!(message.starts_with(&prefix), "Expected the panic at {} but got: {}", prefix, message);
+
+        let column = message[prefix.len()..].split(':').next().unwrap();
+        assert!(column.parse::<u32>().is_ok(), "Expected a column after the line in: {}", message);
+
+        return message;
+    }
+
+    #[test]
+    fn draw_without_pipeline() {
+        let validator = recording();
+        validator.begin_rendering();
+
+        let line = line!() + 1;
+        let message = rejected(line, || validator.draw("draw", false));
+        assert!(message.ends_with("draw without a bound pipeline"), "{}", message);
+    }
+
+    #[test]
+    fn end_recording_inside_rendering() {
+        let mut validator = recording();
+        validator.begin_rendering();
+
+        let line = line!() + 1;
+        let message = rejected(line, || validator.end());
+        assert!(message.ends_with("recording ended inside an open begin_rendering, call end_rendering first"), "{}", message);
+    }
+
+    #[test]
+    fn push_constants_for_unbound_pipeline() {
+        let validator = recording();
+        validator.bind_pipeline(Pipeline::Rasterization(0), &[]);
+
+        let line = line!() + 1;
+        let message = rejected(line, || validator.push_constants(Pipeline::Rasterization(1)));
+        assert!(message.ends_with("push constants for Rasterization(1) which isnt the bound pipeline"), "{}", message);
+    }
+
+    #[test]
+    fn draw_after_compute_pipeline() {
+        let validator = recording();
+        validator.bind_pipeline(Pipeline::Compute(0), &[]);
+        validator.begin_rendering();
+
+        let line = line!() + 1;
+        let message = rejected(line, || validator.draw("draw_indexed", true));
+        assert!(message.ends_with("draw_indexed with only a compute pipeline bound, draws need a rasterization pipeline"), "{}", message);
+    }
+
+    #[test]
+    fn valid_recording_passes() {
+        let mut validator = recording();
+        validator.bind_pipeline(Pipeline::Rasterization(0), &[0]);
+        validator.push_constants(Pipeline::Rasterization(0));
+        validator.begin_rendering();
+        validator.bind_vertex_buffers(0, 1);
+        validator.bind_index_buffer();
+        validator.draw("draw_indexed", true);
+        validator.end_rendering();
+        validator.end();
+    }
+
+    #[test]
+    fn disabled_validator_never_panics() {
+        let mut validator = RecordingValidator::new(false);
+        validator.draw("draw", true);
+        validator.begin_rendering();
+        validator.end();
+    }
+}
@@ -0,0 +1,304 @@
+use ash::vk;
+use smallvec::SmallVec;
+use std::collections::HashMap;
+
+use crate::{BufferId, ImageId, QueueType, Usage, backend::device::InnerDevice};
+
+const WRITE_ACCESS: vk::AccessFlags2 = vk::AccessFlags2::from_raw(
+    vk::AccessFlags2::SHADER_WRITE.as_raw()
+        | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw()
+        | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE.as_raw()
+        | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw()
+        | vk::AccessFlags2::TRANSFER_WRITE.as_raw()
+        | vk::AccessFlags2::HOST_WRITE.as_raw()
+        | vk::AccessFlags2::MEMORY_WRITE.as_raw(),
+);
+
+/// Layout and last access of an image subresource or a whole buffer, buffers always stay `UNDEFINED`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct ResourceState {
+    pub(crate) layout: vk::ImageLayout,
+    pub(crate) stages: vk::PipelineStageFlags2,
+    pub(crate) access: vk::AccessFlags2,
+}
+
+impl ResourceState {
+    pub(crate) const UNKNOWN: Self = Self {
+        layout: vk::ImageLayout::UNDEFINED,
+        stages: vk::PipelineStageFlags2::NONE,
+        access: vk::AccessFlags2::NONE,
+    };
+
+    pub(crate) const fn from_usage(usage: Usage, is_image: bool) -> Self {
+        return Self {
+            layout: if is_image { usage.layout() } else { vk::ImageLayout::UNDEFINED },
+            stages: usage.stages(),
+            access: usage.access(),
+        };
+    }
+
+    /// Reads in the same layout can run together, everything else waits on the previous use
+    pub(crate) fn needs_barrier(&self, next: &ResourceState) -> bool {
+        return self.layout != next.layout || self.access.intersects(WRITE_ACCESS) || next.access.intersects(WRITE_ACCESS);
+    }
+
+    /// Only writes have to be made available, earlier reads just need their stages waited on
+    pub(crate) fn src_access(&self) -> vk::AccessFlags2 {
+        return self.access & WRITE_ACCESS;
+    }
+
+    /// Another queue got the resource after waiting on a semaphore, which already made earlier writes visible.
+    /// Only the layout is kept, barriers wait on all commands so they chain with the semaphore wait.
+    pub(crate) const fn after_queue_handover(&self) -> ResourceState {
+        return ResourceState {
+            layout: self.layout,
+            stages: vk::PipelineStageFlags2::ALL_COMMANDS,
+            access: vk::AccessFlags2::NONE,
+        };
+    }
+
+    pub(crate) fn merge_reads(&self, next: &ResourceState) -> ResourceState {
+        return ResourceState {
+            layout: self.layout,
+            stages: self.stages | next.stages,
+            access: self.access | next.access,
+        };
+    }
+}
+
+/// Use of a resource inside one command buffer
+#[derive(Clone, Copy)]
+pub(crate) struct SubresourceUse {
+    /// State the command buffer expects before its first command, reached by the prologue at submission
+    pub(crate) first: ResourceState,
+    pub(crate) current: ResourceState,
+    /// Set once the command buffer itself recorded a barrier, from then on `first` stays fixed
+    pub(crate) settled: bool,
+}
+
+impl SubresourceUse {
+    pub(crate) fn new(state: ResourceState) -> Self {
+        return Self {
+            first: state,
+            current: state,
+            settled: false,
+        };
+    }
+}
+
+pub(crate) struct TrackedImage {
+    pub(crate) handle: vk::Image,
+    pub(crate) aspect: vk::ImageAspectFlags,
+    pub(crate) array_layers: u32,
+    /// Indexed by `mip_level * array_layers + array_layer`
+    pub(crate) subresources: Vec<Option<SubresourceUse>>,
+}
+
+impl TrackedImage {
+    pub(crate) fn subresource_range(&self, index: usize) -> vk::ImageSubresourceRange {
+        return vk::ImageSubresourceRange {
+            aspect_mask: self.aspect,
+            base_mip_level: index as u32 / self.array_layers,
+            level_count: 1,
+            base_array_layer: index as u32 % self.array_layers,
+            layer_count: 1,
+        };
+    }
+}
+
+pub(crate) struct TrackedBuffer {
+    pub(crate) handle: vk::Buffer,
+    pub(crate) usage: SubresourceUse,
+}
+
+/// Everything a command buffer recorded through `use_image` and `use_buffer`
+#[derive(Default)]
+pub(crate) struct RecordedUsage {
+    pub(crate) images: HashMap<ImageId, TrackedImage>,
+    pub(crate) buffers: HashMap<BufferId, TrackedBuffer>,
+}
+
+impl RecordedUsage {
+    pub(crate) fn is_empty(&self) -> bool {
+        return self.images.is_empty() && self.buffers.is_empty();
+    }
+}
+
+/// State of every tracked resource after the last reconciled submission.
+/// Submissions are reconciled in the order they are made, which is the order the gpu runs them in on one queue.
+/// Across queues that only holds when the later submission waits on a semaphore of the earlier one.
+#[derive(Default)]
+pub(crate) struct ResourceStateTracker {
+    pub(crate) images: HashMap<ImageId, Vec<ResourceState>>,
+    pub(crate) buffers: HashMap<BufferId, ResourceState>,
+    /// Queue of the last submission that used a resource
+    pub(crate) image_queues: HashMap<ImageId, QueueType>,
+    pub(crate) buffer_queues: HashMap<BufferId, QueueType>,
+    /// Usage of recorded command buffers keyed by their prologue, kept until the recorder is reset
+    pub(crate) recorded: HashMap<vk::CommandBuffer, RecordedUsage>,
+}
+
+pub(crate) fn format_aspect(format: vk::Format) -> vk::ImageAspectFlags {
+    return match format {
+        vk::Format::D16_UNORM | vk::Format::D32_SFLOAT | vk::Format::X8_D24_UNORM_PACK32 => vk::ImageAspectFlags::DEPTH,
+        vk::Format::D16_UNORM_S8_UINT | vk::Format::D24_UNORM_S8_UINT | vk::Format::D32_SFLOAT_S8_UINT => vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        _ => vk::ImageAspectFlags::COLOR,
+    };
+}
+
+pub(crate) fn image_barrier(image: vk::Image, range: vk::ImageSubresourceRange, old: &ResourceState, new: &ResourceState) -> vk::ImageMemoryBarrier2<'static> {
+    return vk::ImageMemoryBarrier2::default()
+        .src_stage_mask(old.stages)
+        .src_access_mask(old.src_access())
+        .dst_stage_mask(new.stages)
+        .dst_access_mask(new.access)
+        .old_layout(old.layout)
+        .new_layout(new.layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(range);
+}
+
+pub(crate) fn buffer_barrier(buffer: vk::Buffer, old: &ResourceState, new: &ResourceState) -> vk::BufferMemoryBarrier2<'static> {
+    return vk::BufferMemoryBarrier2::default()
+        .src_stage_mask(old.stages)
+        .src_access_mask(old.src_access())
+        .dst_stage_mask(new.stages)
+        .dst_access_mask(new.access)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .buffer(buffer)
+        .offset(0)
+        .size(vk::WHOLE_SIZE);
+}
+
+// Resource state tracking //
+impl InnerDevice {
+    pub(crate) fn register_recorded_usage(&self, prologue: vk::CommandBuffer, usage: RecordedUsage) {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+        tracker.recorded.insert(prologue, usage);
+    }
+
+    pub(crate) fn forget_recorded_usage(&self, prologue: vk::CommandBuffer) {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+        tracker.recorded.remove(&prologue);
+    }
+
+    /// Checks that a resource last used on another queue can be picked up by this submission, returns whether it changes queues
+    fn check_queue_handover(&self, previous: Option<QueueType>, queue: QueueType, waits_on_semaphores: bool, sharing_mode: vk::SharingMode) -> bool {
+        let Some(previous) = previous.filter(|previous| *previous != queue) else {
+            return false;
+        };
+
+        assert!(waits_on_semaphores, "A resource last used on the {:?} queue is used on the {:?} queue, the submission has to wait on a semaphore signaled after that use", previous, queue);
+        assert!(
+            sharing_mode == vk::SharingMode::CONCURRENT || self.queue_family_index(previous) == self.queue_family_index(queue),
+            "Exclusive resources tracked through use_image and use_buffer cant move between queue families, create them with SharingMode::Concurrent"
+        );
+
+        return true;
+    }
+
+    /// Records the barriers that bring every resource from its last submitted state to what the
+    /// command buffer expects, then takes over the states the command buffer leaves behind.
+    /// Tracked command buffers are one time submit, so the prologue is recorded once per recording.
+    pub(crate) fn reconcile_recorded_usage(&self, prologue: vk::CommandBuffer, queue: QueueType, waits_on_semaphores: bool) -> bool {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+
+        let Some(usage) = tracker.recorded.get(&prologue) else {
+            return false;
+        };
+
+        let mut image_barriers = SmallVec::<[vk::ImageMemoryBarrier2; 8]>::new();
+        let mut buffer_barriers = SmallVec::<[vk::BufferMemoryBarrier2; 8]>::new();
+
+        for (id, image) in &usage.images {
+            let sharing_mode = unsafe { (&*self.image_pool.get()).get_ref(id.id).sharing_mode };
+            let handover = self.check_queue_handover(tracker.image_queues.insert(*id, queue), queue, waits_on_semaphores, sharing_mode);
+
+            let committed = tracker.images.entry(*id).or_insert_with(|| vec![ResourceState::UNKNOWN; image.subresources.len()]);
+
+            for (index, subresource) in image.subresources.iter().enumerate() {
+                let Some(subresource) = subresource else {
+                    continue;
+                };
+
+                let old = if handover { committed[index].after_queue_handover() } else { committed[index] };
+
+                if old.needs_barrier(&subresource.first) {
+                    image_barriers.push(image_barrier(image.handle, image.subresource_range(index), &old, &subresource.first));
+                    committed[index] = subresource.current;
+                } else if subresource.settled {
+                    committed[index] = subresource.current;
+                } else {
+                    // Reads joined the ones from earlier submissions, later writes have to wait on both
+                    committed[index] = old.merge_reads(&subresource.current);
+                }
+            }
+        }
+
+        for (id, buffer) in &usage.buffers {
+            let sharing_mode = unsafe { (&*self.buffer_pool.get()).get_ref(id.id).sharing_mode };
+            let handover = self.check_queue_handover(tracker.buffer_queues.insert(*id, queue), queue, waits_on_semaphores, sharing_mode);
+
+            let committed = tracker.buffers.entry(*id).or_insert(ResourceState::UNKNOWN);
+            let old = if handover { committed.after_queue_handover() } else { *committed };
+
+            if old.needs_barrier(&buffer.usage.first) {
+                buffer_barriers.push(buffer_barrier(buffer.handle, &old, &buffer.usage.first));
+                *committed = buffer.usage.current;
+            } else if buffer.usage.settled {
+                *committed = buffer.usage.current;
+            } else {
+                *committed = old.merge_reads(&buffer.usage.current);
+            }
+        }
+
+        if image_barriers.is_empty() && buffer_barriers.is_empty() {
+            return false;
+        }
+
+        let dep_info = vk::DependencyInfo::default().image_memory_barriers(image_barriers.as_slice()).buffer_memory_barriers(buffer_barriers.as_slice());
+
+        unsafe {
+            let begin_info = vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            self.handle.begin_command_buffer(prologue, &begin_info).expect("Failed to begin prologue cmd buffer");
+            self.handle.cmd_pipeline_barrier2(prologue, &dep_info);
+            self.handle.end_command_buffer(prologue).expect("Failed to end prologue cmd buffer");
+        }
+
+        return true;
+    }
+
+    /// Linear images start out `PREINITIALIZED`, the first tracked use transitions from there so host writes are kept
+    pub(crate) fn track_preinitialized_image(&self, id: ImageId) {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+        tracker.images.insert(
+            id,
+            vec![ResourceState {
+                layout: vk::ImageLayout::PREINITIALIZED,
+                ..ResourceState::UNKNOWN
+            }],
+        );
+    }
+
+    pub(crate) fn forget_image_state(&self, id: ImageId) {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+        tracker.images.remove(&id);
+        tracker.image_queues.remove(&id);
+    }
+
+    pub(crate) fn forget_buffer_state(&self, id: BufferId) {
+        let mut tracker = self.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+        tracker.buffers.remove(&id);
+        tracker.buffer_queues.remove(&id);
+    }
+}
@@ -102,6 +102,9 @@ impl InnerSwapchain {
             }
         };
 
+        // Transfer src is only needed for screenshots, so its left out where the surface doesnt allow it
+        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST | (support.capabilities.supported_usage_flags & vk::ImageUsageFlags::TRANSFER_SRC);
+
         let create_info = vk::SwapchainCreateInfoKHR::default()
             .surface(surface.handle)
             .min_image_count(swapchain_description.image_count)
@@ -109,7 +112,7 @@ impl InnerSwapchain {
             .image_color_space(surface_format.color_space)
             .image_extent(extent)
             .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST)
+            .image_usage(image_usage)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .pre_transform(support.capabilities.current_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
@@ -132,6 +135,16 @@ impl InnerSwapchain {
                         handle: image,
                         allocation: Allocation::default(),
                         format: surface_format.format,
+                        extent: vk::Extent3D { width: extent.width, height: extent.height, depth: 1 },
+                        mip_levels: 1,
+                        array_layers: 1,
+                        usage: image_usage,
+                        flags: vk::ImageCreateFlags::empty(),
+                        image_type: vk::ImageType::TYPE_2D,
+                        tiling: vk::ImageTiling::OPTIMAL,
+                        samples: vk::SampleCountFlags::TYPE_1,
+                        sharing_mode: vk::SharingMode::EXCLUSIVE,
+                        external_memory: None,
                     })
                 };
 
@@ -231,6 +244,7 @@ impl InnerSwapchain {
         let (index, _) = unsafe {
             self.device.handle.wait_for_fences(&[fence.handle], true, u64::MAX).expect("Failed to wait for in flight fence");
             self.device.handle.reset_fences(&[fence.handle]).expect("Failed to reset in flight fence");
+            self.device.begin_profiler_frame(frame_timeline);
 
             let acquire_info = vk::AcquireNextImageInfoKHR::default().swapchain(self.handle).timeout(u64::MAX).semaphore(image_semaphore.handle()).device_mask(1);
             self.swapchain_loader.acquire_next_image2(&acquire_info).expect("Failed to acquire next image")
@@ -256,6 +270,13 @@ impl InnerSwapchain {
         };
     }
 
+    /// Image acquired last that hasnt been presented yet
+    pub(crate) fn current_image(&self) -> Option<ImageId> {
+        let index = unsafe { (&*self.curr_img_indeices.get()).back().copied() };
+
+        return index.map(|i| self.images[i as usize]);
+    }
+
     pub(crate) fn present(&self) {
         let index = unsafe {
             match (&mut *self.curr_img_indeices.get()).pop_back() {
@@ -284,6 +305,7 @@ impl Drop for InnerSwapchain {
             unsafe {
                 (&mut *self.device.image_pool.get()).delete(self.images[i].id);
             };
+            self.device.forget_image_state(self.images[i]);
             self.device.destroy_image_view(self.image_views[i]);
             self.device.destroy_semaphore(self.image_semaphores[i]);
             self.device.destroy_semaphore(self.preset_semaphores[i]);
@@ -0,0 +1,273 @@
+use delegate::delegate;
+
+use crate::*;
+
+/// Device calls shared by `Device` and the driver free `MockDevice` of the `mock` feature.
+/// Code written against this trait can be tested without a GPU.
+pub trait DeviceApi {
+    type Recorder: CommandRecorderApi;
+
+    //// Buffers ////
+    fn create_buffer(&self, buffer_desc: &BufferDescription) -> BufferId;
+    fn destroy_buffer(&self, id: BufferId);
+    fn write_data_to_buffer<T: Copy>(&self, buffer_id: BufferId, data: &[T]);
+    fn get_raw_ptr(&self, buffer_id: BufferId) -> *mut u8;
+
+    //// Images ////
+    fn create_image(&self, image_desc: &ImageDescription) -> ImageId;
+    fn destroy_image(&self, image_id: ImageId);
+    fn create_image_view(&self, image_id: ImageId, image_view_desc: &ImageViewDescription) -> ImageViewId;
+    fn destroy_image_view(&self, image_view_id: ImageViewId);
+
+    //// Samplers ////
+    fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId;
+    fn destroy_sampler(&self, sampler_id: SamplerId);
+
+    //// Pipelines ////
+    fn create_rasterization_pipeline(&self, raster_pipeline_desc: &RasterizationPipelineDescription) -> Pipeline;
+    fn create_compute_pipeline(&self, compute_pipeline_desc: &ComputePipelineDescription) -> Pipeline;
+    fn destroy_pipeline(&self, pipeline: Pipeline);
+
+    //// Descriptors ////
+    fn write_buffer(&self, buffer_write_info: &BufferWriteInfo);
+    fn write_image(&self, image_write_info: &ImageWriteInfo);
+    fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo);
+
+    //// Queries ////
+    fn create_query_pool(&self, kind: QueryKind, count: u32) -> QueryPool;
+    fn destroy_query_pool(&self, pool: QueryPool);
+    fn get_query_results(&self, pool: QueryPool, first_query: u32, query_count: u32, wait: bool) -> QueryResults;
+    fn reset_query_pool(&self, pool: QueryPool, first_query: u32, query_count: u32);
+
+    //// Commands ////
+    fn create_command_recorder(&self, queue_type: QueueType) -> Self::Recorder;
+    fn submit(&self, submit_info: &QueueSubmitInfo);
+    fn wait_idle(&self);
+    fn wait_queue(&self, queue_type: QueueType);
+
+    //// Sync ////
+    fn create_fence(&self, signaled: bool) -> Fence;
+    fn wait_fence(&self, fence: Fence);
+    fn reset_fence(&self, fence: Fence);
+    fn destroy_fence(&self, fence: Fence);
+    fn create_binary_semaphore(&self) -> Semaphore;
+    fn create_timeline_semaphore(&self) -> Semaphore;
+    fn destroy_semaphore(&self, semaphore: Semaphore);
+}
+
+/// Recording calls shared by `CommandRecorder` and `MockCommandRecorder`, see `DeviceApi`
+pub trait CommandRecorderApi {
+    fn reset(&mut self);
+    fn begin_recording(&mut self, usage: CommandBufferUsage);
+    fn end_recording(&mut self) -> ExecutableCommandBuffer;
+
+    //// Queries ////
+    fn reset_query_pool(&mut self, pool: QueryPool, first_query: u32, query_count: u32);
+    fn begin_query(&mut self, pool: QueryPool, query: u32, precise: bool);
+    fn end_query(&mut self, pool: QueryPool, query: u32);
+    fn write_timestamp(&mut self, pool: QueryPool, query: u32, stage: PipelineStage);
+
+    //// Rendering ////
+    fn begin_rendering(&mut self, rendering_begin_info: &RenderingBeginInfo);
+    fn end_rendering(&self);
+    fn set_viewport_and_scissor(&self, width: u32, height: u32);
+    fn set_viewport(&self, viewport: &Viewport);
+    fn set_scissor(&self, scissor: &RenderArea);
+
+    //// Dynamic state ////
+    fn set_cull_mode(&self, cull_mode: CullMode);
+    fn set_front_face(&self, front_face: FrontFace);
+    fn set_depth_test_enable(&self, enable: bool);
+    fn set_depth_write_enable(&self, enable: bool);
+    fn set_depth_compare_op(&self, compare_op: CompareOp);
+    fn set_depth_bias_enable(&self, enable: bool);
+    fn set_depth_bias(&self, constant_factor: f32, clamp: f32, slope_factor: f32);
+    fn set_stencil_test_enable(&self, enable: bool);
+    fn set_stencil_op(&self, faces: StencilFaces, fail_op: StencilOp, pass_op: StencilOp, depth_fail_op: StencilOp, compare_op: CompareOp);
+    fn set_stencil_reference(&self, faces: StencilFaces, reference: u32);
+    fn set_stencil_masks(&self, faces: StencilFaces, compare_mask: u32, write_mask: u32);
+    fn set_blend_constants(&self, constants: [f32; 4]);
+    fn set_line_width(&self, width: f32);
+    fn set_primitive_topology(&self, topology: InputTopology);
+
+    //// Pipelines ////
+    fn set_push_constants<T>(&self, push_constants: &T, pipeline: Pipeline);
+    fn push_constants<T>(&self, pipeline: Pipeline, range: &PushConstants<T>, data: &T);
+    fn update_push_constants<T>(&self, pipeline: Pipeline, offset: u32, data: &T);
+    fn bind_pipeline(&self, pipeline: Pipeline);
+    fn bind_vertex_buffer(&mut self, buffer_id: BufferId, offset: u64);
+    fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: &[(BufferId, u64)], strides: Option<&[u64]>);
+    fn bind_index_buffer(&mut self, buffer_id: BufferId, offset: u64, index_type: IndexType);
+
+    //// Draw commands ////
+    fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32);
+    fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32);
+    fn draw_indirect(&mut self, info: &DrawIndirectInfo);
+    fn draw_indexed_indirect(&mut self, info: &DrawIndexedIndirectInfo);
+    fn draw_mesh_tasks(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32);
+    fn dispatch(&self, info: &DispatchInfo);
+    fn dispatch_indirect(&self, info: &DispatchIndirectInfo);
+
+    //// Sync ////
+    fn pipeline_barrier(&mut self, barriers: &[Barrier]);
+    fn use_image(&mut self, image_id: ImageId, usage: Usage);
+    fn use_image_subresources(&mut self, image_id: ImageId, subresources: Option<ImageSubresources>, usage: Usage);
+    fn use_buffer(&mut self, buffer_id: BufferId, usage: Usage);
+    fn flush_barriers(&self);
+
+    //// Transfer commands ////
+    fn copy_buffer(&mut self, buffer_copy_info: &BufferCopyInfo);
+    fn fill_buffer(&mut self, info: &BufferFillInfo);
+    fn update_buffer<T: Copy>(&mut self, info: &BufferUpdateInfo<T>);
+    fn copy_buffer_to_image(&mut self, info: &BufferImageCopyInfo);
+    fn copy_image_to_buffer(&mut self, info: &BufferImageCopyInfo);
+    fn clear_color_image(&mut self, info: &ImageClearInfo);
+}
+
+// The inherent methods take precedence, so these forward to them
+impl DeviceApi for Device {
+    type Recorder = CommandRecorder;
+
+    delegate! {
+        to self {
+            fn create_buffer(&self, buffer_desc: &BufferDescription) -> BufferId;
+            fn destroy_buffer(&self, id: BufferId);
+            fn write_data_to_buffer<T: Copy>(&self, buffer_id: BufferId, data: &[T]);
+            fn get_raw_ptr(&self, buffer_id: BufferId) -> *mut u8;
+            fn create_image(&self, image_desc: &ImageDescription) -> ImageId;
+            fn destroy_image(&self, image_id: ImageId);
+            fn create_image_view(&self, image_id: ImageId, image_view_desc: &ImageViewDescription) -> ImageViewId;
+            fn destroy_image_view(&self, image_view_id: ImageViewId);
+            fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId;
+            fn destroy_sampler(&self, sampler_id: SamplerId);
+            fn create_rasterization_pipeline(&self, raster_pipeline_desc: &RasterizationPipelineDescription) -> Pipeline;
+            fn create_compute_pipeline(&self, compute_pipeline_desc: &ComputePipelineDescription) -> Pipeline;
+            fn destroy_pipeline(&self, pipeline: Pipeline);
+            fn write_buffer(&self, buffer_write_info: &BufferWriteInfo);
+            fn write_image(&self, image_write_info: &ImageWriteInfo);
+            fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo);
+            fn create_query_pool(&self, kind: QueryKind, count: u32) -> QueryPool;
+            fn destroy_query_pool(&self, pool: QueryPool);
+            fn get_query_results(&self, pool: QueryPool, first_query: u32, query_count: u32, wait: bool) -> QueryResults;
+            fn reset_query_pool(&self, pool: QueryPool, first_query: u32, query_count: u32);
+            fn create_command_recorder(&self, queue_type: QueueType) -> CommandRecorder;
+            fn submit(&self, submit_info: &QueueSubmitInfo);
+            fn wait_idle(&self);
+            fn wait_queue(&self, queue_type: QueueType);
+            fn create_fence(&self, signaled: bool) -> Fence;
+            fn wait_fence(&self, fence: Fence);
+            fn reset_fence(&self, fence: Fence);
+            fn destroy_fence(&self, fence: Fence);
+            fn create_binary_semaphore(&self) -> Semaphore;
+            fn create_timeline_semaphore(&self) -> Semaphore;
+            fn destroy_semaphore(&self, semaphore: Semaphore);
+        }
+    }
+}
+
+impl CommandRecorderApi for CommandRecorder {
+    delegate! {
+        to self {
+            fn reset(&mut self);
+            #[track_caller]
+            fn begin_recording(&mut self, usage: CommandBufferUsage);
+            #[track_caller]
+            fn end_recording(&mut self) -> ExecutableCommandBuffer;
+            #[track_caller]
+            fn reset_query_pool(&mut self, pool: QueryPool, first_query: u32, query_count: u32);
+            #[track_caller]
+            fn begin_query(&mut self, pool: QueryPool, query: u32, precise: bool);
+            #[track_caller]
+            fn end_query(&mut self, pool: QueryPool, query: u32);
+            #[track_caller]
+            fn write_timestamp(&mut self, pool: QueryPool, query: u32, stage: PipelineStage);
+            #[track_caller]
+            fn begin_rendering(&mut self, rendering_begin_info: &RenderingBeginInfo);
+            #[track_caller]
+            fn end_rendering(&self);
+            #[track_caller]
+            fn set_viewport_and_scissor(&self, width: u32, height: u32);
+            #[track_caller]
+            fn set_viewport(&self, viewport: &Viewport);
+            #[track_caller]
+            fn set_scissor(&self, scissor: &RenderArea);
+            #[track_caller]
+            fn set_cull_mode(&self, cull_mode: CullMode);
+            #[track_caller]
+            fn set_front_face(&self, front_face: FrontFace);
+            #[track_caller]
+            fn set_depth_test_enable(&self, enable: bool);
+            #[track_caller]
+            fn set_depth_write_enable(&self, enable: bool);
+            #[track_caller]
+            fn set_depth_compare_op(&self, compare_op: CompareOp);
+            #[track_caller]
+            fn set_depth_bias_enable(&self, enable: bool);
+            #[track_caller]
+            fn set_depth_bias(&self, constant_factor: f32, clamp: f32, slope_factor: f32);
+            #[track_caller]
+            fn set_stencil_test_enable(&self, enable: bool);
+            #[track_caller]
+            fn set_stencil_op(&self, faces: StencilFaces, fail_op: StencilOp, pass_op: StencilOp, depth_fail_op: StencilOp, compare_op: CompareOp);
+            #[track_caller]
+            fn set_stencil_reference(&self, faces: StencilFaces, reference: u32);
+            #[track_caller]
+            fn set_stencil_masks(&self, faces: StencilFaces, compare_mask: u32, write_mask: u32);
+            #[track_caller]
+            fn set_blend_constants(&self, constants: [f32; 4]);
+            #[track_caller]
+            fn set_line_width(&self, width: f32);
+            #[track_caller]
+            fn set_primitive_topology(&self, topology: InputTopology);
+            #[track_caller]
+            fn set_push_constants<T>(&self, push_constants: &T, pipeline: Pipeline);
+            #[track_caller]
+            fn push_constants<T>(&self, pipeline: Pipeline, range: &PushConstants<T>, data: &T);
+            #[track_caller]
+            fn update_push_constants<T>(&self, pipeline: Pipeline, offset: u32, data: &T);
+            #[track_caller]
+            fn bind_pipeline(&self, pipeline: Pipeline);
+            #[track_caller]
+            fn bind_vertex_buffer(&mut self, buffer_id: BufferId, offset: u64);
+            #[track_caller]
+            fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: &[(BufferId, u64)], strides: Option<&[u64]>);
+            #[track_caller]
+            fn bind_index_buffer(&mut self, buffer_id: BufferId, offset: u64, index_type: IndexType);
+            #[track_caller]
+            fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32);
+            #[track_caller]
+            fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32);
+            #[track_caller]
+            fn draw_indirect(&mut self, info: &DrawIndirectInfo);
+            #[track_caller]
+            fn draw_indexed_indirect(&mut self, info: &DrawIndexedIndirectInfo);
+            #[track_caller]
+            fn draw_mesh_tasks(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32);
+            #[track_caller]
+            fn dispatch(&self, info: &DispatchInfo);
+            #[track_caller]
+            fn dispatch_indirect(&self, info: &DispatchIndirectInfo);
+            #[track_caller]
+            fn pipeline_barrier(&mut self, barriers: &[Barrier]);
+            #[track_caller]
+            fn use_image(&mut self, image_id: ImageId, usage: Usage);
+            #[track_caller]
+            fn use_image_subresources(&mut self, image_id: ImageId, subresources: Option<ImageSubresources>, usage: Usage);
+            #[track_caller]
+            fn use_buffer(&mut self, buffer_id: BufferId, usage: Usage);
+            fn flush_barriers(&self);
+            #[track_caller]
+            fn copy_buffer(&mut self, buffer_copy_info: &BufferCopyInfo);
+            #[track_caller]
+            fn fill_buffer(&mut self, info: &BufferFillInfo);
+            #[track_caller]
+            fn update_buffer<T: Copy>(&mut self, info: &BufferUpdateInfo<T>);
+            #[track_caller]
+            fn copy_buffer_to_image(&mut self, info: &BufferImageCopyInfo);
+            #[track_caller]
+            fn copy_image_to_buffer(&mut self, info: &BufferImageCopyInfo);
+            #[track_caller]
+            fn clear_color_image(&mut self, info: &ImageClearInfo);
+        }
+    }
+}
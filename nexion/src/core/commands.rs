@@ -1,45 +1,103 @@
 use ash::vk;
 use smallvec::SmallVec;
-use std::sync::Arc;
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
 
 use crate::{
-    backend::{device::InnerDevice, pipelines::InnerPipelineManager},
+    backend::{
+        device::InnerDevice,
+        gpu_profiler::OpenGpuScope,
+        pipelines::{self, InnerPipelineManager},
+        recording_validator::RecordingValidator,
+        resource_tracker::{self, RecordedUsage, ResourceState, SubresourceUse, TrackedBuffer, TrackedImage},
+    },
     *,
 };
 
 /// Not thread safe!!
 /// This is because normal vulkan command pools arent hread safe either
 /// Hence it felt unnecessary to have an inner struct
+/// Every recorder owns its pool though, so recorders can be moved to worker threads and record in parallel
 pub struct CommandRecorder {
     pub(crate) handle: vk::CommandPool,
     pub(crate) commad_buffers: SmallVec<[vk::CommandBuffer; 2]>,
     pub(crate) exec_command_buffers: SmallVec<[vk::CommandBuffer; 2]>,
     pub(crate) current_commad_buffer: vk::CommandBuffer,
     pub(crate) queue_type: QueueType,
+    pub(crate) level: vk::CommandBufferLevel,
     pub(crate) device: Arc<InnerDevice>,
     pub(crate) pipeline_manager: Arc<InnerPipelineManager>,
+
+    // Objects created while recording that must live until the command buffers are done executing
+    pub(crate) transient_views: Vec<vk::ImageView>,
+    pub(crate) transient_descriptor_pools: Vec<vk::DescriptorPool>,
+
+    // Automatic barriers, see `use_image` and `use_buffer`
+    pub(crate) recorded_usage: RecordedUsage,
+    pub(crate) pending_image_barriers: RefCell<SmallVec<[vk::ImageMemoryBarrier2<'static>; 4]>>,
+    pub(crate) pending_buffer_barriers: RefCell<SmallVec<[vk::BufferMemoryBarrier2<'static>; 4]>>,
+    pub(crate) tracked_prologues: SmallVec<[vk::CommandBuffer; 2]>,
+    // Prologues are re-recorded at every submission, so only one time submit recordings can be tracked
+    pub(crate) one_time_submit: bool,
+
+    // Open gpu scopes, innermost last
+    pub(crate) gpu_scopes: SmallVec<[OpenGpuScope; 4]>,
+    // Whether the bound graphics pipeline uses task and mesh shaders
+    pub(crate) mesh_pipeline_bound: Cell<bool>,
+    // Dynamic states of the bound graphics pipeline, checked by the setters
+    pub(crate) bound_dynamic_states: Cell<DynamicStates>,
+    // Whether the last bind_vertex_buffers passed strides, pipelines with dynamic strides need them
+    pub(crate) vertex_strides_bound: Cell<bool>,
+    // Sample counts of the bound graphics pipeline and the current rendering, they have to match
+    pub(crate) bound_pipeline_samples: Cell<Option<vk::SampleCountFlags>>,
+    pub(crate) rendering_samples: Cell<Option<vk::SampleCountFlags>>,
+    pub(crate) validator: RecordingValidator,
 }
 
 impl CommandRecorder {
     pub fn reset(&mut self) {
+        self.validator.reset();
+
         unsafe {
             self.device.handle.reset_command_pool(self.handle, vk::CommandPoolResetFlags::empty()).expect("Failed to reset command pool");
         }
 
         self.commad_buffers.append(&mut self.exec_command_buffers);
+        self.free_transient_objects();
+        self.forget_tracked_prologues();
     }
 
+    #[track_caller]
     pub fn begin_recording(&mut self, usage: CommandBufferUsage) {
+        self.validator.begin(false);
+
+        assert!(self.level == vk::CommandBufferLevel::PRIMARY, "Secondary recorders record with begin_secondary_recording");
+
         let begin_info = vk::CommandBufferBeginInfo::default().flags(usage.to_vk_flags());
 
         self.current_commad_buffer = self.commad_buffers.pop().unwrap_or_else(|| self.new_cmd_buffer());
+        self.one_time_submit = matches!(usage, CommandBufferUsage::OneTimeSubmit);
+        self.mesh_pipeline_bound.set(false);
+        self.bound_dynamic_states.set(DynamicStates::NONE);
+        self.vertex_strides_bound.set(false);
+        self.bound_pipeline_samples.set(None);
+        self.rendering_samples.set(None);
 
         unsafe {
             self.device.handle.begin_command_buffer(self.current_commad_buffer, &begin_info).expect("Failed to begin cmd buffer!!!");
         }
     }
 
+    #[track_caller]
     pub fn end_recording(&mut self) -> ExecutableCommandBuffer {
+        self.validator.end();
+
+        assert!(self.level == vk::CommandBufferLevel::PRIMARY, "Secondary recorders record with end_secondary_recording");
+        assert!(self.gpu_scopes.is_empty(), "Gpu scope left open at the end of recording");
+        self.flush_barriers();
+
         unsafe {
             self.device.handle.end_command_buffer(self.current_commad_buffer).expect("Failed to end cmd buffer!!!");
         }
@@ -48,14 +106,221 @@ impl CommandRecorder {
         self.exec_command_buffers.push(return_buffer);
         self.current_commad_buffer = vk::CommandBuffer::null();
 
+        // Resources used through use_image and use_buffer get their initial barriers at submission
+        let prologue = if self.recorded_usage.is_empty() {
+            vk::CommandBuffer::null()
+        } else {
+            let prologue = self.commad_buffers.pop().unwrap_or_else(|| self.new_cmd_buffer());
+            self.exec_command_buffers.push(prologue);
+            self.tracked_prologues.push(prologue);
+            self.device.register_recorded_usage(prologue, std::mem::take(&mut self.recorded_usage));
+            prologue
+        };
+
         return ExecutableCommandBuffer {
             handle: return_buffer,
+            prologue: prologue,
             queue_type: self.queue_type,
         };
     }
 
+    /// With `rendering` the command buffer can only be executed inside a matching `begin_rendering`
+    /// that uses `RenderingFlags::ContentsSecondaryCommandBuffers`
+    #[track_caller]
+    pub fn begin_secondary_recording(&mut self, usage: CommandBufferUsage, rendering: Option<&RenderingInheritance>) {
+        self.validator.begin(rendering.is_some());
+
+        assert!(self.level == vk::CommandBufferLevel::SECONDARY, "Primary recorders record with begin_recording");
+
+        let color_formats: SmallVec<[vk::Format; 8]> = rendering.map(|r| r.color_formats.iter().map(|format| format.to_vk_format()).collect()).unwrap_or_default();
+
+        let mut rendering_info = vk::CommandBufferInheritanceRenderingInfo::default().color_attachment_formats(&color_formats);
+        let mut flags = usage.to_vk_flags();
+
+        if let Some(rendering) = rendering {
+            rendering_info = rendering_info
+                .depth_attachment_format(rendering.depth_format.map_or(vk::Format::UNDEFINED, |format| format.to_vk_format()))
+                .stencil_attachment_format(rendering.stencil_format.map_or(vk::Format::UNDEFINED, |format| format.to_vk_format()))
+                .rasterization_samples(rendering.samples.to_vk_flags())
+                .view_mask(rendering.view_mask);
+
+            flags |= vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+        }
+
+        let mut inheritance_info = vk::CommandBufferInheritanceInfo::default();
+        if rendering.is_some() {
+            inheritance_info = inheritance_info.push_next(&mut rendering_info);
+        }
+
+        let begin_info = vk::CommandBufferBeginInfo::default().flags(flags).inheritance_info(&inheritance_info);
+
+        self.current_commad_buffer = self.commad_buffers.pop().unwrap_or_else(|| self.new_cmd_buffer());
+        self.mesh_pipeline_bound.set(false);
+        self.bound_dynamic_states.set(DynamicStates::NONE);
+        self.vertex_strides_bound.set(false);
+        self.bound_pipeline_samples.set(None);
+        self.rendering_samples.set(rendering.map(|rendering| rendering.samples.to_vk_flags()));
+
+        unsafe {
+            self.device.handle.begin_command_buffer(self.current_commad_buffer, &begin_info).expect("Failed to begin secondary cmd buffer");
+        }
+    }
+
+    #[track_caller]
+    pub fn end_secondary_recording(&mut self) -> SecondaryCommandBuffer {
+        self.validator.end();
+
+        assert!(self.level == vk::CommandBufferLevel::SECONDARY, "Primary recorders record with end_recording");
+        assert!(self.gpu_scopes.is_empty(), "Gpu scope left open at the end of recording");
+
+        unsafe {
+            self.device.handle.end_command_buffer(self.current_commad_buffer).expect("Failed to end secondary cmd buffer");
+        }
+
+        let return_buffer = self.current_commad_buffer;
+        self.exec_command_buffers.push(return_buffer);
+        self.current_commad_buffer = vk::CommandBuffer::null();
+
+        return SecondaryCommandBuffer {
+            handle: return_buffer,
+            queue_type: self.queue_type,
+        };
+    }
+
+    /// The secondary recorders have to stay alive and unreset until this command buffer is done executing
+    #[track_caller]
+    pub fn execute_commands(&mut self, command_buffers: &[SecondaryCommandBuffer]) {
+        self.validator.command("execute_commands");
+
+        assert!(self.level == vk::CommandBufferLevel::PRIMARY, "Secondary command buffers can only be executed from a primary");
+
+        let family = self.device.queue_family_index(self.queue_type);
+        let handles: SmallVec<[vk::CommandBuffer; 16]> = command_buffers
+            .iter()
+            .map(|command_buffer| {
+                assert!(self.device.queue_family_index(command_buffer.queue_type) == family, "Secondary command buffer was recorded for another queue family");
+                command_buffer.handle
+            })
+            .collect();
+
+        self.flush_barriers();
+
+        unsafe {
+            self.device.handle.cmd_execute_commands(self.current_commad_buffer, &handles);
+        }
+    }
+
+    //// Queries ////
+    /// Queries must be reset before they are used again, with `reset_query_pool` here or on the device
+    #[track_caller]
+    pub fn reset_query_pool(&mut self, pool: QueryPool, first_query: u32, query_count: u32) {
+        self.validator.outside_rendering("reset_query_pool");
+
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        unsafe {
+            self.device.handle.cmd_reset_query_pool(self.current_commad_buffer, pool.handle, first_query, query_count);
+        }
+    }
+
+    /// `precise` only matters for occlusion queries and needs a device that supports it
+    #[track_caller]
+    pub fn begin_query(&mut self, pool: QueryPool, query: u32, precise: bool) {
+        self.validator.command("begin_query");
+
+        assert!(pool.kind != QueryKind::Timestamp, "Timestamp queries are written with write_timestamp");
+        assert!(query < pool.count, "Query index out of bounds");
+
+        let precise = precise && pool.kind == QueryKind::Occlusion;
+        assert!(!precise || self.device.physical_device.features.occlusion_query_precise == vk::TRUE, "Device doesnt support precise occlusion queries");
+
+        let flags = if precise { vk::QueryControlFlags::PRECISE } else { vk::QueryControlFlags::empty() };
+
+        unsafe {
+            self.device.handle.cmd_begin_query(self.current_commad_buffer, pool.handle, query, flags);
+        }
+    }
+
+    #[track_caller]
+    pub fn end_query(&mut self, pool: QueryPool, query: u32) {
+        self.validator.command("end_query");
+
+        unsafe {
+            self.device.handle.cmd_end_query(self.current_commad_buffer, pool.handle, query);
+        }
+    }
+
+    #[track_caller]
+    pub fn write_timestamp(&mut self, pool: QueryPool, query: u32, stage: PipelineStage) {
+        self.validator.command("write_timestamp");
+
+        assert!(pool.kind == QueryKind::Timestamp, "Timestamps need a QueryKind::Timestamp pool");
+        assert!(query < pool.count, "Query index out of bounds");
+
+        unsafe {
+            self.device.handle.cmd_write_timestamp2(self.current_commad_buffer, stage.to_vk(), pool.handle, query);
+        }
+    }
+
+    #[track_caller]
+    pub fn copy_query_results(&mut self, info: &QueryCopyInfo) {
+        self.validator.outside_rendering("copy_query_results");
+
+        assert!(info.first_query + info.query_count <= info.pool.count, "Query range out of bounds");
+
+        self.flush_barriers();
+
+        let buffer = self.check_and_remeber_buffer_id(info.dst_buffer);
+        let stride = (info.pool.kind.values_per_query() + info.with_availability as u32) as u64 * size_of::<u64>() as u64;
+
+        let mut flags = vk::QueryResultFlags::TYPE_64;
+        if info.with_availability {
+            flags |= vk::QueryResultFlags::WITH_AVAILABILITY;
+        }
+        if info.wait {
+            flags |= vk::QueryResultFlags::WAIT;
+        }
+
+        unsafe {
+            self.device
+                .handle
+                .cmd_copy_query_pool_results(self.current_commad_buffer, info.pool.handle, info.first_query, info.query_count, buffer, info.dst_offset, stride, flags);
+        }
+    }
+
+    //// Profiling ////
+    /// Times everything recorded until the matching `end_gpu_scope`, scopes nest.
+    /// Results show up in `Device::gpu_profile_results` once the frame is done.
+    #[track_caller]
+    pub fn begin_gpu_scope(&mut self, name: &str) {
+        self.validator.command("begin_gpu_scope");
+
+        let (scope, query) = self.device.begin_gpu_scope(name, self.gpu_scopes.last().copied());
+        self.gpu_scopes.push(scope);
+
+        unsafe {
+            self.device.handle.cmd_write_timestamp2(self.current_commad_buffer, vk::PipelineStageFlags2::ALL_COMMANDS, scope.pool, query);
+        }
+    }
+
+    #[track_caller]
+    pub fn end_gpu_scope(&mut self) {
+        self.validator.command("end_gpu_scope");
+
+        let scope = self.gpu_scopes.pop().expect("end_gpu_scope without begin_gpu_scope");
+        let query = self.device.end_gpu_scope(scope);
+
+        unsafe {
+            self.device.handle.cmd_write_timestamp2(self.current_commad_buffer, vk::PipelineStageFlags2::ALL_COMMANDS, scope.pool, query);
+        }
+    }
+
     // Dynamic rendering
+    #[track_caller]
     pub fn begin_rendering(&mut self, rendering_begin_info: &RenderingBeginInfo) {
+        self.validator.begin_rendering();
+
+        self.flush_barriers();
         let mut color_attachment_info = SmallVec::<[vk::RenderingAttachmentInfo; 4]>::new();
 
         for color_attachement in rendering_begin_info.color_attachments {
@@ -79,6 +344,9 @@ impl CommandRecorder {
             )
         }
 
+        self.rendering_samples.set(Some(self.attachment_sample_count(rendering_begin_info)));
+        self.check_sample_counts();
+
         let mut rendering_info = vk::RenderingInfo::default()
             .flags(rendering_begin_info.rendering_flags.to_vk())
             .color_attachments(color_attachment_info.as_slice())
@@ -144,14 +412,22 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn end_rendering(&self) {
+        self.validator.end_rendering();
+
+        self.rendering_samples.set(None);
+
         unsafe {
             self.device.handle.cmd_end_rendering(self.current_commad_buffer);
         }
     }
 
     //// Bind Commands ////
+    #[track_caller]
     pub fn set_viewport_and_scissor(&self, width: u32, height: u32) {
+        self.validator.command("set_viewport_and_scissor");
+
         unsafe {
             self.device.handle.cmd_set_viewport(
                 self.current_commad_buffer,
@@ -177,36 +453,259 @@ impl CommandRecorder {
         }
     }
 
-    pub fn set_push_constants<T>(&self, push_constants: &T, pipeline: Pipeline) {
-        let data_ptr = push_constants as *const T as *const u8;
-        let data_size = std::mem::size_of::<T>();
+    /// Negative heights flip the viewport
+    #[track_caller]
+    pub fn set_viewport(&self, viewport: &Viewport) {
+        self.validator.command("set_viewport");
+
+        unsafe {
+            self.device.handle.cmd_set_viewport(self.current_commad_buffer, 0, &[viewport.to_vk()]);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_scissor(&self, scissor: &RenderArea) {
+        self.validator.command("set_scissor");
+
+        unsafe {
+            self.device.handle.cmd_set_scissor(
+                self.current_commad_buffer,
+                0,
+                &[vk::Rect2D {
+                    offset: scissor.offset.to_vk(),
+                    extent: scissor.extent.to_vk(),
+                }],
+            );
+        }
+    }
+
+    //// Dynamic state ////
+    // Each of these needs the matching DynamicStates flag on the bound graphics pipeline
+
+    #[track_caller]
+    pub fn set_cull_mode(&self, cull_mode: CullMode) {
+        self.validator.command("set_cull_mode");
+        self.check_dynamic_state(DynamicStates::CULL_MODE, "set_cull_mode", "CULL_MODE");
+
+        unsafe {
+            self.device.handle.cmd_set_cull_mode(self.current_commad_buffer, cull_mode.to_vk_flag());
+        }
+    }
+
+    #[track_caller]
+    pub fn set_front_face(&self, front_face: FrontFace) {
+        self.validator.command("set_front_face");
+        self.check_dynamic_state(DynamicStates::FRONT_FACE, "set_front_face", "FRONT_FACE");
+
+        unsafe {
+            self.device.handle.cmd_set_front_face(self.current_commad_buffer, front_face.to_vk_flag());
+        }
+    }
+
+    #[track_caller]
+    pub fn set_depth_test_enable(&self, enable: bool) {
+        self.validator.command("set_depth_test_enable");
+        self.check_dynamic_state(DynamicStates::DEPTH_TEST, "set_depth_test_enable", "DEPTH_TEST");
+
+        unsafe {
+            self.device.handle.cmd_set_depth_test_enable(self.current_commad_buffer, enable);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_depth_write_enable(&self, enable: bool) {
+        self.validator.command("set_depth_write_enable");
+        self.check_dynamic_state(DynamicStates::DEPTH_WRITE, "set_depth_write_enable", "DEPTH_WRITE");
+
+        unsafe {
+            self.device.handle.cmd_set_depth_write_enable(self.current_commad_buffer, enable);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_depth_compare_op(&self, compare_op: CompareOp) {
+        self.validator.command("set_depth_compare_op");
+        self.check_dynamic_state(DynamicStates::DEPTH_COMPARE, "set_depth_compare_op", "DEPTH_COMPARE");
+
+        unsafe {
+            self.device.handle.cmd_set_depth_compare_op(self.current_commad_buffer, compare_op.to_vk());
+        }
+    }
+
+    #[track_caller]
+    pub fn set_depth_bias_enable(&self, enable: bool) {
+        self.validator.command("set_depth_bias_enable");
+        self.check_dynamic_state(DynamicStates::DEPTH_BIAS, "set_depth_bias_enable", "DEPTH_BIAS");
+
+        unsafe {
+            self.device.handle.cmd_set_depth_bias_enable(self.current_commad_buffer, enable);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_depth_bias(&self, constant_factor: f32, clamp: f32, slope_factor: f32) {
+        self.validator.command("set_depth_bias");
+        self.check_dynamic_state(DynamicStates::DEPTH_BIAS, "set_depth_bias", "DEPTH_BIAS");
+
+        unsafe {
+            self.device.handle.cmd_set_depth_bias(self.current_commad_buffer, constant_factor, clamp, slope_factor);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_stencil_test_enable(&self, enable: bool) {
+        self.validator.command("set_stencil_test_enable");
+        self.check_dynamic_state(DynamicStates::STENCIL_OPS, "set_stencil_test_enable", "STENCIL_OPS");
+
+        unsafe {
+            self.device.handle.cmd_set_stencil_test_enable(self.current_commad_buffer, enable);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_stencil_op(&self, faces: StencilFaces, fail_op: StencilOp, pass_op: StencilOp, depth_fail_op: StencilOp, compare_op: CompareOp) {
+        self.validator.command("set_stencil_op");
+        self.check_dynamic_state(DynamicStates::STENCIL_OPS, "set_stencil_op", "STENCIL_OPS");
 
-        let slot = unsafe { &(*self.pipeline_manager.pipelines.get()) }.get_ref(pipeline.get_raw());
         unsafe {
             self.device
                 .handle
-                .cmd_push_constants(self.current_commad_buffer, slot.layout, slot.push_constants_info.stage_flags.to_vk(), 0, std::slice::from_raw_parts(data_ptr, data_size));
+                .cmd_set_stencil_op(self.current_commad_buffer, faces.to_vk(), fail_op.to_vk(), pass_op.to_vk(), depth_fail_op.to_vk(), compare_op.to_vk());
         }
     }
 
+    #[track_caller]
+    pub fn set_stencil_reference(&self, faces: StencilFaces, reference: u32) {
+        self.validator.command("set_stencil_reference");
+        self.check_dynamic_state(DynamicStates::STENCIL_REFERENCE, "set_stencil_reference", "STENCIL_REFERENCE");
+
+        unsafe {
+            self.device.handle.cmd_set_stencil_reference(self.current_commad_buffer, faces.to_vk(), reference);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_stencil_masks(&self, faces: StencilFaces, compare_mask: u32, write_mask: u32) {
+        self.validator.command("set_stencil_masks");
+        self.check_dynamic_state(DynamicStates::STENCIL_MASKS, "set_stencil_masks", "STENCIL_MASKS");
+
+        unsafe {
+            self.device.handle.cmd_set_stencil_compare_mask(self.current_commad_buffer, faces.to_vk(), compare_mask);
+            self.device.handle.cmd_set_stencil_write_mask(self.current_commad_buffer, faces.to_vk(), write_mask);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_blend_constants(&self, constants: [f32; 4]) {
+        self.validator.command("set_blend_constants");
+        self.check_dynamic_state(DynamicStates::BLEND_CONSTANTS, "set_blend_constants", "BLEND_CONSTANTS");
+
+        unsafe {
+            self.device.handle.cmd_set_blend_constants(self.current_commad_buffer, &constants);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_line_width(&self, width: f32) {
+        self.validator.command("set_line_width");
+        self.check_dynamic_state(DynamicStates::LINE_WIDTH, "set_line_width", "LINE_WIDTH");
+
+        unsafe {
+            self.device.handle.cmd_set_line_width(self.current_commad_buffer, width);
+        }
+    }
+
+    #[track_caller]
+    pub fn set_primitive_topology(&self, topology: InputTopology) {
+        self.validator.command("set_primitive_topology");
+        self.check_dynamic_state(DynamicStates::PRIMITIVE_TOPOLOGY, "set_primitive_topology", "PRIMITIVE_TOPOLOGY");
+
+        unsafe {
+            self.device.handle.cmd_set_primitive_topology(self.current_commad_buffer, topology.to_vk());
+        }
+    }
+
+    /// Writes at the offset of the pipeline's `push_constants` range
+    #[track_caller]
+    pub fn set_push_constants<T>(&self, push_constants: &T, pipeline: Pipeline) {
+        let slot = unsafe { &(*self.pipeline_manager.pipelines.get()) }.get_ref(pipeline.get_raw());
+        let offset = slot.push_constant_ranges.first().map_or(0, |range| range.offset);
+
+        self.update_push_constants(pipeline, offset, push_constants);
+    }
+
+    #[track_caller]
+    pub fn push_constants<T>(&self, pipeline: Pipeline, range: &PushConstants<T>, data: &T) {
+        self.update_push_constants(pipeline, range.offset, data);
+    }
+
+    /// Partial update at `offset`, like a single field with `memoffset::offset_of!`.
+    /// The bytes have to lie inside every range of the pipeline they touch.
+    #[track_caller]
+    pub fn update_push_constants<T>(&self, pipeline: Pipeline, offset: u32, data: &T) {
+        self.validator.push_constants(pipeline);
+
+        let size = size_of::<T>() as u32;
+        let slot = unsafe { &(*self.pipeline_manager.pipelines.get()) }.get_ref(pipeline.get_raw());
+        let stages = pipelines::push_constant_stages(&slot.push_constant_ranges, offset, size);
+
+        unsafe {
+            let bytes = std::slice::from_raw_parts(data as *const T as *const u8, size as usize);
+            self.device.handle.cmd_push_constants(self.current_commad_buffer, slot.layout, stages, offset, bytes);
+        }
+    }
+
+    #[track_caller]
     pub fn bind_pipeline(&self, pipeline: Pipeline) {
         let slot = unsafe { &(*self.pipeline_manager.pipelines.get()) }.get_ref(pipeline.get_raw());
+        self.validator.bind_pipeline(pipeline, &slot.vertex_bindings);
+
+        if slot.bind_point == vk::PipelineBindPoint::GRAPHICS {
+            self.mesh_pipeline_bound.set(slot.mesh_shading);
+            self.bound_dynamic_states.set(slot.dynamic_states);
+            self.bound_pipeline_samples.set(Some(slot.samples));
+            self.check_sample_counts();
+        }
+
         unsafe {
             self.device.handle.cmd_bind_descriptor_sets(self.current_commad_buffer, slot.bind_point, slot.layout, 0, &[self.device.bindless_descriptors.set], &[]);
             self.device.handle.cmd_bind_pipeline(self.current_commad_buffer, slot.bind_point, slot.pipeline);
         }
     }
 
+    #[track_caller]
     pub fn bind_vertex_buffer(&mut self, buffer_id: BufferId, offset: u64) {
-        let buffer = [self.check_and_remeber_buffer_id(buffer_id)];
-        let offset = [offset];
+        self.bind_vertex_buffers(0, &[(buffer_id, offset)], None);
+    }
+
+    /// Binds `buffers` as (buffer, offset) to consecutive bindings starting at `first_binding`.
+    /// `strides` overrides the vertex struct sizes, pipelines with `DynamicStates::VERTEX_STRIDE` draw with the strides of the last call.
+    #[track_caller]
+    pub fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: &[(BufferId, u64)], strides: Option<&[u64]>) {
+        self.validator.bind_vertex_buffers(first_binding, buffers.len() as u32);
+
+        let max_bindings = self.device.physical_device.properties.limits.max_vertex_input_bindings;
+        assert!(first_binding + buffers.len() as u32 <= max_bindings, "Vertex bindings go up to {} but the device only supports {}", first_binding + buffers.len() as u32, max_bindings);
+
+        let handles: SmallVec<[vk::Buffer; 4]> = buffers.iter().map(|(buffer_id, _)| self.check_and_remeber_buffer_id(*buffer_id)).collect();
+        let offsets: SmallVec<[u64; 4]> = buffers.iter().map(|(_, offset)| *offset).collect();
+        self.vertex_strides_bound.set(strides.is_some());
 
         unsafe {
-            self.device.handle.cmd_bind_vertex_buffers(self.current_commad_buffer, 0, &buffer, &offset);
+            match strides {
+                Some(strides) => {
+                    assert!(strides.len() == buffers.len(), "Need one stride per vertex buffer");
+                    self.device.handle.cmd_bind_vertex_buffers2(self.current_commad_buffer, first_binding, &handles, &offsets, None, Some(strides));
+                }
+                None => self.device.handle.cmd_bind_vertex_buffers(self.current_commad_buffer, first_binding, &handles, &offsets),
+            }
         }
     }
 
+    #[track_caller]
     pub fn bind_index_buffer(&mut self, buffer_id: BufferId, offset: u64, index_type: IndexType) {
+        self.validator.bind_index_buffer();
+
         let buffer = self.check_and_remeber_buffer_id(buffer_id);
 
         unsafe {
@@ -215,33 +714,53 @@ impl CommandRecorder {
     }
 
     //// Draw commands ////
+    #[track_caller]
     pub fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
+        self.validator.draw("draw", false);
+        self.check_vertex_strides("draw");
+
         unsafe {
             self.device.handle.cmd_draw(self.current_commad_buffer, vertex_count, instance_count, first_vertex, first_instance);
         };
     }
 
+    #[track_caller]
     pub fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
+        self.validator.draw("draw_indexed", true);
+        self.check_vertex_strides("draw_indexed");
+
         unsafe {
             self.device.handle.cmd_draw_indexed(self.current_commad_buffer, index_count, instance_count, first_index, vertex_offset, first_instance);
         }
     }
 
+    #[track_caller]
     pub fn draw_indirect(&mut self, info: &DrawIndirectInfo) {
+        self.validator.draw("draw_indirect", false);
+        self.check_vertex_strides("draw_indirect");
+
         let buf = self.check_and_remeber_buffer_id(info.buffer);
         unsafe {
             self.device.handle.cmd_draw_indirect(self.current_commad_buffer, buf, info.offset, info.draw_count, info.stride);
         }
     }
 
+    #[track_caller]
     pub fn draw_indexed_indirect(&mut self, info: &DrawIndexedIndirectInfo) {
+        self.validator.draw("draw_indexed_indirect", true);
+        self.check_vertex_strides("draw_indexed_indirect");
+
         let buf = self.check_and_remeber_buffer_id(info.buffer);
         unsafe {
             self.device.handle.cmd_draw_indexed_indirect(self.current_commad_buffer, buf, info.offset, info.draw_count, info.stride);
         }
     }
 
+    #[track_caller]
     pub fn draw_indirect_count(&mut self, info: &DrawIndirectCountInfo) {
+        self.validator.draw("draw_indirect_count", false);
+        self.check_vertex_strides("draw_indirect_count");
+
         let buf = self.check_and_remeber_buffer_id(info.buffer);
         let count_buf = self.check_and_remeber_buffer_id(info.count_buffer);
         unsafe {
@@ -251,7 +770,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn draw_indexed_indirect_count(&mut self, info: &DrawIndexedIndirectCountInfo) {
+        self.validator.draw("draw_indexed_indirect_count", true);
+        self.check_vertex_strides("draw_indexed_indirect_count");
+
         let buf = self.check_and_remeber_buffer_id(info.buffer);
         let count_buf = self.check_and_remeber_buffer_id(info.count_buffer);
         unsafe {
@@ -262,13 +785,21 @@ impl CommandRecorder {
     }
 
     //// Compute commands ////
+    #[track_caller]
     pub fn dispatch(&self, info: &DispatchInfo) {
+        self.validator.dispatch("dispatch");
+
+        self.flush_barriers();
         unsafe {
             self.device.handle.cmd_dispatch(self.current_commad_buffer, info.group_count_x, info.group_count_y, info.group_count_z);
         }
     }
 
-    pub fn dispatch_indirect(&mut self, info: &DispatchIndirectInfo) {
+    #[track_caller]
+    pub fn dispatch_indirect(&self, info: &DispatchIndirectInfo) {
+        self.validator.dispatch("dispatch_indirect");
+
+        self.flush_barriers();
         let buffer = self.check_and_remeber_buffer_id(info.buffer);
         unsafe {
             self.device.handle.cmd_dispatch_indirect(self.current_commad_buffer, buffer, info.offset);
@@ -276,7 +807,11 @@ impl CommandRecorder {
     }
 
     //// Pipeline barriers and sync ////
+    #[track_caller]
     pub fn pipeline_barrier(&mut self, barriers: &[Barrier]) {
+        self.validator.command("pipeline_barrier");
+
+        self.flush_barriers();
         let mut mem_barriers = SmallVec::<[vk::MemoryBarrier2; 2]>::new();
         let mut image_barriers = SmallVec::<[vk::ImageMemoryBarrier2; 2]>::new();
         let mut buffer_barriers = SmallVec::<[vk::BufferMemoryBarrier2; 2]>::new();
@@ -294,6 +829,7 @@ impl CommandRecorder {
                 }
                 Barrier::Image(img_barrier) => {
                     let img = self.check_and_remeber_image_id(img_barrier.image);
+                    let (src_family, dst_family) = self.ownership_families(img_barrier.src_queue, img_barrier.dst_queue);
 
                     image_barriers.push(
                         vk::ImageMemoryBarrier2::default()
@@ -303,18 +839,24 @@ impl CommandRecorder {
                             .dst_access_mask(img_barrier.dst_access.to_vk())
                             .old_layout(img_barrier.old_layout.to_vk_layout())
                             .new_layout(img_barrier.new_layout.to_vk_layout())
+                            .src_queue_family_index(src_family)
+                            .dst_queue_family_index(dst_family)
                             .image(img)
                             .subresource_range(img_barrier.subresources.to_vk_subresource_range()),
                     );
                 }
                 Barrier::Buffer(buffer_barrier) => {
                     let buf = self.check_and_remeber_buffer_id(buffer_barrier.buffer);
+                    let (src_family, dst_family) = self.ownership_families(buffer_barrier.src_queue, buffer_barrier.dst_queue);
+
                     buffer_barriers.push(
                         vk::BufferMemoryBarrier2::default()
                             .src_stage_mask(buffer_barrier.src_stage.to_vk())
                             .src_access_mask(buffer_barrier.src_access.to_vk())
                             .dst_stage_mask(buffer_barrier.dst_stage.to_vk())
                             .dst_access_mask(buffer_barrier.dst_access.to_vk())
+                            .src_queue_family_index(src_family)
+                            .dst_queue_family_index(dst_family)
                             .buffer(buf)
                             .offset(buffer_barrier.offset)
                             .size(buffer_barrier.size),
@@ -333,8 +875,214 @@ impl CommandRecorder {
         }
     }
 
+    /// Source half of moving an exclusive buffer from `src_queue` to `dst_queue`, recorded on the source queue.
+    /// The destination queue has to record `acquire_buffer` with the same barrier after waiting on a semaphore.
+    #[track_caller]
+    pub fn release_buffer(&mut self, barrier: &BufferBarrier) {
+        self.validator.command("release_buffer");
+
+        self.check_ownership_transfer(barrier.src_queue, barrier.dst_queue, barrier.src_queue);
+
+        self.pipeline_barrier(&[Barrier::Buffer(BufferBarrier {
+            dst_stage: PipelineStage::None,
+            dst_access: AccessType::None,
+            ..*barrier
+        })]);
+    }
+
+    /// Destination half of `release_buffer`, recorded on the destination queue
+    #[track_caller]
+    pub fn acquire_buffer(&mut self, barrier: &BufferBarrier) {
+        self.validator.command("acquire_buffer");
+
+        self.check_ownership_transfer(barrier.src_queue, barrier.dst_queue, barrier.dst_queue);
+
+        self.pipeline_barrier(&[Barrier::Buffer(BufferBarrier {
+            src_stage: PipelineStage::None,
+            src_access: AccessType::None,
+            ..*barrier
+        })]);
+    }
+
+    /// Image version of `release_buffer`, the layout transition happens once between the two halves
+    #[track_caller]
+    pub fn release_image(&mut self, barrier: &ImageBarrier) {
+        self.validator.command("release_image");
+
+        self.check_ownership_transfer(barrier.src_queue, barrier.dst_queue, barrier.src_queue);
+
+        self.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            dst_stage: PipelineStage::None,
+            dst_access: AccessType::None,
+            ..*barrier
+        })]);
+    }
+
+    #[track_caller]
+    pub fn acquire_image(&mut self, barrier: &ImageBarrier) {
+        self.validator.command("acquire_image");
+
+        self.check_ownership_transfer(barrier.src_queue, barrier.dst_queue, barrier.dst_queue);
+
+        self.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            src_stage: PipelineStage::None,
+            src_access: AccessType::None,
+            ..*barrier
+        })]);
+    }
+
+    //// Automatic barriers ////
+
+    /// Declares how the following commands use the whole image. The barrier from its previous use is worked
+    /// out from tracked state and emitted together with other pending ones before the next command.
+    /// Barriers needed before the first use in this command buffer are recorded at submission, so the recording
+    /// has to use `CommandBufferUsage::OneTimeSubmit`.
+    /// Resources last used on another queue need the submission to wait on a semaphore signaled after that use.
+    /// Has to be called outside of rendering, images transitioned by hand are not tracked.
+    #[track_caller]
+    pub fn use_image(&mut self, image_id: ImageId, usage: Usage) {
+        self.use_image_subresources(image_id, None, usage);
+    }
+
+    /// Like `use_image` but only for some mip levels and array layers, the aspect is taken from the format
+    #[track_caller]
+    pub fn use_image_subresources(&mut self, image_id: ImageId, subresources: Option<ImageSubresources>, usage: Usage) {
+        self.validator.outside_rendering("use_image");
+
+        assert!(self.level == vk::CommandBufferLevel::PRIMARY, "Declare resource usage on the primary recorder that executes the secondaries");
+        assert!(self.one_time_submit, "Resource usage can only be tracked in recordings begun with CommandBufferUsage::OneTimeSubmit");
+        let slot = unsafe { (&*self.device.image_pool.get()).get_ref(image_id.id) };
+
+        let image = self.recorded_usage.images.entry(image_id).or_insert_with(|| TrackedImage {
+            handle: slot.handle,
+            aspect: resource_tracker::format_aspect(slot.format),
+            array_layers: slot.array_layers,
+            subresources: vec![None; (slot.mip_levels * slot.array_layers) as usize],
+        });
+
+        let (mips, layers) = match subresources {
+            Some(sub) => {
+                assert!(sub.mip_level < slot.mip_levels, "Mip range is out of bounds for the image");
+                assert!(sub.base_array_layer < slot.array_layers, "Layer range is out of bounds for the image");
+
+                let level_count = sub.level_count.min(slot.mip_levels - sub.mip_level);
+                let layer_count = sub.layer_count.min(slot.array_layers - sub.base_array_layer);
+                (sub.mip_level..sub.mip_level + level_count, sub.base_array_layer..sub.base_array_layer + layer_count)
+            }
+            None => (0..slot.mip_levels, 0..slot.array_layers),
+        };
+
+        let next = ResourceState::from_usage(usage, true);
+        let indices = mips.clone().flat_map(|mip| layers.clone().map(move |layer| (mip * slot.array_layers + layer) as usize));
+
+        // A range that was used uniformly before moves with a single barrier
+        let first_index = (mips.start * slot.array_layers + layers.start) as usize;
+        let uniform = image.subresources[first_index]
+            .filter(|first| first.current.needs_barrier(&next))
+            .filter(|first| indices.clone().all(|index| image.subresources[index].is_some_and(|other| other.current == first.current)));
+
+        if let Some(previous) = uniform {
+            let range = vk::ImageSubresourceRange {
+                aspect_mask: image.aspect,
+                base_mip_level: mips.start,
+                level_count: mips.len() as u32,
+                base_array_layer: layers.start,
+                layer_count: layers.len() as u32,
+            };
+            self.pending_image_barriers.borrow_mut().push(resource_tracker::image_barrier(image.handle, range, &previous.current, &next));
+
+            for index in indices {
+                let subresource = image.subresources[index].as_mut().unwrap();
+                subresource.current = next;
+                subresource.settled = true;
+            }
+
+            return;
+        }
+
+        for index in indices {
+            let range = image.subresource_range(index);
+
+            match &mut image.subresources[index] {
+                None => image.subresources[index] = Some(SubresourceUse::new(next)),
+                Some(subresource) => {
+                    if subresource.current.needs_barrier(&next) {
+                        self.pending_image_barriers.borrow_mut().push(resource_tracker::image_barrier(image.handle, range, &subresource.current, &next));
+                        subresource.current = next;
+                        subresource.settled = true;
+                    } else {
+                        subresource.current = subresource.current.merge_reads(&next);
+                        if !subresource.settled {
+                            subresource.first = subresource.first.merge_reads(&next);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Buffer version of `use_image`, buffers are always tracked as a whole
+    #[track_caller]
+    pub fn use_buffer(&mut self, buffer_id: BufferId, usage: Usage) {
+        self.validator.outside_rendering("use_buffer");
+
+        assert!(self.level == vk::CommandBufferLevel::PRIMARY, "Declare resource usage on the primary recorder that executes the secondaries");
+        assert!(self.one_time_submit, "Resource usage can only be tracked in recordings begun with CommandBufferUsage::OneTimeSubmit");
+        let next = ResourceState::from_usage(usage, false);
+
+        match self.recorded_usage.buffers.get_mut(&buffer_id) {
+            None => {
+                let handle = self.check_and_remeber_buffer_id(buffer_id);
+                self.recorded_usage.buffers.insert(
+                    buffer_id,
+                    TrackedBuffer {
+                        handle: handle,
+                        usage: SubresourceUse::new(next),
+                    },
+                );
+            }
+            Some(buffer) => {
+                let tracked = &mut buffer.usage;
+
+                if tracked.current.needs_barrier(&next) {
+                    self.pending_buffer_barriers.borrow_mut().push(resource_tracker::buffer_barrier(buffer.handle, &tracked.current, &next));
+                    tracked.current = next;
+                    tracked.settled = true;
+                } else {
+                    tracked.current = tracked.current.merge_reads(&next);
+                    if !tracked.settled {
+                        tracked.first = tracked.first.merge_reads(&next);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emits the barriers queued by `use_image` and `use_buffer`, commands recorded through the recorder do this on their own
+    pub fn flush_barriers(&self) {
+        let mut image_barriers = self.pending_image_barriers.borrow_mut();
+        let mut buffer_barriers = self.pending_buffer_barriers.borrow_mut();
+
+        if image_barriers.is_empty() && buffer_barriers.is_empty() {
+            return;
+        }
+
+        let dep_info = vk::DependencyInfo::default().image_memory_barriers(image_barriers.as_slice()).buffer_memory_barriers(buffer_barriers.as_slice());
+
+        unsafe {
+            self.device.handle.cmd_pipeline_barrier2(self.current_commad_buffer, &dep_info);
+        }
+
+        image_barriers.clear();
+        buffer_barriers.clear();
+    }
+
     //// Copy commands ////
+    #[track_caller]
     pub fn copy_buffer(&mut self, buffer_copy_info: &BufferCopyInfo) {
+        self.validator.outside_rendering("copy_buffer");
+
+        self.flush_barriers();
         let src_buffer = self.check_and_remeber_buffer_id(buffer_copy_info.src_buffer);
         let dst_buffer = self.check_and_remeber_buffer_id(buffer_copy_info.dst_buffer);
 
@@ -351,7 +1099,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn fill_buffer(&mut self, info: &BufferFillInfo) {
+        self.validator.outside_rendering("fill_buffer");
+
+        self.flush_barriers();
         let buffer = self.check_and_remeber_buffer_id(info.buffer);
 
         unsafe {
@@ -359,7 +1111,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn update_buffer<T: Copy>(&mut self, info: &BufferUpdateInfo<T>) {
+        self.validator.outside_rendering("update_buffer");
+
+        self.flush_barriers();
         let buffer = self.check_and_remeber_buffer_id(info.buffer);
 
         unsafe {
@@ -371,7 +1127,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn copy_buffer_to_image(&mut self, info: &BufferImageCopyInfo) {
+        self.validator.outside_rendering("copy_buffer_to_image");
+
+        self.flush_barriers();
         let src = self.check_and_remeber_buffer_id(info.buffer);
         let dst = self.check_and_remeber_image_id(info.image);
 
@@ -394,7 +1154,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn copy_image_to_buffer(&mut self, info: &BufferImageCopyInfo) {
+        self.validator.outside_rendering("copy_image_to_buffer");
+
+        self.flush_barriers();
         // same struct is symmetric
         let src = self.check_and_remeber_image_id(info.image);
         let dst = self.check_and_remeber_buffer_id(info.buffer);
@@ -418,7 +1182,11 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
     pub fn copy_image(&mut self, info: &ImageCopyInfo) {
+        self.validator.outside_rendering("copy_image");
+
+        self.flush_barriers();
         let src = self.check_and_remeber_image_id(info.src_image);
         let dst = self.check_and_remeber_image_id(info.dst_image);
 
@@ -454,7 +1222,110 @@ impl CommandRecorder {
         }
     }
 
+    #[track_caller]
+    pub fn resolve_image(&mut self, info: &ImageResolveInfo) {
+        self.validator.outside_rendering("resolve_image");
+
+        self.flush_barriers();
+        let src = self.check_and_remeber_image_id(info.src_image);
+        let dst = self.check_and_remeber_image_id(info.dst_image);
+
+        let region = vk::ImageResolve2::default()
+            .src_subresource(info.region.src_subresource.to_vk_subresource_layers())
+            .src_offset(info.region.src_offset.to_vk())
+            .dst_subresource(info.region.dst_subresource.to_vk_subresource_layers())
+            .dst_offset(info.region.dst_offset.to_vk())
+            .extent(info.region.extent.to_vk());
+
+        let resolve_info = vk::ResolveImageInfo2::default()
+            .src_image(src)
+            .src_image_layout(info.src_image_layout.to_vk_layout())
+            .dst_image(dst)
+            .dst_image_layout(info.dst_image_layout.to_vk_layout())
+            .regions(std::slice::from_ref(&region));
+
+        unsafe {
+            self.device.handle.cmd_resolve_image2(self.current_commad_buffer, &resolve_info);
+        }
+    }
+
+    //// Clear commands ////
+    #[track_caller]
+    pub fn clear_color_image(&mut self, info: &ImageClearInfo) {
+        self.validator.outside_rendering("clear_color_image");
+
+        assert!(!matches!(info.value, ClearValue::DepthStencil { .. }), "Color images need a color clear value");
+
+        self.flush_barriers();
+        let image = self.check_and_remeber_image_id(info.image);
+
+        unsafe {
+            self.device.handle.cmd_clear_color_image(
+                self.current_commad_buffer,
+                image,
+                info.layout.to_vk_layout(),
+                &info.value.to_vk().color,
+                &[info.subresources.to_vk_subresource_range()],
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn clear_depth_stencil_image(&mut self, info: &ImageClearInfo) {
+        self.validator.outside_rendering("clear_depth_stencil_image");
+
+        assert!(matches!(info.value, ClearValue::DepthStencil { .. }), "Depth stencil images need a depth stencil clear value");
+
+        self.flush_barriers();
+        let image = self.check_and_remeber_image_id(info.image);
+
+        unsafe {
+            self.device.handle.cmd_clear_depth_stencil_image(
+                self.current_commad_buffer,
+                image,
+                info.layout.to_vk_layout(),
+                &info.value.to_vk().depth_stencil,
+                &[info.subresources.to_vk_subresource_range()],
+            );
+        }
+    }
+
+    /// Clears regions of the attachments of the current `begin_rendering`
+    #[track_caller]
+    pub fn clear_attachments(&mut self, attachments: &[AttachmentClear], rects: &[ClearRect]) {
+        self.validator.inside_rendering("clear_attachments");
+
+        let attachments: SmallVec<[vk::ClearAttachment; 4]> = attachments
+            .iter()
+            .map(|attachment| vk::ClearAttachment {
+                aspect_mask: attachment.aspect.to_vk_aspect(),
+                color_attachment: attachment.color_attachment,
+                clear_value: attachment.value.to_vk(),
+            })
+            .collect();
+
+        let rects: SmallVec<[vk::ClearRect; 4]> = rects
+            .iter()
+            .map(|rect| vk::ClearRect {
+                rect: vk::Rect2D {
+                    offset: rect.area.offset.to_vk(),
+                    extent: rect.area.extent.to_vk(),
+                },
+                base_array_layer: rect.base_array_layer,
+                layer_count: rect.layer_count,
+            })
+            .collect();
+
+        unsafe {
+            self.device.handle.cmd_clear_attachments(self.current_commad_buffer, &attachments, &rects);
+        }
+    }
+
+    #[track_caller]
     pub fn blit_image2(&mut self, info: &BlitInfo) {
+        self.validator.outside_rendering("blit_image2");
+
+        self.flush_barriers();
         let src = self.check_and_remeber_image_id(info.src_image);
         let dst = self.check_and_remeber_image_id(info.dst_image);
 
@@ -493,35 +1364,447 @@ impl CommandRecorder {
         }
     }
 
+    //// Mipmaps ////
+
+    /// Fills mip levels `mip_level + 1 .. mip_level + level_count` of the given layers by repeatedly downsampling the previous level.
+    /// The base level is expected to be in `TransferDst` (as left by a buffer upload) and the rest of the levels are treated as undefined.
+    /// After this call every level in the range is in `ShaderReadOnly`.
+    ///
+    /// Blits are used when the format supports them, formats that cant be blitted (or linearly filtered when `Filter::Linear` is asked for)
+    /// fall back to a compute shader, which requires a 2D image created with `ImageUsage::STORAGE`
+    /// and a device supporting storage image reads and writes without format.
+    /// The fallback binds its own compute pipeline and descriptor set 0 over the bindless one,
+    /// call `bind_pipeline` again before the next dispatch.
+    #[track_caller]
+    pub fn generate_mipmaps(&mut self, image: ImageId, subresources: ImageSubresources, filter: Filter) {
+        self.validator.outside_rendering("generate_mipmaps");
+
+        self.flush_barriers();
+        let (format, extent, mip_levels, array_layers, usage, image_type) = {
+            let img = unsafe { (&*self.device.image_pool.get()).get_ref(image.id) };
+            (img.format, img.extent, img.mip_levels, img.array_layers, img.usage, img.image_type)
+        };
+
+        assert!(subresources.mip_level + subresources.level_count <= mip_levels, "Mip range is out of bounds for the image");
+        assert!(subresources.base_array_layer + subresources.layer_count <= array_layers, "Layer range is out of bounds for the image");
+
+        if subresources.level_count <= 1 {
+            return;
+        }
+
+        let features = self.device.get_format_features(format);
+
+        let mut required = vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST;
+        if filter == Filter::Linear {
+            required |= vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+        }
+
+        if features.contains(required) && self.queue_type == QueueType::Graphics {
+            self.generate_mipmaps_blit(image, subresources, extent, filter);
+        } else {
+            assert!(features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE), "Format supports neither blits nor storage images, cant generate mipmaps");
+            assert!(usage.contains(vk::ImageUsageFlags::STORAGE), "Image format cant be blitted, the compute fallback needs the image to have STORAGE usage");
+            assert!(self.queue_type != QueueType::Transfer, "Mipmaps cant be generated on the transfer queue");
+            assert!(image_type == vk::ImageType::TYPE_2D, "Image format cant be blitted and the compute fallback only handles 2D images");
+            let device_features = &self.device.physical_device.features;
+            assert!(
+                device_features.shader_storage_image_read_without_format == vk::TRUE && device_features.shader_storage_image_write_without_format == vk::TRUE,
+                "Format cant be blitted and the device doesnt support storage images without format, cant generate mipmaps"
+            );
+            self.generate_mipmaps_compute(image, subresources, extent, format, filter);
+        }
+    }
+
+    fn generate_mipmaps_blit(&mut self, image: ImageId, subresources: ImageSubresources, extent: vk::Extent3D, filter: Filter) {
+        let level_range = |level: u32, count: u32| ImageSubresources {
+            mip_level: level,
+            level_count: count,
+            ..subresources
+        };
+
+        let base = subresources.mip_level;
+        let last = base + subresources.level_count - 1;
+
+        self.pipeline_barrier(&[
+            Barrier::Image(ImageBarrier {
+                image: image,
+                old_layout: ImageLayout::TransferDst,
+                new_layout: ImageLayout::TransferSrc,
+                src_stage: PipelineStage::Transfer,
+                dst_stage: PipelineStage::Transfer,
+                src_access: AccessType::TransferWrite,
+                dst_access: AccessType::TransferRead,
+                subresources: level_range(base, 1),
+                ..Default::default()
+            }),
+            Barrier::Image(ImageBarrier {
+                image: image,
+                old_layout: ImageLayout::Undefined,
+                new_layout: ImageLayout::TransferDst,
+                src_stage: PipelineStage::TopOfPipe,
+                dst_stage: PipelineStage::Transfer,
+                src_access: AccessType::None,
+                dst_access: AccessType::TransferWrite,
+                subresources: level_range(base + 1, subresources.level_count - 1),
+                ..Default::default()
+            }),
+        ]);
+
+        for level in (base + 1)..=last {
+            let src_extent = mip_extent(extent, level - 1);
+            let dst_extent = mip_extent(extent, level);
+
+            self.blit_image2(&BlitInfo {
+                src_image: image,
+                src_layout: ImageLayout::TransferSrc,
+                dst_image: image,
+                dst_layout: ImageLayout::TransferDst,
+                regions: &[BlitRegion {
+                    src_subresource: level_range(level - 1, 1),
+                    src_offsets: [Offset3D { x: 0, y: 0, z: 0 }, src_extent],
+                    dst_subresource: level_range(level, 1),
+                    dst_offsets: [Offset3D { x: 0, y: 0, z: 0 }, dst_extent],
+                }],
+                filter: filter,
+            });
+
+            let mut barriers = SmallVec::<[Barrier; 2]>::new();
+
+            barriers.push(Barrier::Image(ImageBarrier {
+                image: image,
+                old_layout: ImageLayout::TransferSrc,
+                new_layout: ImageLayout::ShaderReadOnly,
+                src_stage: PipelineStage::Transfer,
+                dst_stage: PipelineStage::AllCommands,
+                src_access: AccessType::TransferRead,
+                dst_access: AccessType::ShaderRead,
+                subresources: level_range(level - 1, 1),
+                ..Default::default()
+            }));
+
+            if level == last {
+                barriers.push(Barrier::Image(ImageBarrier {
+                    image: image,
+                    old_layout: ImageLayout::TransferDst,
+                    new_layout: ImageLayout::ShaderReadOnly,
+                    src_stage: PipelineStage::Transfer,
+                    dst_stage: PipelineStage::AllCommands,
+                    src_access: AccessType::TransferWrite,
+                    dst_access: AccessType::ShaderRead,
+                    subresources: level_range(level, 1),
+                    ..Default::default()
+                }));
+            } else {
+                barriers.push(Barrier::Image(ImageBarrier {
+                    image: image,
+                    old_layout: ImageLayout::TransferDst,
+                    new_layout: ImageLayout::TransferSrc,
+                    src_stage: PipelineStage::Transfer,
+                    dst_stage: PipelineStage::Transfer,
+                    src_access: AccessType::TransferWrite,
+                    dst_access: AccessType::TransferRead,
+                    subresources: level_range(level, 1),
+                    ..Default::default()
+                }));
+            }
+
+            self.pipeline_barrier(&barriers);
+        }
+    }
+
+    fn generate_mipmaps_compute(&mut self, image: ImageId, subresources: ImageSubresources, extent: vk::Extent3D, format: vk::Format, filter: Filter) {
+        let mipmap_pipeline = self.pipeline_manager.get_mipmap_pipeline();
+        let image_handle = self.check_and_remeber_image_id(image);
+
+        let level_range = |level: u32, count: u32| ImageSubresources {
+            mip_level: level,
+            level_count: count,
+            ..subresources
+        };
+
+        let base = subresources.mip_level;
+        let last = base + subresources.level_count - 1;
+
+        self.pipeline_barrier(&[
+            Barrier::Image(ImageBarrier {
+                image: image,
+                old_layout: ImageLayout::TransferDst,
+                new_layout: ImageLayout::General,
+                src_stage: PipelineStage::Transfer,
+                dst_stage: PipelineStage::ComputeShader,
+                src_access: AccessType::TransferWrite,
+                dst_access: AccessType::ShaderRead,
+                subresources: level_range(base, 1),
+                ..Default::default()
+            }),
+            Barrier::Image(ImageBarrier {
+                image: image,
+                old_layout: ImageLayout::Undefined,
+                new_layout: ImageLayout::General,
+                src_stage: PipelineStage::TopOfPipe,
+                dst_stage: PipelineStage::ComputeShader,
+                src_access: AccessType::None,
+                dst_access: AccessType::ShaderWrite,
+                subresources: level_range(base + 1, subresources.level_count - 1),
+                ..Default::default()
+            }),
+        ]);
+
+        unsafe {
+            self.device.handle.cmd_bind_pipeline(self.current_commad_buffer, vk::PipelineBindPoint::COMPUTE, mipmap_pipeline.pipeline);
+        }
+        self.validator.unbind_compute_pipeline();
+
+        for level in (base + 1)..=last {
+            let src_extent = mip_extent(extent, level - 1);
+            let dst_extent = mip_extent(extent, level);
+
+            for layer in subresources.base_array_layer..(subresources.base_array_layer + subresources.layer_count) {
+                let src_view = self.create_transient_view(image_handle, format, subresources.aspect, level - 1, layer);
+                let dst_view = self.create_transient_view(image_handle, format, subresources.aspect, level, layer);
+
+                let set = self.allocate_transient_set(mipmap_pipeline.set_layout);
+
+                let src_info = [vk::DescriptorImageInfo::default().image_view(src_view).image_layout(vk::ImageLayout::GENERAL)];
+                let dst_info = [vk::DescriptorImageInfo::default().image_view(dst_view).image_layout(vk::ImageLayout::GENERAL)];
+
+                let writes = [
+                    vk::WriteDescriptorSet::default().dst_set(set).dst_binding(0).descriptor_type(vk::DescriptorType::STORAGE_IMAGE).image_info(&src_info),
+                    vk::WriteDescriptorSet::default().dst_set(set).dst_binding(1).descriptor_type(vk::DescriptorType::STORAGE_IMAGE).image_info(&dst_info),
+                ];
+
+                let push_constants = [src_extent.x as u32, src_extent.y as u32, dst_extent.x as u32, dst_extent.y as u32, (filter == Filter::Nearest) as u32];
+
+                unsafe {
+                    self.device.handle.update_descriptor_sets(&writes, &[]);
+                    self.device.handle.cmd_bind_descriptor_sets(self.current_commad_buffer, vk::PipelineBindPoint::COMPUTE, mipmap_pipeline.layout, 0, &[set], &[]);
+                    self.device.handle.cmd_push_constants(
+                        self.current_commad_buffer,
+                        mipmap_pipeline.layout,
+                        vk::ShaderStageFlags::COMPUTE,
+                        0,
+                        std::slice::from_raw_parts(push_constants.as_ptr() as *const u8, std::mem::size_of_val(&push_constants)),
+                    );
+                    self.device.handle.cmd_dispatch(self.current_commad_buffer, (dst_extent.x as u32).div_ceil(8), (dst_extent.y as u32).div_ceil(8), 1);
+                }
+            }
+
+            self.pipeline_barrier(&[Barrier::Memory(MemoryBarrier {
+                src_stage: PipelineStage::ComputeShader,
+                dst_stage: PipelineStage::ComputeShader,
+                src_access: AccessType::ShaderWrite,
+                dst_access: AccessType::ShaderRead,
+            })]);
+        }
+
+        self.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            image: image,
+            old_layout: ImageLayout::General,
+            new_layout: ImageLayout::ShaderReadOnly,
+            src_stage: PipelineStage::ComputeShader,
+            dst_stage: PipelineStage::AllCommands,
+            src_access: AccessType::ShaderWrite,
+            dst_access: AccessType::ShaderRead,
+            subresources: subresources,
+            ..Default::default()
+        })]);
+    }
+
     //// Mesh shaders ////
+    #[track_caller]
+    pub fn draw_mesh_tasks(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        self.validator.graphics_work("draw_mesh_tasks");
+
+        let loader = self.mesh_loader();
+        unsafe {
+            loader.cmd_draw_mesh_tasks(self.current_commad_buffer, group_count_x, group_count_y, group_count_z);
+        }
+    }
+
+    /// `info.buffer` holds `DrawMeshTasksIndirectCommand`s
+    #[track_caller]
+    pub fn draw_mesh_tasks_indirect(&mut self, info: &DrawMeshTasksIndirectInfo) {
+        self.validator.graphics_work("draw_mesh_tasks_indirect");
+
+        let buf = self.check_and_remeber_buffer_id(info.buffer);
+        let loader = self.mesh_loader();
+        unsafe {
+            loader.cmd_draw_mesh_tasks_indirect(self.current_commad_buffer, buf, info.offset, info.draw_count, info.stride);
+        }
+    }
+
+    #[track_caller]
+    pub fn draw_mesh_tasks_indirect_count(&mut self, info: &DrawMeshTasksIndirectCountInfo) {
+        self.validator.graphics_work("draw_mesh_tasks_indirect_count");
+
+        let buf = self.check_and_remeber_buffer_id(info.buffer);
+        let count_buf = self.check_and_remeber_buffer_id(info.count_buffer);
+        let loader = self.mesh_loader();
+        unsafe {
+            loader.cmd_draw_mesh_tasks_indirect_count(self.current_commad_buffer, buf, info.offset, count_buf, info.count_offset, info.max_draw_count, info.stride);
+        }
+    }
+
+    fn mesh_loader(&self) -> &ash::ext::mesh_shader::Device {
+        assert!(self.mesh_pipeline_bound.get(), "Mesh task draws need a pipeline built with GeometryStage::Mesh");
+
+        return self.device.mesh_shader.as_ref().expect("Mesh shaders need DeviceDescription::mesh_shaders");
+    }
+}
+
+/// Returns the far corner of a mip level, used as the second blit offset
+fn mip_extent(extent: vk::Extent3D, level: u32) -> Offset3D {
+    return Offset3D {
+        x: (extent.width >> level).max(1) as i32,
+        y: (extent.height >> level).max(1) as i32,
+        z: (extent.depth >> level).max(1) as i32,
+    };
 }
 
 impl CommandRecorder {
     fn check_and_remeber_image_id(&mut self, id: ImageId) -> vk::Image {
-        let img = unsafe { (&mut *self.device.image_pool.get()).get_ref(id.id) };
+        let img = unsafe { (&*self.device.image_pool.get()).get_ref(id.id) };
 
         return img.handle;
     }
 
-    fn check_and_remeber_buffer_id(&mut self, id: BufferId) -> vk::Buffer {
-        let buffer = unsafe { (&mut *self.device.buffer_pool.get()).get_ref(id.id) };
+    /// Queue families of a barrier, ignored unless it actually moves between families
+    fn ownership_families(&self, src_queue: QueueType, dst_queue: QueueType) -> (u32, u32) {
+        let src_family = self.device.queue_family_index(src_queue);
+        let dst_family = self.device.queue_family_index(dst_queue);
+
+        if src_family == vk::QUEUE_FAMILY_IGNORED || dst_family == vk::QUEUE_FAMILY_IGNORED || src_family == dst_family {
+            return (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED);
+        }
+
+        return (src_family, dst_family);
+    }
+
+    fn check_ownership_transfer(&self, src_queue: QueueType, dst_queue: QueueType, recording_queue: QueueType) {
+        assert!(src_queue != QueueType::None && dst_queue != QueueType::None, "Ownership transfers need both src_queue and dst_queue");
+        assert!(self.queue_type == recording_queue, "Ownership transfer half recorded on {:?} instead of {:?}", self.queue_type, recording_queue);
+    }
+
+    /// Common sample count of all attachments, resolve targets have to be single sampled
+    fn attachment_sample_count(&self, rendering_begin_info: &RenderingBeginInfo) -> vk::SampleCountFlags {
+        let view_pool = unsafe { &*self.device.image_view_pool.get() };
+        let mut samples = None;
+
+        let attachments = rendering_begin_info.color_attachments.iter().chain(rendering_begin_info.depth_attachment.iter()).chain(rendering_begin_info.stencil_attachment.iter());
+
+        for attachment in attachments {
+            let attachment_samples = view_pool.get_ref(attachment.image_view.id).samples;
+
+            assert!(samples.is_none_or(|samples| samples == attachment_samples), "All attachments of a rendering need the same sample count");
+            samples = Some(attachment_samples);
+
+            if let Some(resolve_view) = attachment.resolve_image_view {
+                assert!(attachment_samples != vk::SampleCountFlags::TYPE_1, "Only multisampled attachments can be resolved");
+                assert!(view_pool.get_ref(resolve_view.id).samples == vk::SampleCountFlags::TYPE_1, "Resolve targets must be single sampled");
+            }
+        }
+
+        return samples.unwrap_or(vk::SampleCountFlags::TYPE_1);
+    }
+
+    fn check_sample_counts(&self) {
+        if let (Some(pipeline), Some(rendering)) = (self.bound_pipeline_samples.get(), self.rendering_samples.get()) {
+            assert!(pipeline == rendering, "Pipeline renders with {:?} samples but the attachments have {:?}", pipeline, rendering);
+        }
+    }
+
+    #[track_caller]
+    fn check_vertex_strides(&self, command: &str) {
+        if self.bound_dynamic_states.get().contains(DynamicStates::VERTEX_STRIDE) {
+            assert!(self.vertex_strides_bound.get(), "{} with a DynamicStates::VERTEX_STRIDE pipeline needs strides on the last bind_vertex_buffers", command);
+        }
+    }
+
+    #[track_caller]
+    fn check_dynamic_state(&self, state: DynamicStates, command: &str, flag: &str) {
+        assert!(self.bound_dynamic_states.get().contains(state), "{} needs a bound graphics pipeline created with DynamicStates::{}", command, flag);
+    }
+
+    fn forget_tracked_prologues(&mut self) {
+        for prologue in self.tracked_prologues.drain(..) {
+            self.device.forget_recorded_usage(prologue);
+        }
+    }
+
+    fn check_and_remeber_buffer_id(&self, id: BufferId) -> vk::Buffer {
+        let buffer = unsafe { (&*self.device.buffer_pool.get()).get_ref(id.id) };
 
         return buffer.handle;
     }
 
     fn check_and_remeber_image_view_id(&mut self, id: ImageViewId) -> vk::ImageView {
-        let img_view = unsafe { (&mut *self.device.image_view_pool.get()).get_ref(id.id) };
+        let img_view = unsafe { (&*self.device.image_view_pool.get()).get_ref(id.id) };
         return img_view.handle;
     }
 
     pub(crate) fn new_cmd_buffer(&self) -> vk::CommandBuffer {
-        let alloc_info = vk::CommandBufferAllocateInfo::default().command_buffer_count(1).command_pool(self.handle).level(vk::CommandBufferLevel::PRIMARY);
+        let alloc_info = vk::CommandBufferAllocateInfo::default().command_buffer_count(1).command_pool(self.handle).level(self.level);
 
         let cmd_buffer = unsafe { self.device.handle.allocate_command_buffers(&alloc_info).expect("Failed to allocate command buffer") }[0];
 
         return cmd_buffer;
     }
 
+    fn create_transient_view(&mut self, image: vk::Image, format: vk::Format, aspect: ImageAspect, level: u32, layer: u32) -> vk::ImageView {
+        let create_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: aspect.to_vk_aspect(),
+                base_mip_level: level,
+                level_count: 1,
+                base_array_layer: layer,
+                layer_count: 1,
+            });
+
+        let view = unsafe { self.device.handle.create_image_view(&create_info, None).expect("Failed to create transient image view") };
+        self.transient_views.push(view);
+
+        return view;
+    }
+
+    fn allocate_transient_set(&mut self, layout: vk::DescriptorSetLayout) -> vk::DescriptorSet {
+        let layouts = [layout];
+
+        if let Some(&pool) = self.transient_descriptor_pools.last() {
+            let alloc_info = vk::DescriptorSetAllocateInfo::default().descriptor_pool(pool).set_layouts(&layouts);
+
+            if let Ok(sets) = unsafe { self.device.handle.allocate_descriptor_sets(&alloc_info) } {
+                return sets[0];
+            }
+        }
+
+        let pool_sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 64,
+        }];
+        let pool_info = vk::DescriptorPoolCreateInfo::default().max_sets(32).pool_sizes(&pool_sizes);
+        let pool = unsafe { self.device.handle.create_descriptor_pool(&pool_info, None).expect("Failed to create transient descriptor pool") };
+        self.transient_descriptor_pools.push(pool);
+
+        let alloc_info = vk::DescriptorSetAllocateInfo::default().descriptor_pool(pool).set_layouts(&layouts);
+
+        return unsafe { self.device.handle.allocate_descriptor_sets(&alloc_info).expect("Failed to allocate transient descriptor set") }[0];
+    }
+
+    fn free_transient_objects(&mut self) {
+        unsafe {
+            for view in self.transient_views.drain(..) {
+                self.device.handle.destroy_image_view(view, None);
+            }
+
+            for &pool in &self.transient_descriptor_pools {
+                self.device.handle.reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty()).expect("Failed to reset transient descriptor pool");
+            }
+        }
+    }
+
     pub(crate) fn flush_descriptors(&mut self) {
         let buffer = self.device.bindless_descriptors.device_address_buffer.as_ref().unwrap();
         let address_data = self.device.bindless_descriptors.get_addresses();
@@ -532,9 +1815,20 @@ impl CommandRecorder {
     }
 }
 
+// Pending barriers never point to extension structs. While recording, the resource and pipeline pools are only read,
+// the resource tracker and gpu profiler are behind locks and the mipmap pipeline is built through a OnceLock
+unsafe impl Send for CommandRecorder {}
+
 impl Drop for CommandRecorder {
     fn drop(&mut self) {
+        self.free_transient_objects();
+        self.forget_tracked_prologues();
+
         unsafe {
+            for pool in self.transient_descriptor_pools.drain(..) {
+                self.device.handle.destroy_descriptor_pool(pool, None);
+            }
+
             self.device.handle.destroy_command_pool(self.handle, None);
         }
     }
@@ -542,6 +1836,22 @@ impl Drop for CommandRecorder {
 
 #[derive(Clone, Copy)]
 pub struct ExecutableCommandBuffer {
+    pub(crate) handle: vk::CommandBuffer,
+    // Recorded at submission with the barriers for resources used through use_image and use_buffer, null if there are none
+    pub(crate) prologue: vk::CommandBuffer,
+    pub(crate) queue_type: QueueType,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryPool {
+    pub(crate) handle: vk::QueryPool,
+    pub(crate) kind: QueryKind,
+    pub(crate) count: u32,
+}
+
+/// Recorded by a secondary recorder, see `CommandRecorder::execute_commands`
+#[derive(Clone, Copy)]
+pub struct SecondaryCommandBuffer {
     pub(crate) handle: vk::CommandBuffer,
     pub(crate) queue_type: QueueType,
 }
@@ -3,11 +3,11 @@ use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use smallvec::smallvec;
 
 use crate::{
-    backend::{device::InnerDevice, pipelines::InnerPipelineManager, swapchain::InnerSwapchain},
+    backend::{device::InnerDevice, pipelines::InnerPipelineManager, recording_validator::RecordingValidator, swapchain::InnerSwapchain},
     utils::texture::Texture,
     *,
 };
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 
 #[derive(Clone)]
 pub struct Device {
@@ -51,6 +51,34 @@ impl Device {
     }
 }
 
+// Buffer slice //
+impl Device {
+    /// Carves a slice out of a large backing buffer shared with other slices of the same kind
+    pub fn create_buffer_slice(&self, slice_desc: &BufferSliceDescription) -> BufferSlice {
+        return self.inner_device.create_buffer_slice(slice_desc);
+    }
+
+    pub fn destroy_buffer_slice(&self, slice: BufferSlice) {
+        self.inner_device.destroy_buffer_slice(slice);
+    }
+
+    pub fn write_data_to_buffer_slice<T: Copy>(&self, slice: BufferSlice, data: &[T]) {
+        self.inner_device.write_data_to_buffer_slice(slice, data);
+    }
+}
+
+// Buffer View //
+impl Device {
+    /// Formatted view of the non empty `range` (in bytes) of a buffer with `UNIFORM_TEXEL` or `STORAGE_TEXEL` usage
+    pub fn create_buffer_view(&self, buffer_id: BufferId, format: Format, range: std::ops::Range<u64>) -> BufferViewId {
+        return self.inner_device.create_buffer_view(buffer_id, format, range);
+    }
+
+    pub fn destroy_buffer_view(&self, buffer_view_id: BufferViewId) {
+        self.inner_device.destroy_buffer_view(buffer_view_id);
+    }
+}
+
 // Image //
 impl Device {
     pub fn create_image(&self, image_desc: &ImageDescription) -> ImageId {
@@ -60,10 +88,131 @@ impl Device {
     pub fn destroy_image(&self, image_id: ImageId) {
         self.inner_device.destroy_image(image_id);
     }
+
+    /// Mapped memory of a linear image created with `MemoryType::PreferHost`, use `get_image_subresource_layout` for the row pitch
+    pub fn get_image_raw_ptr(&self, image_id: ImageId) -> *mut u8 {
+        return self.inner_device.get_image_raw_ptr(image_id);
+    }
+
+    pub fn get_image_subresource_layout(&self, image_id: ImageId, aspect: ImageAspect, mip_level: u32, array_layer: u32) -> SubresourceLayout {
+        return self.inner_device.get_image_subresource_layout(image_id, aspect, mip_level, array_layer);
+    }
+
+    /// Copies one mip level of one array layer back to the CPU.
+    /// `layout` is the layout the image is currently in, it gets transitioned back to it after the copy.
+    /// The image needs `TRANSFER_SRC` usage. Blocks until the copy has finished on the graphics queue.
+    pub fn read_image(&self, image: ImageId, layout: ImageLayout, subresource: ImageSubresources) -> ImageData {
+        assert!(layout != ImageLayout::Undefined, "read_image needs the current layout of the image, not Undefined");
+
+        let (vk_format, image_extent, usage) = {
+            let img = unsafe { (&*self.inner_device.image_pool.get()).get_ref(image.id) };
+            (img.format, img.extent, img.usage)
+        };
+
+        assert!(usage.contains(vk::ImageUsageFlags::TRANSFER_SRC), "read_image needs an image created with TRANSFER_SRC usage");
+
+        let format = Format::from_vk_format(vk_format).unwrap_or_else(|| panic!("read_image doesnt support images with format {:?}", vk_format));
+
+        // Depth and stencil aspects are copied out separately, stencil ends up as one byte per texel
+        let format = match (format, subresource.aspect) {
+            (_, ImageAspect::DepthStencil) => panic!("read_image can only read the depth or the stencil aspect, not both"),
+            (Format::D24UnormS8Uint, ImageAspect::Stencil) => Format::R8Unorm,
+            (format, _) => format,
+        };
+
+        let subresource = ImageSubresources {
+            level_count: 1,
+            layer_count: 1,
+            ..subresource
+        };
+
+        let extent = Extent3D {
+            width: (image_extent.width >> subresource.mip_level).max(1),
+            height: (image_extent.height >> subresource.mip_level).max(1),
+            depth: (image_extent.depth >> subresource.mip_level).max(1),
+        };
+
+        let size = format.region_size(extent.width, extent.height, extent.depth);
+
+        let staging_buffer = self.create_buffer(&BufferDescription {
+            usage: BufferUsage::TRANSFER_DST,
+            size: size,
+            memory_type: MemoryType::PreferHost,
+            create_mapped: true,
+            ..Default::default()
+        });
+
+        let mut recorder = self.create_command_recorder(QueueType::Graphics);
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+
+        recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            image: image,
+            old_layout: layout,
+            new_layout: ImageLayout::TransferSrc,
+            src_stage: PipelineStage::AllCommands,
+            dst_stage: PipelineStage::Transfer,
+            src_access: AccessType::MemoryWrite,
+            dst_access: AccessType::TransferRead,
+            subresources: subresource,
+            ..Default::default()
+        })]);
+
+        recorder.copy_image_to_buffer(&BufferImageCopyInfo {
+            buffer: staging_buffer,
+            image: image,
+            dst_image_layout: ImageLayout::TransferSrc,
+            region: BufferImageCopyRegion {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: subresource,
+                image_offset: Offset3D { x: 0, y: 0, z: 0 },
+                image_extent: extent,
+            },
+        });
+
+        recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            image: image,
+            old_layout: ImageLayout::TransferSrc,
+            new_layout: layout,
+            src_stage: PipelineStage::Transfer,
+            dst_stage: PipelineStage::AllCommands,
+            src_access: AccessType::TransferRead,
+            dst_access: AccessType::None,
+            subresources: subresource,
+            ..Default::default()
+        })]);
+
+        let exec_buffer = recorder.end_recording();
+
+        self.submit(&QueueSubmitInfo {
+            fence: None,
+            command_buffers: &[exec_buffer],
+            wait_semaphores: &[],
+            signal_semaphores: &[],
+        });
+        self.wait_queue(QueueType::Graphics);
+
+        let mut data = vec![0u8; size as usize];
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.get_raw_ptr(staging_buffer), data.as_mut_ptr(), size as usize);
+        }
+
+        self.destroy_buffer(staging_buffer);
+
+        return ImageData { format, extent, data };
+    }
 }
 
 // Image View //
 impl Device {
+    /// Sample counts usable for 2D optimal images with this format and usage, 1 is always first
+    pub fn supported_sample_counts(&self, format: Format, usage: ImageUsage) -> Vec<SampleCount> {
+        let supported = self.inner_device.supported_sample_counts(format.to_vk_format(), usage.to_vk_flag());
+
+        return SampleCount::ALL.into_iter().filter(|samples| supported.contains(samples.to_vk_flags())).collect();
+    }
+
     pub fn create_image_view(&self, image_id: ImageId, image_view_desc: &ImageViewDescription) -> ImageViewId {
         return self.inner_device.create_image_view(image_id, image_view_desc);
     }
@@ -75,10 +224,18 @@ impl Device {
 
 // Sampler //
 impl Device {
+    /// Samplers are cached, an identical description returns the same SamplerId.
+    /// Every create needs a matching destroy, the sampler is freed after the last one.
     pub fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId {
         return self.inner_device.create_sampler(sampler_desc);
     }
 
+    /// Cached sampler that is also written to the bindless sampler array.
+    /// Indices are handed out from the top of the array down, so they dont clash with low indices written through `write_sampler`.
+    pub fn create_bindless_sampler(&self, sampler_desc: &SamplerDescription) -> BindlessSampler {
+        return self.inner_device.create_bindless_sampler(sampler_desc);
+    }
+
     pub fn destroy_sampler(&self, sampler_id: SamplerId) {
         self.inner_device.destroy_sampler(sampler_id);
     }
@@ -103,6 +260,47 @@ impl Device {
         self.destroy_image(texture.image);
         self.destroy_image_view(texture.image_view);
     }
+
+    /// Decodes a PNG, JPEG or Radiance HDR file and uploads it as a sampled texture.
+    /// Blocks until the upload has finished on the graphics queue.
+    #[cfg(feature = "texture-loading")]
+    pub fn load_texture_from_path<P: AsRef<std::path::Path>>(&self, path: P, texture_desc: &crate::utils::texture::TextureDescription) -> Texture {
+        let img = image::open(path).expect("Failed to load texture file");
+        return crate::utils::texture::create_texture_from_image(self, img, texture_desc);
+    }
+
+    /// Same as `load_texture_from_path`, but the encoded file is already in memory.
+    /// The container format is guessed from the data.
+    #[cfg(feature = "texture-loading")]
+    pub fn load_texture_from_bytes(&self, bytes: &[u8], texture_desc: &crate::utils::texture::TextureDescription) -> Texture {
+        let img = image::load_from_memory(bytes).expect("Failed to decode texture");
+        return crate::utils::texture::create_texture_from_image(self, img, texture_desc);
+    }
+
+    /// Uploads a KTX2 file with all of its mips, layers and faces, block compressed formats are kept as is.
+    /// Supercompressed (basis universal, zstd) files are not supported.
+    #[cfg(feature = "texture-containers")]
+    pub fn load_ktx2_from_path<P: AsRef<std::path::Path>>(&self, path: P, index: u32) -> Texture {
+        let bytes = std::fs::read(path).expect("Failed to read KTX2 file");
+        return crate::utils::texture_container::create_texture_from_ktx2(self, &bytes, index);
+    }
+
+    #[cfg(feature = "texture-containers")]
+    pub fn load_ktx2_from_bytes(&self, bytes: &[u8], index: u32) -> Texture {
+        return crate::utils::texture_container::create_texture_from_ktx2(self, bytes, index);
+    }
+
+    /// Uploads a DDS file (legacy or DX10 header) with all of its mips, layers and cube faces.
+    #[cfg(feature = "texture-containers")]
+    pub fn load_dds_from_path<P: AsRef<std::path::Path>>(&self, path: P, index: u32) -> Texture {
+        let bytes = std::fs::read(path).expect("Failed to read DDS file");
+        return crate::utils::texture_container::create_texture_from_dds(self, &bytes, index);
+    }
+
+    #[cfg(feature = "texture-containers")]
+    pub fn load_dds_from_bytes(&self, bytes: &[u8], index: u32) -> Texture {
+        return crate::utils::texture_container::create_texture_from_dds(self, bytes, index);
+    }
 }
 
 impl Device {
@@ -129,6 +327,10 @@ impl Device {
         self.inner_device.write_image(image_write_info);
     }
 
+    pub fn write_buffer_view(&self, buffer_view_write_info: &BufferViewWriteInfo) {
+        self.inner_device.write_buffer_view(buffer_view_write_info);
+    }
+
     pub fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo) {
         self.inner_device.write_sampler(sampler_write_info);
     }
@@ -154,6 +356,15 @@ impl Device {
 // Command buffer //
 impl Device {
     pub fn create_command_recorder(&self, queue_type: QueueType) -> CommandRecorder {
+        return self.new_command_recorder(queue_type, vk::CommandBufferLevel::PRIMARY);
+    }
+
+    /// Records secondary command buffers for `CommandRecorder::execute_commands`, create one per worker thread
+    pub fn create_secondary_command_recorder(&self, queue_type: QueueType) -> CommandRecorder {
+        return self.new_command_recorder(queue_type, vk::CommandBufferLevel::SECONDARY);
+    }
+
+    fn new_command_recorder(&self, queue_type: QueueType, level: vk::CommandBufferLevel) -> CommandRecorder {
         return CommandRecorder {
             handle: self.inner_device.create_cmd_recorder_data(queue_type),
             commad_buffers: smallvec![],
@@ -161,7 +372,22 @@ impl Device {
             current_commad_buffer: vk::CommandBuffer::null(),
             pipeline_manager: self.pipeline_manager.clone(),
             queue_type: queue_type,
+            level: level,
             device: self.inner_device.clone(),
+            transient_views: Vec::new(),
+            transient_descriptor_pools: Vec::new(),
+            recorded_usage: Default::default(),
+            pending_image_barriers: Default::default(),
+            pending_buffer_barriers: Default::default(),
+            tracked_prologues: smallvec![],
+            one_time_submit: false,
+            gpu_scopes: smallvec![],
+            mesh_pipeline_bound: Cell::new(false),
+            bound_dynamic_states: Cell::new(DynamicStates::NONE),
+            vertex_strides_bound: Cell::new(false),
+            bound_pipeline_samples: Cell::new(None),
+            rendering_samples: Cell::new(None),
+            validator: RecordingValidator::new(self.inner_device.desc.validate_recording),
         };
     }
 }
@@ -203,6 +429,109 @@ impl Device {
     }
 }
 
+// External memory //
+#[cfg(unix)]
+impl Device {
+    /// Exports the memory of a resource created with `external_memory` set, every call returns a new fd
+    pub fn export_memory_fd(&self, resource: ExternalResource) -> ExternalMemoryFd {
+        use std::os::fd::FromRawFd;
+
+        let external = unsafe {
+            match resource {
+                ExternalResource::Buffer(id) => (&*self.inner_device.buffer_pool.get()).get_ref(id.id).external_memory.expect("Only buffers created with external_memory can be exported"),
+                ExternalResource::Image(id) => (&*self.inner_device.image_pool.get()).get_ref(id.id).external_memory.expect("Only images created with external_memory can be exported"),
+            }
+        };
+
+        let fd = self.inner_device.export_memory_fd(&external);
+
+        return ExternalMemoryFd {
+            fd: unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) },
+            size: external.size,
+            handle_type: external.handle_type,
+        };
+    }
+
+    /// Creates a buffer backed by memory exported from another device or process.
+    /// `buffer_desc` has to match the description the memory was exported with.
+    pub fn import_buffer_from_fd(&self, buffer_desc: &BufferDescription, memory: ExternalMemoryFd) -> BufferId {
+        use std::os::fd::IntoRawFd;
+
+        return self.inner_device.create_buffer_with_memory(
+            buffer_desc,
+            Some(crate::backend::external_memory::ExternalAllocation::Import {
+                handle_type: memory.handle_type,
+                fd: memory.fd.into_raw_fd(),
+                size: memory.size,
+            }),
+        );
+    }
+
+    /// Image version of `import_buffer_from_fd`
+    pub fn import_image_from_fd(&self, image_desc: &ImageDescription, memory: ExternalMemoryFd) -> ImageId {
+        use std::os::fd::IntoRawFd;
+
+        return self.inner_device.create_image_with_memory(
+            image_desc,
+            Some(crate::backend::external_memory::ExternalAllocation::Import {
+                handle_type: memory.handle_type,
+                fd: memory.fd.into_raw_fd(),
+                size: memory.size,
+            }),
+        );
+    }
+
+    /// Semaphore that can be shared with `export_semaphore_fd`
+    pub fn create_exportable_semaphore(&self, timeline: bool) -> Semaphore {
+        let handle = self.inner_device.create_exportable_semaphore(timeline);
+
+        if timeline {
+            return Semaphore::Timeline(TimelineSemaphore { handle });
+        } else {
+            return Semaphore::Binary(BinarySemaphore { handle });
+        }
+    }
+
+    pub fn export_semaphore_fd(&self, semaphore: Semaphore) -> std::os::fd::OwnedFd {
+        use std::os::fd::FromRawFd;
+
+        let fd = self.inner_device.export_semaphore_fd(semaphore.handle());
+        return unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) };
+    }
+
+    /// Makes `semaphore` share the payload of an exported one, both need to be of the same type
+    pub fn import_semaphore_fd(&self, semaphore: Semaphore, fd: std::os::fd::OwnedFd) {
+        use std::os::fd::IntoRawFd;
+
+        self.inner_device.import_semaphore_fd(semaphore.handle(), fd.into_raw_fd());
+    }
+}
+
+// Queries //
+impl Device {
+    pub fn create_query_pool(&self, kind: QueryKind, count: u32) -> QueryPool {
+        return QueryPool {
+            handle: self.inner_device.create_query_pool(kind, count),
+            kind: kind,
+            count: count,
+        };
+    }
+
+    pub fn destroy_query_pool(&self, pool: QueryPool) {
+        self.inner_device.destroy_query_pool(pool.handle);
+    }
+
+    /// Reads results without stalling unless `wait` is set, queries that arent done yet are marked unavailable
+    pub fn get_query_results(&self, pool: QueryPool, first_query: u32, query_count: u32, wait: bool) -> QueryResults {
+        return self.inner_device.get_query_results(&pool, first_query, query_count, wait);
+    }
+
+    /// Host side reset, the queries must not be in use by the GPU
+    pub fn reset_query_pool(&self, pool: QueryPool, first_query: u32, query_count: u32) {
+        self.inner_device.reset_query_pool(&pool, first_query, query_count);
+    }
+}
+
 // Queue submissions
 impl Device {
     pub fn submit(&self, submit_info: &QueueSubmitInfo) {
@@ -217,3 +546,17 @@ impl Device {
         self.inner_device.wait_queue(queue_type);
     }
 }
+
+// Profiling //
+impl Device {
+    /// Scope tree of the last frame whose timestamps were read back, empty until a profiled frame completed
+    pub fn gpu_profile_results(&self) -> Vec<GpuScope> {
+        return self.inner_device.gpu_profile_results();
+    }
+
+    /// Moves gpu scopes to the query pool of `frame`. `Swapchain::acquire_image` does this already,
+    /// loops without a swapchain call it once the GPU is done with the previous use of `frame`.
+    pub fn begin_gpu_profile_frame(&self, frame: usize) {
+        self.inner_device.begin_profiler_frame(frame);
+    }
+}
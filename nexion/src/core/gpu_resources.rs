@@ -11,6 +11,28 @@ impl BufferId {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BufferViewId {
+    pub(crate) id: u64,
+}
+
+impl BufferViewId {
+    pub const fn null() -> BufferViewId {
+        return BufferViewId { id: u64::MAX };
+    }
+}
+
+/// Range of a shared backing buffer handed out by `Device::create_buffer_slice`.
+/// Pass `buffer` and `offset` anywhere a buffer id plus offset is taken.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BufferSlice {
+    pub buffer: BufferId,
+    pub offset: u64,
+    pub size: u64,
+    /// Device address of the first byte of the slice
+    pub address: u64,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct ImageId {
     pub(crate) id: u64,
@@ -33,6 +55,13 @@ impl SamplerId {
     }
 }
 
+/// Sampler shared through the sampler cache together with its slot in the bindless sampler array
+#[derive(Clone, Copy, Debug)]
+pub struct BindlessSampler {
+    pub sampler: SamplerId,
+    pub index: u32,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct ImageViewId {
     pub(crate) id: u64,
@@ -64,3 +93,22 @@ impl Pipeline {
         };
     }
 }
+
+/// Resource whose memory gets exported with `Device::export_memory_fd`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExternalResource {
+    Buffer(BufferId),
+    Image(ImageId),
+}
+
+impl From<BufferId> for ExternalResource {
+    fn from(buffer: BufferId) -> Self {
+        return ExternalResource::Buffer(buffer);
+    }
+}
+
+impl From<ImageId> for ExternalResource {
+    fn from(image: ImageId) -> Self {
+        return ExternalResource::Image(image);
+    }
+}
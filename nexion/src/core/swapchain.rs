@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
-    Fence, ImageId, ImageViewId, Semaphore, SwapchainDescription,
+    Device, Fence, ImageAspect, ImageData, ImageId, ImageLayout, ImageSubresources, ImageViewId, Semaphore, SwapchainDescription,
     backend::swapchain::{InnerSwapchain, Surface},
 };
 
@@ -45,4 +45,22 @@ impl Swapchain {
     pub fn present(&self) {
         self.inner.present();
     }
+
+    /// Reads back the image returned by the last `acquire_image`, call it after submitting the frame and before `present`.
+    /// `layout` is the layout the frame left the image in, usually `PresentSrc`.
+    pub fn capture_current_image(&self, device: &Device, layout: ImageLayout) -> ImageData {
+        let image = self.inner.current_image().expect("No acquired swapchain image to capture");
+
+        return device.read_image(
+            image,
+            layout,
+            ImageSubresources {
+                aspect: ImageAspect::Color,
+                mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+        );
+    }
 }
@@ -52,6 +52,29 @@ pub struct RenderArea {
     pub extent: Extent2D,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl Viewport {
+    pub(crate) const fn to_vk(&self) -> vk::Viewport {
+        return vk::Viewport {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            min_depth: self.min_depth,
+            max_depth: self.max_depth,
+        };
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LoadOp {
     Load,
@@ -221,6 +244,27 @@ impl<'a> Default for RenderingBeginInfo<'a> {
     }
 }
 
+/// Attachments of the rendering a secondary command buffer gets executed in, has to match the `begin_rendering` call
+pub struct RenderingInheritance<'a> {
+    pub color_formats: &'a [Format],
+    pub depth_format: Option<Format>,
+    pub stencil_format: Option<Format>,
+    pub samples: SampleCount,
+    pub view_mask: u32,
+}
+
+impl<'a> Default for RenderingInheritance<'a> {
+    fn default() -> Self {
+        Self {
+            color_formats: &[],
+            depth_format: None,
+            stencil_format: None,
+            samples: SampleCount::Type1,
+            view_mask: 0,
+        }
+    }
+}
+
 // Indirect draw
 
 #[repr(C)]
@@ -297,6 +341,18 @@ pub struct BufferCopyInfo<'a> {
     pub regions: &'a [CopyRegion],
 }
 
+/// Writes `query_count` results as u64, optionally followed by an availability value per query
+pub struct QueryCopyInfo {
+    pub pool: QueryPool,
+    pub first_query: u32,
+    pub query_count: u32,
+    pub dst_buffer: BufferId,
+    pub dst_offset: u64,
+    pub with_availability: bool,
+    /// Waits for the results on the GPU instead of writing whatever is available
+    pub wait: bool,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BufferFillInfo {
     pub buffer: BufferId,
@@ -338,6 +394,40 @@ pub struct ImageCopyInfo {
     pub region: ImageCopyRegion,
 }
 
+/// Multisampled `src_image` gets averaged into the single sampled `dst_image`, the region works like a copy region
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageResolveInfo {
+    pub src_image: ImageId,
+    pub src_image_layout: ImageLayout,
+    pub dst_image: ImageId,
+    pub dst_image_layout: ImageLayout,
+    pub region: ImageCopyRegion,
+}
+
+/// Clear outside of rendering, the layout has to be `General` or `TransferDst`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageClearInfo {
+    pub image: ImageId,
+    pub layout: ImageLayout,
+    pub value: ClearValue,
+    pub subresources: ImageSubresources,
+}
+
+/// Attachment of the current rendering to clear, `color_attachment` is only read for color aspects
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttachmentClear {
+    pub aspect: ImageAspect,
+    pub color_attachment: u32,
+    pub value: ClearValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearRect {
+    pub area: RenderArea,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ImageCopyRegion {
     pub src_subresource: ImageSubresources,
@@ -447,6 +537,8 @@ pub enum AccessType {
     DepthStencilWrite,
     TransferRead,
     TransferWrite,
+    MemoryRead,
+    MemoryWrite,
 }
 
 impl AccessType {
@@ -465,6 +557,8 @@ impl AccessType {
             AccessType::DepthStencilWrite => vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
             AccessType::TransferRead => vk::AccessFlags2::TRANSFER_READ,
             AccessType::TransferWrite => vk::AccessFlags2::TRANSFER_WRITE,
+            AccessType::MemoryRead => vk::AccessFlags2::MEMORY_READ,
+            AccessType::MemoryWrite => vk::AccessFlags2::MEMORY_WRITE,
         }
     }
 }
@@ -555,9 +649,120 @@ pub enum Barrier {
     Buffer(BufferBarrier),
 }
 
+/// How the next commands use a resource, see `CommandRecorder::use_image` and `CommandRecorder::use_buffer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    ColorAttachment,
+    DepthStencilAttachment,
+    /// Read only depth attachment that can be sampled at the same time
+    DepthStencilRead,
+    /// Sampled images or read only storage in any shader stage
+    ShaderRead,
+    /// Storage images and buffers, images go to the general layout
+    ShaderWrite,
+    ShaderReadWrite,
+    UniformRead,
+    VertexBuffer,
+    IndexBuffer,
+    IndirectBuffer,
+    TransferSrc,
+    TransferDst,
+    HostRead,
+    Present,
+}
+
+impl Usage {
+    pub(crate) const fn stages(&self) -> vk::PipelineStageFlags2 {
+        let shaders = vk::PipelineStageFlags2::from_raw(
+            vk::PipelineStageFlags2::PRE_RASTERIZATION_SHADERS.as_raw() | vk::PipelineStageFlags2::FRAGMENT_SHADER.as_raw() | vk::PipelineStageFlags2::COMPUTE_SHADER.as_raw(),
+        );
+
+        match self {
+            Usage::ColorAttachment => vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            Usage::DepthStencilAttachment | Usage::DepthStencilRead => {
+                vk::PipelineStageFlags2::from_raw(vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS.as_raw() | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS.as_raw())
+            }
+            Usage::ShaderRead | Usage::ShaderWrite | Usage::ShaderReadWrite | Usage::UniformRead => shaders,
+            Usage::VertexBuffer | Usage::IndexBuffer => vk::PipelineStageFlags2::VERTEX_INPUT,
+            Usage::IndirectBuffer => vk::PipelineStageFlags2::DRAW_INDIRECT,
+            Usage::TransferSrc | Usage::TransferDst => vk::PipelineStageFlags2::ALL_TRANSFER,
+            Usage::HostRead => vk::PipelineStageFlags2::HOST,
+            // Chains with whatever stage the acquire and present semaphores wait on
+            Usage::Present => vk::PipelineStageFlags2::ALL_COMMANDS,
+        }
+    }
+
+    pub(crate) const fn access(&self) -> vk::AccessFlags2 {
+        match self {
+            Usage::ColorAttachment => vk::AccessFlags2::from_raw(vk::AccessFlags2::COLOR_ATTACHMENT_READ.as_raw() | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE.as_raw()),
+            Usage::DepthStencilAttachment => {
+                vk::AccessFlags2::from_raw(vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ.as_raw() | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw())
+            }
+            Usage::DepthStencilRead => vk::AccessFlags2::from_raw(vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ.as_raw() | vk::AccessFlags2::SHADER_READ.as_raw()),
+            Usage::ShaderRead => vk::AccessFlags2::SHADER_READ,
+            Usage::ShaderWrite => vk::AccessFlags2::SHADER_WRITE,
+            Usage::ShaderReadWrite => vk::AccessFlags2::from_raw(vk::AccessFlags2::SHADER_READ.as_raw() | vk::AccessFlags2::SHADER_WRITE.as_raw()),
+            Usage::UniformRead => vk::AccessFlags2::UNIFORM_READ,
+            Usage::VertexBuffer => vk::AccessFlags2::VERTEX_ATTRIBUTE_READ,
+            Usage::IndexBuffer => vk::AccessFlags2::INDEX_READ,
+            Usage::IndirectBuffer => vk::AccessFlags2::INDIRECT_COMMAND_READ,
+            Usage::TransferSrc => vk::AccessFlags2::TRANSFER_READ,
+            Usage::TransferDst => vk::AccessFlags2::TRANSFER_WRITE,
+            Usage::HostRead => vk::AccessFlags2::HOST_READ,
+            Usage::Present => vk::AccessFlags2::NONE,
+        }
+    }
+
+    pub(crate) const fn is_write(&self) -> bool {
+        return matches!(self, Usage::ColorAttachment | Usage::DepthStencilAttachment | Usage::ShaderWrite | Usage::ShaderReadWrite | Usage::TransferDst);
+    }
+
+    /// Attachments count as reads too, with a load op they keep what earlier passes wrote
+    pub(crate) const fn is_read(&self) -> bool {
+        return !matches!(self, Usage::ShaderWrite | Usage::TransferDst);
+    }
+
+    pub(crate) const fn layout(&self) -> vk::ImageLayout {
+        match self {
+            Usage::ColorAttachment => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            Usage::DepthStencilAttachment => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            Usage::DepthStencilRead => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            Usage::ShaderRead => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            Usage::TransferSrc => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Usage::TransferDst => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            Usage::Present => vk::ImageLayout::PRESENT_SRC_KHR,
+            _ => vk::ImageLayout::GENERAL,
+        }
+    }
+}
+
 // Mesh shaders
 
-pub struct DrawMeshTasksIndirect {}
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DrawMeshTasksIndirectCommand {
+    pub group_count_x: u32,
+    pub group_count_y: u32,
+    pub group_count_z: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawMeshTasksIndirectInfo {
+    pub buffer: BufferId,
+    pub offset: u64,
+    pub draw_count: u32,
+    pub stride: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawMeshTasksIndirectCountInfo {
+    pub buffer: BufferId,
+    pub offset: u64,
+    pub count_buffer: BufferId,
+    pub count_offset: u64,
+    pub max_draw_count: u32,
+    pub stride: u32,
+}
 
 //Submit info
 pub struct SemaphoreInfo {
@@ -23,6 +23,18 @@ pub struct DeviceDescription {
     pub mesh_shaders: bool,
     pub atomic_float_operations: bool,
     pub ray_tracing: bool,
+    /// Enables `BorderColor::FloatCustom` and `BorderColor::IntCustom`
+    pub custom_border_colors: bool,
+    /// Enables the Min and Max `SamplerReductionMode`s
+    pub sampler_filter_minmax: bool,
+    /// Enables exporting and importing memory and semaphores as opaque file descriptors
+    pub external_memory: bool,
+    /// Enables dma-buf memory handles, needs `external_memory` as well
+    pub external_memory_dma_buf: bool,
+    /// Enables `QueryKind::PipelineStatistics` query pools
+    pub pipeline_statistics_queries: bool,
+    /// Tracks the state of every `CommandRecorder` and panics at the misused call, meant for debug builds
+    pub validate_recording: bool,
 }
 
 impl Default for DeviceDescription {
@@ -33,6 +45,12 @@ impl Default for DeviceDescription {
             mesh_shaders: false,
             atomic_float_operations: false,
             ray_tracing: false,
+            custom_border_colors: false,
+            sampler_filter_minmax: false,
+            external_memory: false,
+            external_memory_dma_buf: false,
+            pipeline_statistics_queries: false,
+            validate_recording: false,
         };
     }
 }
@@ -103,3 +121,14 @@ impl Offset2D {
         return ash::vk::Offset2D { x: self.x, y: self.y };
     }
 }
+
+/// Timing of a `CommandRecorder::begin_gpu_scope` / `end_gpu_scope` pair, see `Device::gpu_profile_results`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuScope {
+    pub name: String,
+    /// Relative to the first scope of the frame
+    pub start_ms: f64,
+    pub duration_ms: f64,
+    /// Scopes begun while this one was open on the same recorder
+    pub children: Vec<GpuScope>,
+}
@@ -62,6 +62,16 @@ impl BufferUsage {
     /// Specifies that the buffer contains **indirect dispatch or drawing parameters**.
     pub const INDIRECT: Self = Self { flags: vk::BufferUsageFlags::INDIRECT_BUFFER };
 
+    /// Specifies that the buffer can back a read only **texel buffer view** (`Buffer<T>` in shaders).
+    pub const UNIFORM_TEXEL: Self = Self {
+        flags: vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER,
+    };
+
+    /// Specifies that the buffer can back a read write **texel buffer view** (`RWBuffer<T>` in shaders).
+    pub const STORAGE_TEXEL: Self = Self {
+        flags: vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER,
+    };
+
     /// Specifies that the buffer can be used as the **source** in a transfer operation
     pub const TRANSFER_SRC: Self = Self { flags: vk::BufferUsageFlags::TRANSFER_SRC };
 
@@ -97,12 +107,121 @@ impl BitOr<&BufferUsage> for BufferUsage {
     }
 }
 
+/// Counters of a `QueryKind::PipelineStatistics` pool, can be combined using Bitwise Or (|).
+/// Results hold one value per enabled counter, in the order they are declared here.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct PipelineStatistics {
+    pub(crate) flags: vk::QueryPipelineStatisticFlags,
+}
+
+impl PipelineStatistics {
+    pub const INPUT_ASSEMBLY_VERTICES: Self = Self { flags: vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES };
+    pub const INPUT_ASSEMBLY_PRIMITIVES: Self = Self { flags: vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES };
+    pub const VERTEX_SHADER_INVOCATIONS: Self = Self { flags: vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS };
+    pub const GEOMETRY_SHADER_INVOCATIONS: Self = Self { flags: vk::QueryPipelineStatisticFlags::GEOMETRY_SHADER_INVOCATIONS };
+    pub const GEOMETRY_SHADER_PRIMITIVES: Self = Self { flags: vk::QueryPipelineStatisticFlags::GEOMETRY_SHADER_PRIMITIVES };
+    pub const CLIPPING_INVOCATIONS: Self = Self { flags: vk::QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS };
+    pub const CLIPPING_PRIMITIVES: Self = Self { flags: vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES };
+    pub const FRAGMENT_SHADER_INVOCATIONS: Self = Self { flags: vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS };
+    pub const COMPUTE_SHADER_INVOCATIONS: Self = Self { flags: vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS };
+
+    pub(crate) fn to_vk_flag(&self) -> vk::QueryPipelineStatisticFlags {
+        self.flags
+    }
+}
+
+impl BitOr for PipelineStatistics {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self::Output {
+        Self { flags: self.flags | other.flags }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueryKind {
+    /// Counts samples passing the depth and stencil tests, precise queries give the exact count instead of zero or non zero
+    Occlusion,
+    /// Needs `DeviceDescription::pipeline_statistics_queries`
+    PipelineStatistics(PipelineStatistics),
+    Timestamp,
+}
+
+impl QueryKind {
+    pub(crate) fn to_vk(&self) -> vk::QueryType {
+        match self {
+            Self::Occlusion => vk::QueryType::OCCLUSION,
+            Self::PipelineStatistics(_) => vk::QueryType::PIPELINE_STATISTICS,
+            Self::Timestamp => vk::QueryType::TIMESTAMP,
+        }
+    }
+
+    /// Number of u64 values a single query produces
+    pub(crate) fn values_per_query(&self) -> u32 {
+        match self {
+            Self::PipelineStatistics(statistics) => statistics.flags.as_raw().count_ones(),
+            _ => 1,
+        }
+    }
+}
+
+/// Results of `Device::get_query_results`
+pub struct QueryResults {
+    pub values_per_query: u32,
+    pub values: Vec<u64>,
+    pub available: Vec<bool>,
+}
+
+impl QueryResults {
+    /// Values of the `index`th query that was read, None if it wasnt available yet
+    pub fn get(&self, index: usize) -> Option<&[u64]> {
+        if !self.available[index] {
+            return None;
+        }
+
+        let stride = self.values_per_query as usize;
+        return Some(&self.values[index * stride..(index + 1) * stride]);
+    }
+}
+
+/// Handle types memory can be exported as or imported from, needs `DeviceDescription::external_memory`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalMemoryHandleType {
+    /// Only meaningful to another vulkan device on the same physical device and driver
+    OpaqueFd,
+    /// Linux dma-buf, can be shared with other APIs like video encoders. Needs `DeviceDescription::external_memory_dma_buf`
+    DmaBuf,
+}
+
+impl ExternalMemoryHandleType {
+    pub(crate) const fn to_vk(self) -> vk::ExternalMemoryHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD,
+            Self::DmaBuf => vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+        }
+    }
+}
+
+/// Exported memory of a buffer or image, everything needed to import it on another device
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct ExternalMemoryFd {
+    pub fd: std::os::fd::OwnedFd,
+    /// Size of the allocation, importing needs the exact same value
+    pub size: u64,
+    pub handle_type: ExternalMemoryHandleType,
+}
+
 /// Buffer descriptions, create mapped works only for perfer host memory type
+#[derive(Clone, Copy)]
 pub struct BufferDescription {
     pub usage: BufferUsage,
     pub size: vk::DeviceSize,
     pub memory_type: MemoryType,
     pub create_mapped: bool,
+    /// Gives the buffer its own exportable memory allocation, such buffers cant be mapped
+    pub external_memory: Option<ExternalMemoryHandleType>,
+    /// Exclusive buffers used on several queue types need ownership transfers, see `CommandRecorder::release_buffer`
+    pub sharing_mode: SharingMode,
 }
 
 impl Default for BufferDescription {
@@ -112,6 +231,34 @@ impl Default for BufferDescription {
             size: 10,
             memory_type: MemoryType::Auto,
             create_mapped: false,
+            external_memory: None,
+            sharing_mode: SharingMode::Exclusive,
+        };
+    }
+}
+
+/// Suballocation out of a shared backing buffer, see `Device::create_buffer_slice`.
+/// Slices with the same usage, memory type and mapping share backing buffers.
+pub struct BufferSliceDescription {
+    pub usage: BufferUsage,
+    pub size: vk::DeviceSize,
+    pub memory_type: MemoryType,
+    pub create_mapped: bool,
+    /// Extra alignment of the offset on top of what the usage requires, 0 for none
+    pub alignment: vk::DeviceSize,
+    /// Applies to the whole backing buffer, ownership transfers of exclusive slices move the other slices along
+    pub sharing_mode: SharingMode,
+}
+
+impl Default for BufferSliceDescription {
+    fn default() -> Self {
+        return BufferSliceDescription {
+            usage: BufferUsage::STORAGE,
+            size: 10,
+            memory_type: MemoryType::Auto,
+            create_mapped: false,
+            alignment: 0,
+            sharing_mode: SharingMode::Exclusive,
         };
     }
 }
@@ -195,25 +342,92 @@ impl BitOr<&ImageUsage> for &ImageUsage {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct ImageCreateFlags {
+    pub(crate) flags: vk::ImageCreateFlags,
+}
+
+impl ImageCreateFlags {
+    /// Allows views with a different but compatible format, like sRGB and UNORM views of the same image.
+    pub const MUTABLE_FORMAT: Self = Self { flags: vk::ImageCreateFlags::MUTABLE_FORMAT };
+    /// Lets a `MUTABLE_FORMAT` image carry usages only some of its view formats support, like storage on sRGB images.
+    pub const EXTENDED_USAGE: Self = Self { flags: vk::ImageCreateFlags::EXTENDED_USAGE };
+
+    /// Needed for `Cube` and `CubeArray` views, the image must be 2D, square and have a multiple of 6 layers
+    pub const CUBE_COMPATIBLE: Self = Self { flags: vk::ImageCreateFlags::CUBE_COMPATIBLE };
+    /// Allows `Type2D` and `Type2DArray` views of the slices of a 3D image
+    pub const TYPE_2D_ARRAY_COMPATIBLE: Self = Self {
+        flags: vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE,
+    };
+
+    pub const fn empty() -> Self {
+        return Self { flags: vk::ImageCreateFlags::empty() };
+    }
+
+    pub const fn contains(&self, other: Self) -> bool {
+        return self.flags.as_raw() & other.flags.as_raw() == other.flags.as_raw();
+    }
+
+    /// Converts to raw Vulkan flags
+    pub(crate) fn to_vk_flag(&self) -> vk::ImageCreateFlags {
+        self.flags
+    }
+}
+
+impl BitOr for ImageCreateFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        Self { flags: self.flags | other.flags }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Format {
     // --- Unsigned Normalized (UNORM) Formats - Standard Color & Textures ---
+    R8Unorm,
+    Rg8Unorm,
     Rgba8Unorm,
     Bgra8Unorm,
     Rgb565Unorm,
 
+    // --- sRGB Formats - Color textures authored in sRGB space ---
+    Rgba8Srgb,
+    Bgra8Srgb,
+
     // --- Signed/Unsigned Integers (SINT/UINT) ---
     R32Uint,
     Rgba8Uint,
     Rgba32Sint,
 
     // --- Float Formats (SFLOAT) - High Precision & Data ---
+    R16Float,
+    Rg16Float,
     Rgba16Float,
     Rg32Float,
     Rgb32Float,
     Rgba32Float,
     R32Float,
 
+    // --- Vertex Attribute Formats - Packed, normalized and integer vertex data ---
+    Rgba8Snorm,
+    Rg16Unorm,
+    Rg16Snorm,
+    Rgba16Unorm,
+    Rgba16Snorm,
+    Rg16Uint,
+    Rg16Sint,
+    Rgba16Uint,
+    Rgba16Sint,
+    R32Sint,
+    Rg32Uint,
+    Rg32Sint,
+    Rgb32Uint,
+    Rgb32Sint,
+    Rgba32Uint,
+    Rgb10A2Unorm,
+    Rgb10A2Snorm,
+
     // --- Depth and Stencil Formats ---
     D32Float,
     D24UnormS8Uint,
@@ -221,29 +435,68 @@ pub enum Format {
 
     // --- Block Compressed (Slightly less common but essential for assets) ---
     BC1RgbaUnorm,
+    BC1RgbaSrgb,
+    BC2Unorm,
+    BC2Srgb,
+    BC3Unorm,
+    BC3Srgb,
+    BC4Unorm,
+    BC4Snorm,
+    BC5Unorm,
+    BC5Snorm,
+    BC6HUfloat,
+    BC6HSfloat,
     BC7Unorm,
+    BC7Srgb,
 }
 
 impl Format {
     pub(crate) const fn to_vk_format(&self) -> vk::Format {
         return match self {
             // Unsigned Normalized (UNORM)
+            Self::R8Unorm => vk::Format::R8_UNORM,
+            Self::Rg8Unorm => vk::Format::R8G8_UNORM,
             Self::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
             Self::Bgra8Unorm => vk::Format::B8G8R8A8_UNORM,
             Self::Rgb565Unorm => vk::Format::R5G6B5_UNORM_PACK16,
 
+            // sRGB
+            Self::Rgba8Srgb => vk::Format::R8G8B8A8_SRGB,
+            Self::Bgra8Srgb => vk::Format::B8G8R8A8_SRGB,
+
             // Signed/Unsigned Integers (SINT/UINT)
             Self::R32Uint => vk::Format::R32_UINT,
             Self::Rgba8Uint => vk::Format::R8G8B8A8_UINT,
             Self::Rgba32Sint => vk::Format::R32G32B32A32_SINT,
 
             // Float Formats (SFLOAT)
+            Self::R16Float => vk::Format::R16_SFLOAT,
+            Self::Rg16Float => vk::Format::R16G16_SFLOAT,
             Self::Rgba16Float => vk::Format::R16G16B16A16_SFLOAT,
             Self::Rg32Float => vk::Format::R32G32_SFLOAT,
             Self::Rgb32Float => vk::Format::R32G32B32_SFLOAT,
             Self::Rgba32Float => vk::Format::R32G32B32A32_SFLOAT,
             Self::R32Float => vk::Format::R32_SFLOAT,
 
+            // Vertex Attribute Formats
+            Self::Rgba8Snorm => vk::Format::R8G8B8A8_SNORM,
+            Self::Rg16Unorm => vk::Format::R16G16_UNORM,
+            Self::Rg16Snorm => vk::Format::R16G16_SNORM,
+            Self::Rgba16Unorm => vk::Format::R16G16B16A16_UNORM,
+            Self::Rgba16Snorm => vk::Format::R16G16B16A16_SNORM,
+            Self::Rg16Uint => vk::Format::R16G16_UINT,
+            Self::Rg16Sint => vk::Format::R16G16_SINT,
+            Self::Rgba16Uint => vk::Format::R16G16B16A16_UINT,
+            Self::Rgba16Sint => vk::Format::R16G16B16A16_SINT,
+            Self::R32Sint => vk::Format::R32_SINT,
+            Self::Rg32Uint => vk::Format::R32G32_UINT,
+            Self::Rg32Sint => vk::Format::R32G32_SINT,
+            Self::Rgb32Uint => vk::Format::R32G32B32_UINT,
+            Self::Rgb32Sint => vk::Format::R32G32B32_SINT,
+            Self::Rgba32Uint => vk::Format::R32G32B32A32_UINT,
+            Self::Rgb10A2Unorm => vk::Format::A2B10G10R10_UNORM_PACK32,
+            Self::Rgb10A2Snorm => vk::Format::A2B10G10R10_SNORM_PACK32,
+
             // Depth and Stencil
             Self::D32Float => vk::Format::D32_SFLOAT,
             Self::D24UnormS8Uint => vk::Format::D24_UNORM_S8_UINT,
@@ -251,12 +504,140 @@ impl Format {
 
             // Block Compressed
             Self::BC1RgbaUnorm => vk::Format::BC1_RGBA_UNORM_BLOCK,
+            Self::BC1RgbaSrgb => vk::Format::BC1_RGBA_SRGB_BLOCK,
+            Self::BC2Unorm => vk::Format::BC2_UNORM_BLOCK,
+            Self::BC2Srgb => vk::Format::BC2_SRGB_BLOCK,
+            Self::BC3Unorm => vk::Format::BC3_UNORM_BLOCK,
+            Self::BC3Srgb => vk::Format::BC3_SRGB_BLOCK,
+            Self::BC4Unorm => vk::Format::BC4_UNORM_BLOCK,
+            Self::BC4Snorm => vk::Format::BC4_SNORM_BLOCK,
+            Self::BC5Unorm => vk::Format::BC5_UNORM_BLOCK,
+            Self::BC5Snorm => vk::Format::BC5_SNORM_BLOCK,
+            Self::BC6HUfloat => vk::Format::BC6H_UFLOAT_BLOCK,
+            Self::BC6HSfloat => vk::Format::BC6H_SFLOAT_BLOCK,
             Self::BC7Unorm => vk::Format::BC7_UNORM_BLOCK,
+            Self::BC7Srgb => vk::Format::BC7_SRGB_BLOCK,
+        };
+    }
+
+    /// Inverse of `to_vk_format`, returns None for vulkan formats nexion doesnt expose
+    pub(crate) const fn from_vk_format(format: vk::Format) -> Option<Format> {
+        return match format {
+            vk::Format::R8_UNORM => Some(Self::R8Unorm),
+            vk::Format::R8G8_UNORM => Some(Self::Rg8Unorm),
+            vk::Format::R8G8B8A8_UNORM => Some(Self::Rgba8Unorm),
+            vk::Format::B8G8R8A8_UNORM => Some(Self::Bgra8Unorm),
+            vk::Format::R5G6B5_UNORM_PACK16 => Some(Self::Rgb565Unorm),
+
+            vk::Format::R8G8B8A8_SRGB => Some(Self::Rgba8Srgb),
+            vk::Format::B8G8R8A8_SRGB => Some(Self::Bgra8Srgb),
+
+            vk::Format::R32_UINT => Some(Self::R32Uint),
+            vk::Format::R8G8B8A8_UINT => Some(Self::Rgba8Uint),
+            vk::Format::R32G32B32A32_SINT => Some(Self::Rgba32Sint),
+
+            vk::Format::R16_SFLOAT => Some(Self::R16Float),
+            vk::Format::R16G16_SFLOAT => Some(Self::Rg16Float),
+            vk::Format::R16G16B16A16_SFLOAT => Some(Self::Rgba16Float),
+            vk::Format::R32G32_SFLOAT => Some(Self::Rg32Float),
+            vk::Format::R32G32B32_SFLOAT => Some(Self::Rgb32Float),
+            vk::Format::R32G32B32A32_SFLOAT => Some(Self::Rgba32Float),
+            vk::Format::R32_SFLOAT => Some(Self::R32Float),
+
+            vk::Format::R8G8B8A8_SNORM => Some(Self::Rgba8Snorm),
+            vk::Format::R16G16_UNORM => Some(Self::Rg16Unorm),
+            vk::Format::R16G16_SNORM => Some(Self::Rg16Snorm),
+            vk::Format::R16G16B16A16_UNORM => Some(Self::Rgba16Unorm),
+            vk::Format::R16G16B16A16_SNORM => Some(Self::Rgba16Snorm),
+            vk::Format::R16G16_UINT => Some(Self::Rg16Uint),
+            vk::Format::R16G16_SINT => Some(Self::Rg16Sint),
+            vk::Format::R16G16B16A16_UINT => Some(Self::Rgba16Uint),
+            vk::Format::R16G16B16A16_SINT => Some(Self::Rgba16Sint),
+            vk::Format::R32_SINT => Some(Self::R32Sint),
+            vk::Format::R32G32_UINT => Some(Self::Rg32Uint),
+            vk::Format::R32G32_SINT => Some(Self::Rg32Sint),
+            vk::Format::R32G32B32_UINT => Some(Self::Rgb32Uint),
+            vk::Format::R32G32B32_SINT => Some(Self::Rgb32Sint),
+            vk::Format::R32G32B32A32_UINT => Some(Self::Rgba32Uint),
+            vk::Format::A2B10G10R10_UNORM_PACK32 => Some(Self::Rgb10A2Unorm),
+            vk::Format::A2B10G10R10_SNORM_PACK32 => Some(Self::Rgb10A2Snorm),
+
+            vk::Format::D32_SFLOAT => Some(Self::D32Float),
+            vk::Format::D24_UNORM_S8_UINT => Some(Self::D24UnormS8Uint),
+            vk::Format::D16_UNORM => Some(Self::D16Unorm),
+
+            vk::Format::BC1_RGBA_UNORM_BLOCK => Some(Self::BC1RgbaUnorm),
+            vk::Format::BC1_RGBA_SRGB_BLOCK => Some(Self::BC1RgbaSrgb),
+            vk::Format::BC2_UNORM_BLOCK => Some(Self::BC2Unorm),
+            vk::Format::BC2_SRGB_BLOCK => Some(Self::BC2Srgb),
+            vk::Format::BC3_UNORM_BLOCK => Some(Self::BC3Unorm),
+            vk::Format::BC3_SRGB_BLOCK => Some(Self::BC3Srgb),
+            vk::Format::BC4_UNORM_BLOCK => Some(Self::BC4Unorm),
+            vk::Format::BC4_SNORM_BLOCK => Some(Self::BC4Snorm),
+            vk::Format::BC5_UNORM_BLOCK => Some(Self::BC5Unorm),
+            vk::Format::BC5_SNORM_BLOCK => Some(Self::BC5Snorm),
+            vk::Format::BC6H_UFLOAT_BLOCK => Some(Self::BC6HUfloat),
+            vk::Format::BC6H_SFLOAT_BLOCK => Some(Self::BC6HSfloat),
+            vk::Format::BC7_UNORM_BLOCK => Some(Self::BC7Unorm),
+            vk::Format::BC7_SRGB_BLOCK => Some(Self::BC7Srgb),
+
+            _ => None,
+        };
+    }
+
+    /// Size of one texel block as (width, height, bytes).
+    /// Uncompressed formats have 1x1 blocks, block compressed formats have 4x4 blocks.
+    pub const fn texel_block_info(&self) -> (u32, u32, u32) {
+        return match self {
+            Self::R8Unorm => (1, 1, 1),
+            Self::Rg8Unorm | Self::Rgb565Unorm | Self::R16Float | Self::D16Unorm => (1, 1, 2),
+            Self::Rgba8Unorm | Self::Bgra8Unorm | Self::Rgba8Srgb | Self::Bgra8Srgb | Self::R32Uint | Self::Rgba8Uint | Self::Rg16Float | Self::R32Float | Self::D32Float | Self::D24UnormS8Uint => (1, 1, 4),
+            Self::Rgba8Snorm | Self::Rg16Unorm | Self::Rg16Snorm | Self::Rg16Uint | Self::Rg16Sint | Self::R32Sint | Self::Rgb10A2Unorm | Self::Rgb10A2Snorm => (1, 1, 4),
+            Self::Rgba16Float | Self::Rg32Float => (1, 1, 8),
+            Self::Rgba16Unorm | Self::Rgba16Snorm | Self::Rgba16Uint | Self::Rgba16Sint | Self::Rg32Uint | Self::Rg32Sint => (1, 1, 8),
+            Self::Rgb32Float | Self::Rgb32Uint | Self::Rgb32Sint => (1, 1, 12),
+            Self::Rgba32Float | Self::Rgba32Sint | Self::Rgba32Uint => (1, 1, 16),
+            Self::BC1RgbaUnorm | Self::BC1RgbaSrgb | Self::BC4Unorm | Self::BC4Snorm => (4, 4, 8),
+            Self::BC2Unorm | Self::BC2Srgb | Self::BC3Unorm | Self::BC3Srgb | Self::BC5Unorm | Self::BC5Snorm | Self::BC6HUfloat | Self::BC6HSfloat | Self::BC7Unorm | Self::BC7Srgb => (4, 4, 16),
+        };
+    }
+
+    /// Whether an image of this format created with `MUTABLE_FORMAT` can be viewed with the other format.
+    /// Uncompressed color formats are compatible when their texel sizes match, block compressed formats only within the same BC variant
+    /// and depth formats only with themselves.
+    pub fn is_view_compatible(&self, other: Format) -> bool {
+        return self.compatibility_class() == other.compatibility_class();
+    }
+
+    // (kind, size), kind 0 is uncompressed color, 1 is depth and 2 is block compressed
+    const fn compatibility_class(&self) -> (u32, u32) {
+        return match self {
+            Self::D32Float => (1, 0),
+            Self::D24UnormS8Uint => (1, 1),
+            Self::D16Unorm => (1, 2),
+            Self::BC1RgbaUnorm | Self::BC1RgbaSrgb => (2, 1),
+            Self::BC2Unorm | Self::BC2Srgb => (2, 2),
+            Self::BC3Unorm | Self::BC3Srgb => (2, 3),
+            Self::BC4Unorm | Self::BC4Snorm => (2, 4),
+            Self::BC5Unorm | Self::BC5Snorm => (2, 5),
+            Self::BC6HUfloat | Self::BC6HSfloat => (2, 6),
+            Self::BC7Unorm | Self::BC7Srgb => (2, 7),
+            _ => (0, self.texel_block_info().2),
         };
     }
+
+    /// Number of bytes a tightly packed `width x height x depth` region takes in this format
+    pub const fn region_size(&self, width: u32, height: u32, depth: u32) -> u64 {
+        let (block_width, block_height, block_bytes) = self.texel_block_info();
+        let blocks_x = width.div_ceil(block_width) as u64;
+        let blocks_y = height.div_ceil(block_height) as u64;
+
+        return blocks_x * blocks_y * depth as u64 * block_bytes as u64;
+    }
 }
 
 #[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SampleCount {
     Type1,
     Type2,
@@ -279,10 +660,18 @@ impl SampleCount {
             Self::Type64 => vk::SampleCountFlags::TYPE_64,
         };
     }
+
+    pub(crate) const ALL: [SampleCount; 7] = [Self::Type1, Self::Type2, Self::Type4, Self::Type8, Self::Type16, Self::Type32, Self::Type64];
+
+    pub const fn count(&self) -> u32 {
+        return self.to_vk_flags().as_raw();
+    }
 }
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ImageLayout {
     Undefined,
+    /// Initial layout of linear images, unlike `Undefined` transitioning out of it keeps what the host wrote
+    Preinitialized,
     General,
     ColorAttachment,
     DepthStencilAttachment,
@@ -298,6 +687,7 @@ impl ImageLayout {
     pub(crate) const fn to_vk_layout(self) -> vk::ImageLayout {
         match self {
             ImageLayout::Undefined => vk::ImageLayout::UNDEFINED,
+            ImageLayout::Preinitialized => vk::ImageLayout::PREINITIALIZED,
             ImageLayout::General => vk::ImageLayout::GENERAL,
             ImageLayout::ColorAttachment => vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
             ImageLayout::DepthStencilAttachment => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
@@ -310,6 +700,52 @@ impl ImageLayout {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ImageTiling {
+    #[default]
+    Optimal,
+    /// Row major texel layout, combined with `MemoryType::PreferHost` the image can be mapped and accessed directly from the CPU.
+    /// Linear images are limited to 2D, a single mip level and layer, and no multisampling.
+    /// They start in `ImageLayout::Preinitialized` so data written before the first transition is kept.
+    Linear,
+}
+
+impl ImageTiling {
+    pub(crate) const fn to_vk(self) -> vk::ImageTiling {
+        match self {
+            Self::Optimal => vk::ImageTiling::OPTIMAL,
+            Self::Linear => vk::ImageTiling::LINEAR,
+        }
+    }
+
+    pub(crate) const fn initial_layout(self) -> vk::ImageLayout {
+        match self {
+            Self::Optimal => vk::ImageLayout::UNDEFINED,
+            Self::Linear => vk::ImageLayout::PREINITIALIZED,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SharingMode {
+    /// Owned by one queue family at a time, ownership moves with release and acquire barriers
+    #[default]
+    Exclusive,
+    /// Usable from every queue family without ownership transfers, can be slower on some hardware
+    Concurrent,
+}
+
+/// Memory layout of one subresource of a linear image, offsets are relative to `Device::get_image_raw_ptr`
+#[derive(Clone, Copy, Debug)]
+pub struct SubresourceLayout {
+    pub offset: u64,
+    pub size: u64,
+    pub row_pitch: u64,
+    pub array_pitch: u64,
+    pub depth_pitch: u64,
+}
+
+#[derive(Clone, Copy)]
 pub struct ImageDescription {
     pub usage: ImageUsage,
     pub format: Format,
@@ -319,6 +755,11 @@ pub struct ImageDescription {
     pub mip_levels: u32,
     pub array_layers: u32,
     pub samples: SampleCount,
+    pub flags: ImageCreateFlags,
+    pub tiling: ImageTiling,
+    pub sharing_mode: SharingMode,
+    /// Gives the image its own exportable memory allocation
+    pub external_memory: Option<ExternalMemoryHandleType>,
 }
 
 impl Default for ImageDescription {
@@ -332,10 +773,30 @@ impl Default for ImageDescription {
             mip_levels: 1,
             array_layers: 1,
             samples: SampleCount::Type1,
+            flags: ImageCreateFlags::empty(),
+            tiling: ImageTiling::Optimal,
+            sharing_mode: SharingMode::Exclusive,
+            external_memory: None,
         };
     }
 }
 
+/// CPU side copy of a single image subresource, returned by `Device::read_image`.
+/// Rows and slices are tightly packed, block compressed formats are packed per block row.
+pub struct ImageData {
+    pub format: Format,
+    pub extent: Extent3D,
+    pub data: Vec<u8>,
+}
+
+impl ImageData {
+    /// Size of one row of texels (or blocks) in bytes
+    pub const fn row_pitch(&self) -> usize {
+        let (block_width, _, block_bytes) = self.format.texel_block_info();
+        return self.extent.width.div_ceil(block_width) as usize * block_bytes as usize;
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ImageSubresources {
     pub aspect: ImageAspect,
@@ -424,9 +885,74 @@ impl ImageAspect {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComponentSwizzle {
+    #[default]
+    Identity,
+    Zero,
+    One,
+    R,
+    G,
+    B,
+    A,
+}
+
+impl ComponentSwizzle {
+    pub(crate) const fn to_vk(self) -> vk::ComponentSwizzle {
+        match self {
+            Self::Identity => vk::ComponentSwizzle::IDENTITY,
+            Self::Zero => vk::ComponentSwizzle::ZERO,
+            Self::One => vk::ComponentSwizzle::ONE,
+            Self::R => vk::ComponentSwizzle::R,
+            Self::G => vk::ComponentSwizzle::G,
+            Self::B => vk::ComponentSwizzle::B,
+            Self::A => vk::ComponentSwizzle::A,
+        }
+    }
+}
+
+/// Where each channel read through a view comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ComponentMapping {
+    pub r: ComponentSwizzle,
+    pub g: ComponentSwizzle,
+    pub b: ComponentSwizzle,
+    pub a: ComponentSwizzle,
+}
+
+impl ComponentMapping {
+    pub const IDENTITY: Self = Self {
+        r: ComponentSwizzle::Identity,
+        g: ComponentSwizzle::Identity,
+        b: ComponentSwizzle::Identity,
+        a: ComponentSwizzle::Identity,
+    };
+
+    /// Samples single channel images as grayscale with opaque alpha
+    pub const GRAYSCALE: Self = Self {
+        r: ComponentSwizzle::R,
+        g: ComponentSwizzle::R,
+        b: ComponentSwizzle::R,
+        a: ComponentSwizzle::One,
+    };
+
+    pub(crate) const fn to_vk(self) -> vk::ComponentMapping {
+        return vk::ComponentMapping {
+            r: self.r.to_vk(),
+            g: self.g.to_vk(),
+            b: self.b.to_vk(),
+            a: self.a.to_vk(),
+        };
+    }
+}
+
 pub struct ImageViewDescription {
     pub view_type: ImageViewType,
     pub subresources: ImageSubresources,
+    /// Reinterprets the image with another compatible format, the image needs `ImageCreateFlags::MUTABLE_FORMAT`.
+    /// None uses the format of the image.
+    pub format: Option<Format>,
+    pub components: ComponentMapping,
 }
 
 impl Default for ImageViewDescription {
@@ -434,6 +960,8 @@ impl Default for ImageViewDescription {
         return Self {
             view_type: ImageViewType::Type2D,
             subresources: ImageSubresources::default(),
+            format: None,
+            components: ComponentMapping::IDENTITY,
         };
     }
 }
@@ -490,6 +1018,7 @@ impl SamplerAddressMode {
 }
 
 /// Border colors for ClampToBorder
+/// The custom colors need `DeviceDescription::custom_border_colors`
 #[derive(Clone, Copy, Debug)]
 pub enum BorderColor {
     FloatTransparentBlack,
@@ -498,6 +1027,8 @@ pub enum BorderColor {
     IntOpaqueBlack,
     FloatOpaqueWhite,
     IntOpaqueWhite,
+    FloatCustom([f32; 4]),
+    IntCustom([i32; 4]),
 }
 impl BorderColor {
     pub(crate) fn to_vk(self) -> vk::BorderColor {
@@ -508,12 +1039,40 @@ impl BorderColor {
             BorderColor::IntOpaqueBlack => vk::BorderColor::INT_OPAQUE_BLACK,
             BorderColor::FloatOpaqueWhite => vk::BorderColor::FLOAT_OPAQUE_WHITE,
             BorderColor::IntOpaqueWhite => vk::BorderColor::INT_OPAQUE_WHITE,
+            BorderColor::FloatCustom(_) => vk::BorderColor::FLOAT_CUSTOM_EXT,
+            BorderColor::IntCustom(_) => vk::BorderColor::INT_CUSTOM_EXT,
+        }
+    }
+
+    pub(crate) fn custom_color(self) -> Option<vk::ClearColorValue> {
+        match self {
+            BorderColor::FloatCustom(float32) => Some(vk::ClearColorValue { float32 }),
+            BorderColor::IntCustom(int32) => Some(vk::ClearColorValue { int32 }),
+            _ => None,
+        }
+    }
+}
+
+/// How texels in the filter footprint are combined, Min and Max need `DeviceDescription::sampler_filter_minmax`
+#[derive(Clone, Copy, Debug, Default)]
+pub enum SamplerReductionMode {
+    #[default]
+    WeightedAverage,
+    Min,
+    Max,
+}
+impl SamplerReductionMode {
+    pub(crate) fn to_vk(self) -> vk::SamplerReductionMode {
+        match self {
+            SamplerReductionMode::WeightedAverage => vk::SamplerReductionMode::WEIGHTED_AVERAGE,
+            SamplerReductionMode::Min => vk::SamplerReductionMode::MIN,
+            SamplerReductionMode::Max => vk::SamplerReductionMode::MAX,
         }
     }
 }
 
 /// Optional compare operation for depth samplers
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CompareOp {
     Never,
     Less,
@@ -539,6 +1098,8 @@ impl CompareOp {
     }
 }
 
+/// Identical descriptions share one vulkan sampler, see `Device::create_sampler`
+#[derive(Clone, Copy, Debug)]
 pub struct SamplerDescription {
     pub mag_filter: Filter,
     pub min_filter: Filter,
@@ -553,6 +1114,7 @@ pub struct SamplerDescription {
     pub max_lod: f32,
     pub border_color: BorderColor,
     pub unnormalized_coordinates: bool,
+    pub reduction_mode: SamplerReductionMode,
 }
 
 impl Default for SamplerDescription {
@@ -571,6 +1133,7 @@ impl Default for SamplerDescription {
             max_lod: 0.0,
             border_color: BorderColor::IntOpaqueBlack,
             unnormalized_coordinates: false,
+            reduction_mode: SamplerReductionMode::WeightedAverage,
         }
     }
 }
@@ -1,7 +1,7 @@
 use crate::*;
 use crate::{BufferId, ImageViewId, SamplerId};
 use ash::vk;
-use std::{ops::BitOr, u64};
+use std::{marker::PhantomData, ops::BitOr, u64};
 
 ////Descriptors////
 
@@ -44,6 +44,27 @@ impl Default for ImageWriteInfo {
     }
 }
 
+pub enum TexelBufferDescriptorType {
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+}
+
+pub struct BufferViewWriteInfo {
+    pub view: BufferViewId,
+    pub texel_buffer_descriptor_type: TexelBufferDescriptorType,
+    pub index: u32,
+}
+
+impl Default for BufferViewWriteInfo {
+    fn default() -> Self {
+        return BufferViewWriteInfo {
+            view: BufferViewId::null(),
+            texel_buffer_descriptor_type: TexelBufferDescriptorType::UniformTexelBuffer,
+            index: 0,
+        };
+    }
+}
+
 pub struct SamplerWriteInfo {
     pub sampler: SamplerId,
     pub index: u32,
@@ -80,6 +101,27 @@ impl VertexFormat for [u8; 4] {
 impl VertexFormat for u32 {
     const FORMAT: Format = Format::R32Uint;
 }
+impl VertexFormat for [u32; 2] {
+    const FORMAT: Format = Format::Rg32Uint;
+}
+impl VertexFormat for [u32; 3] {
+    const FORMAT: Format = Format::Rgb32Uint;
+}
+impl VertexFormat for [u32; 4] {
+    const FORMAT: Format = Format::Rgba32Uint;
+}
+impl VertexFormat for i32 {
+    const FORMAT: Format = Format::R32Sint;
+}
+impl VertexFormat for [i32; 2] {
+    const FORMAT: Format = Format::Rg32Sint;
+}
+impl VertexFormat for [i32; 3] {
+    const FORMAT: Format = Format::Rgb32Sint;
+}
+impl VertexFormat for [i32; 4] {
+    const FORMAT: Format = Format::Rgba32Sint;
+}
 
 #[derive(Clone, Copy, Debug)]
 pub enum VertexInputRate {
@@ -111,6 +153,18 @@ pub struct VertexAttribute {
     pub offset: u32,
 }
 
+/// Implemented by structs declared with `vertex!` or `#[derive(Vertex)]`
+pub trait Vertex: Copy {
+    const INPUT_RATE: VertexInputRate;
+    /// Locations set with `#[location(n)]` are kept as is instead of following earlier bindings
+    const EXPLICIT_LOCATIONS: bool = false;
+
+    /// Attributes in field order, on binding 0 with locations counted from 0
+    fn attributes() -> Vec<VertexAttribute>;
+}
+
+/// Can be built from several vertex structs, e.g. a per vertex and a per instance stream:
+/// `VertexInputDescription::default().with_binding::<MyVertex>(0).with_binding::<MyInstance>(1)`
 #[derive(Clone)]
 pub struct VertexInputDescription {
     pub bindings: Vec<VertexBinding>,
@@ -124,6 +178,31 @@ impl Default for VertexInputDescription {
 }
 
 impl VertexInputDescription {
+    /// Adds `V` at `binding` with its `Vertex::INPUT_RATE`, its locations continue after the attributes already in the description
+    pub fn with_binding<V: Vertex>(mut self, binding: u32) -> Self {
+        assert!(self.bindings.iter().all(|b| b.binding != binding), "Vertex binding {} is already used", binding);
+
+        let first_location = if V::EXPLICIT_LOCATIONS { 0 } else { self.attributes.iter().map(|a| a.location + 1).max().unwrap_or(0) };
+
+        self.bindings.push(VertexBinding {
+            binding: binding,
+            stride: size_of::<V>() as u32,
+            input_rate: V::INPUT_RATE,
+        });
+
+        self.attributes.extend(V::attributes().into_iter().map(|a| VertexAttribute {
+            location: first_location + a.location,
+            binding: binding,
+            ..a
+        }));
+
+        let mut locations: Vec<u32> = self.attributes.iter().map(|a| a.location).collect();
+        locations.sort_unstable();
+        assert!(locations.windows(2).all(|pair| pair[0] != pair[1]), "Two vertex attributes share a location");
+
+        return self;
+    }
+
     pub fn to_vk(&self) -> (Vec<ash::vk::VertexInputBindingDescription>, Vec<ash::vk::VertexInputAttributeDescription>) {
         let bindings = self
             .bindings
@@ -151,7 +230,7 @@ impl VertexInputDescription {
 }
 
 //// Rasterization pipeline create info ////
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CullMode {
     None,
     Front,
@@ -170,7 +249,7 @@ impl CullMode {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FrontFace {
     Clockwise,
     CounterClockwise,
@@ -221,6 +300,28 @@ impl Default for DepthStencilOptions {
     }
 }
 
+/// Has to match the sample count of the attachments the pipeline renders to
+#[derive(Clone, Copy)]
+pub struct MultisampleOptions {
+    pub samples: SampleCount,
+    /// Runs the fragment shader per sample instead of per pixel
+    pub sample_shading: bool,
+    /// Fraction of samples shaded individually when `sample_shading` is on, 1.0 shades every sample
+    pub min_sample_shading: f32,
+    pub alpha_to_coverage: bool,
+}
+
+impl Default for MultisampleOptions {
+    fn default() -> Self {
+        Self {
+            samples: SampleCount::Type1,
+            sample_shading: false,
+            min_sample_shading: 1.0,
+            alpha_to_coverage: false,
+        }
+    }
+}
+
 //Outputs for dynamic rendering
 #[derive(Clone)]
 pub struct PipelineOutputs<'a> {
@@ -273,9 +374,12 @@ impl BitOr for ShaderStages {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputTopology {
     TriangleList,
+    TriangleStrip,
+    LineList,
+    LineStrip,
     PointList,
 }
 
@@ -283,11 +387,134 @@ impl InputTopology {
     pub(crate) const fn to_vk(&self) -> vk::PrimitiveTopology {
         match self {
             InputTopology::TriangleList => vk::PrimitiveTopology::TRIANGLE_LIST,
+            InputTopology::TriangleStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+            InputTopology::LineList => vk::PrimitiveTopology::LINE_LIST,
+            InputTopology::LineStrip => vk::PrimitiveTopology::LINE_STRIP,
             InputTopology::PointList => vk::PrimitiveTopology::POINT_LIST,
         }
     }
 }
 
+/// Pipeline state that is set while recording instead of being baked into the pipeline.
+/// Viewport and scissor are always dynamic. Can be combined using Bitwise Or (|)
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Default)]
+pub struct DynamicStates {
+    pub(crate) bits: u32,
+}
+
+impl DynamicStates {
+    pub const NONE: Self = Self { bits: 0 };
+    /// `CommandRecorder::set_cull_mode`
+    pub const CULL_MODE: Self = Self { bits: 1 << 0 };
+    /// `CommandRecorder::set_front_face`
+    pub const FRONT_FACE: Self = Self { bits: 1 << 1 };
+    /// `CommandRecorder::set_depth_test_enable`
+    pub const DEPTH_TEST: Self = Self { bits: 1 << 2 };
+    /// `CommandRecorder::set_depth_write_enable`
+    pub const DEPTH_WRITE: Self = Self { bits: 1 << 3 };
+    /// `CommandRecorder::set_depth_compare_op`
+    pub const DEPTH_COMPARE: Self = Self { bits: 1 << 4 };
+    /// `CommandRecorder::set_depth_bias_enable` and `set_depth_bias`
+    pub const DEPTH_BIAS: Self = Self { bits: 1 << 5 };
+    /// `CommandRecorder::set_stencil_test_enable` and `set_stencil_op`
+    pub const STENCIL_OPS: Self = Self { bits: 1 << 6 };
+    /// `CommandRecorder::set_stencil_reference`
+    pub const STENCIL_REFERENCE: Self = Self { bits: 1 << 7 };
+    /// `CommandRecorder::set_stencil_masks`
+    pub const STENCIL_MASKS: Self = Self { bits: 1 << 8 };
+    /// `CommandRecorder::set_blend_constants`
+    pub const BLEND_CONSTANTS: Self = Self { bits: 1 << 9 };
+    /// `CommandRecorder::set_line_width`, widths other than 1.0 need the wide lines feature
+    pub const LINE_WIDTH: Self = Self { bits: 1 << 10 };
+    /// `CommandRecorder::set_primitive_topology`, has to stay in the topology class of the pipeline
+    pub const PRIMITIVE_TOPOLOGY: Self = Self { bits: 1 << 11 };
+    /// Strides passed to `CommandRecorder::bind_vertex_buffers`
+    pub const VERTEX_STRIDE: Self = Self { bits: 1 << 12 };
+
+    pub const fn contains(&self, other: Self) -> bool {
+        return self.bits & other.bits == other.bits;
+    }
+
+    pub(crate) fn to_vk(&self) -> Vec<vk::DynamicState> {
+        let table: [(Self, &[vk::DynamicState]); 13] = [
+            (Self::CULL_MODE, &[vk::DynamicState::CULL_MODE]),
+            (Self::FRONT_FACE, &[vk::DynamicState::FRONT_FACE]),
+            (Self::DEPTH_TEST, &[vk::DynamicState::DEPTH_TEST_ENABLE]),
+            (Self::DEPTH_WRITE, &[vk::DynamicState::DEPTH_WRITE_ENABLE]),
+            (Self::DEPTH_COMPARE, &[vk::DynamicState::DEPTH_COMPARE_OP]),
+            (Self::DEPTH_BIAS, &[vk::DynamicState::DEPTH_BIAS_ENABLE, vk::DynamicState::DEPTH_BIAS]),
+            (Self::STENCIL_OPS, &[vk::DynamicState::STENCIL_TEST_ENABLE, vk::DynamicState::STENCIL_OP]),
+            (Self::STENCIL_REFERENCE, &[vk::DynamicState::STENCIL_REFERENCE]),
+            (Self::STENCIL_MASKS, &[vk::DynamicState::STENCIL_COMPARE_MASK, vk::DynamicState::STENCIL_WRITE_MASK]),
+            (Self::BLEND_CONSTANTS, &[vk::DynamicState::BLEND_CONSTANTS]),
+            (Self::LINE_WIDTH, &[vk::DynamicState::LINE_WIDTH]),
+            (Self::PRIMITIVE_TOPOLOGY, &[vk::DynamicState::PRIMITIVE_TOPOLOGY]),
+            (Self::VERTEX_STRIDE, &[vk::DynamicState::VERTEX_INPUT_BINDING_STRIDE]),
+        ];
+
+        let mut states = vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+
+        for (flag, vk_states) in table {
+            if self.contains(flag) {
+                states.extend_from_slice(vk_states);
+            }
+        }
+
+        return states;
+    }
+}
+
+impl BitOr for DynamicStates {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self { bits: self.bits | rhs.bits }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StencilFaces {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl StencilFaces {
+    pub(crate) const fn to_vk(&self) -> vk::StencilFaceFlags {
+        match self {
+            Self::Front => vk::StencilFaceFlags::FRONT,
+            Self::Back => vk::StencilFaceFlags::BACK,
+            Self::FrontAndBack => vk::StencilFaceFlags::FRONT_AND_BACK,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrementAndClamp,
+    DecrementAndClamp,
+    Invert,
+    IncrementAndWrap,
+    DecrementAndWrap,
+}
+
+impl StencilOp {
+    pub(crate) const fn to_vk(&self) -> vk::StencilOp {
+        match self {
+            Self::Keep => vk::StencilOp::KEEP,
+            Self::Zero => vk::StencilOp::ZERO,
+            Self::Replace => vk::StencilOp::REPLACE,
+            Self::IncrementAndClamp => vk::StencilOp::INCREMENT_AND_CLAMP,
+            Self::DecrementAndClamp => vk::StencilOp::DECREMENT_AND_CLAMP,
+            Self::Invert => vk::StencilOp::INVERT,
+            Self::IncrementAndWrap => vk::StencilOp::INCREMENT_AND_WRAP,
+            Self::DecrementAndWrap => vk::StencilOp::DECREMENT_AND_WRAP,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct PushConstantsDescription {
     pub stage_flags: ShaderStages,
@@ -305,6 +532,46 @@ impl Default for PushConstantsDescription {
     }
 }
 
+impl PushConstantsDescription {
+    pub(crate) fn to_vk(&self) -> vk::PushConstantRange {
+        return vk::PushConstantRange::default().offset(self.offset).size(self.size).stage_flags(self.stage_flags.to_vk());
+    }
+}
+
+/// Push constant range typed by the struct it holds. Put `description()` in the pipeline description
+/// and write it with `CommandRecorder::push_constants`, the size always matches `T`.
+pub struct PushConstants<T> {
+    pub stage_flags: ShaderStages,
+    pub offset: u32,
+    marker: PhantomData<T>,
+}
+
+impl<T> PushConstants<T> {
+    pub const fn new(stage_flags: ShaderStages, offset: u32) -> Self {
+        return Self {
+            stage_flags: stage_flags,
+            offset: offset,
+            marker: PhantomData,
+        };
+    }
+
+    pub const fn description(&self) -> PushConstantsDescription {
+        return PushConstantsDescription {
+            stage_flags: self.stage_flags,
+            offset: self.offset,
+            size: size_of::<T>() as u32,
+        };
+    }
+}
+
+impl<T> Clone for PushConstants<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PushConstants<T> {}
+
 #[derive(Clone)]
 pub enum GeometryStage<'a> {
     Classic { vertex_input: VertexInputDescription, topology: InputTopology, vertex_shader: &'a str },
@@ -317,12 +584,17 @@ pub struct RasterizationPipelineDescription<'a> {
     pub fragment_shader_path: &'a str,
 
     pub push_constants: PushConstantsDescription,
+    /// More ranges next to `push_constants`, a shader stage can only be part of one range
+    pub extra_push_constants: &'a [PushConstantsDescription],
     pub cull_mode: CullMode,
     pub front_face: FrontFace,
     pub polygon_mode: PolygonMode,
     pub depth_stencil: DepthStencilOptions,
+    pub multisample: MultisampleOptions,
     pub alpha_blend_enable: bool,
     pub outputs: PipelineOutputs<'a>,
+    /// State left to the command recorder, the baked values above are ignored for these
+    pub dynamic_states: DynamicStates,
 }
 
 impl<'a> Default for RasterizationPipelineDescription<'a> {
@@ -334,13 +606,16 @@ impl<'a> Default for RasterizationPipelineDescription<'a> {
                 vertex_shader: " ",
             },
             push_constants: PushConstantsDescription::default(),
+            extra_push_constants: &[],
             fragment_shader_path: " ",
             cull_mode: CullMode::None,
             front_face: FrontFace::CounterClockwise,
             polygon_mode: PolygonMode::Fill,
             depth_stencil: DepthStencilOptions::default(),
+            multisample: MultisampleOptions::default(),
             alpha_blend_enable: false,
             outputs: PipelineOutputs::default(),
+            dynamic_states: DynamicStates::NONE,
         }
     }
 }
@@ -350,4 +625,16 @@ impl<'a> Default for RasterizationPipelineDescription<'a> {
 pub struct ComputePipelineDescription {
     pub shader_path: &'static str,
     pub push_constants: PushConstantsDescription,
+    /// More ranges next to `push_constants`, a shader stage can only be part of one range
+    pub extra_push_constants: &'static [PushConstantsDescription],
+}
+
+impl Default for ComputePipelineDescription {
+    fn default() -> Self {
+        Self {
+            shader_path: " ",
+            push_constants: PushConstantsDescription::default(),
+            extra_push_constants: &[],
+        }
+    }
 }
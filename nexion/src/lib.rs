@@ -6,11 +6,14 @@ pub mod utils;
 use std::fs;
 use std::path::Path;
 
-pub use core::{commands::*, device::*, gpu_resources::*, instance::*, swapchain::*};
+pub use core::{api::*, commands::*, device::*, gpu_resources::*, instance::*, swapchain::*};
 pub use definations::{commands::*, core::*, gpu_resources::*, pipelines::*};
 
 pub use memoffset;
 
+#[cfg(feature = "derive")]
+pub use nexion_derive::Vertex;
+
 // For copying the nexion.slang file to your directory.
 
 const NEXION_SHADER: &str = include_str!("nexion.slang");
@@ -44,9 +47,10 @@ macro_rules! vertex {
             $( pub $field: $ty, )*
         }
 
-        impl $name {
-            pub fn vertex_input_description() -> $crate::VertexInputDescription {
-                use std::mem;
+        impl $crate::Vertex for $name {
+            const INPUT_RATE: $crate::VertexInputRate = $crate::VertexInputRate::$rate;
+
+            fn attributes() -> Vec<$crate::VertexAttribute> {
                 let mut location = 0u32;
 
                 let mut attributes = Vec::new();
@@ -60,16 +64,14 @@ macro_rules! vertex {
                     location += 1;
                 )*
 
-                $crate::VertexInputDescription {
-                    bindings: vec![
-                        $crate::VertexBinding {
-                            binding: 0,
-                            stride: mem::size_of::<Self>() as u32,
-                            input_rate: $crate::VertexInputRate::$rate,
-                        }
-                    ],
-                    attributes,
-                }
+                attributes
+            }
+        }
+
+        impl $name {
+            /// Only this struct at binding 0, use `VertexInputDescription::with_binding` to combine several
+            pub fn vertex_input_description() -> $crate::VertexInputDescription {
+                $crate::VertexInputDescription::default().with_binding::<Self>(0)
             }
         }
     };
@@ -0,0 +1,239 @@
+use crate::*;
+use std::path::Path;
+
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = ((bits >> 15) as u32) << 31;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let value = match exponent {
+        0 => {
+            // Zero or subnormal
+            let magnitude = mantissa as f32 / 1024.0 / 16384.0;
+            return if sign != 0 { -magnitude } else { magnitude };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+
+    return f32::from_bits(value);
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+
+    return ((value + 0.055) / 1.055).powf(2.4);
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        return value * 12.92;
+    }
+
+    return 1.055 * value.powf(1.0 / 2.4) - 0.055;
+}
+
+impl ImageData {
+    fn texel_count(&self) -> usize {
+        assert!(self.extent.depth == 1, "Only 2D image data can be saved");
+
+        return (self.extent.width * self.extent.height) as usize;
+    }
+
+    /// Converts to 8 bit sRGB encoded rgba, missing channels are 0 with alpha 1.
+    /// Float formats are linear, they are clamped to 0..1 and encoded. 8 bit unorm formats are copied as they are
+    /// and depth is written as a plain 0..1 gray ramp. Integer formats are rejected.
+    fn to_rgba8(&self) -> Vec<u8> {
+        let texels = self.texel_count();
+        let mut out = Vec::with_capacity(texels * 4);
+
+        match self.format {
+            Format::Rgba8Unorm | Format::Rgba8Srgb => out.extend_from_slice(&self.data[..texels * 4]),
+            Format::Rgba8Uint => panic!("Rgba8Uint holds integers rather than colors, it cant be saved as an image"),
+            Format::Bgra8Unorm | Format::Bgra8Srgb => {
+                for bgra in self.data.chunks_exact(4) {
+                    out.extend_from_slice(&[bgra[2], bgra[1], bgra[0], bgra[3]]);
+                }
+            }
+            Format::R8Unorm => {
+                for &r in &self.data {
+                    out.extend_from_slice(&[r, r, r, 255]);
+                }
+            }
+            Format::Rg8Unorm => {
+                for rg in self.data.chunks_exact(2) {
+                    out.extend_from_slice(&[rg[0], rg[1], 0, 255]);
+                }
+            }
+            _ => {
+                let depth = matches!(self.format, Format::D16Unorm | Format::D32Float | Format::D24UnormS8Uint);
+
+                for (i, value) in self.to_rgba32f().into_iter().enumerate() {
+                    let value = value.clamp(0.0, 1.0);
+                    let value = if depth || i % 4 == 3 { value } else { linear_to_srgb(value) };
+                    out.push((value * 255.0).round() as u8);
+                }
+            }
+        }
+
+        return out;
+    }
+
+    /// Converts to linear float rgba, sRGB formats are decoded and missing channels are 0 with alpha 1
+    fn to_rgba32f(&self) -> Vec<f32> {
+        let texels = self.texel_count();
+        let mut out = Vec::with_capacity(texels * 4);
+
+        let f32_at = |i: usize| f32::from_le_bytes([self.data[i * 4], self.data[i * 4 + 1], self.data[i * 4 + 2], self.data[i * 4 + 3]]);
+        let f16_at = |i: usize| half_to_f32(u16::from_le_bytes([self.data[i * 2], self.data[i * 2 + 1]]));
+
+        match self.format {
+            Format::Rgba32Float => out.extend((0..texels * 4).map(f32_at)),
+            Format::Rgb32Float => {
+                for t in 0..texels {
+                    out.extend_from_slice(&[f32_at(t * 3), f32_at(t * 3 + 1), f32_at(t * 3 + 2), 1.0]);
+                }
+            }
+            Format::Rg32Float => {
+                for t in 0..texels {
+                    out.extend_from_slice(&[f32_at(t * 2), f32_at(t * 2 + 1), 0.0, 1.0]);
+                }
+            }
+            Format::R32Float | Format::D32Float => {
+                for t in 0..texels {
+                    let r = f32_at(t);
+                    out.extend_from_slice(&[r, r, r, 1.0]);
+                }
+            }
+            Format::Rgba16Float => out.extend((0..texels * 4).map(f16_at)),
+            Format::Rg16Float => {
+                for t in 0..texels {
+                    out.extend_from_slice(&[f16_at(t * 2), f16_at(t * 2 + 1), 0.0, 1.0]);
+                }
+            }
+            Format::R16Float => {
+                for t in 0..texels {
+                    let r = f16_at(t);
+                    out.extend_from_slice(&[r, r, r, 1.0]);
+                }
+            }
+            Format::D16Unorm => {
+                for t in 0..texels {
+                    let d = u16::from_le_bytes([self.data[t * 2], self.data[t * 2 + 1]]) as f32 / 65535.0;
+                    out.extend_from_slice(&[d, d, d, 1.0]);
+                }
+            }
+            Format::D24UnormS8Uint => {
+                // Depth aspect copies come out as x8_d24
+                for t in 0..texels {
+                    let bits = u32::from_le_bytes([self.data[t * 4], self.data[t * 4 + 1], self.data[t * 4 + 2], self.data[t * 4 + 3]]);
+                    let d = (bits & 0x00ff_ffff) as f32 / 16777215.0;
+                    out.extend_from_slice(&[d, d, d, 1.0]);
+                }
+            }
+            Format::Rgba8Unorm | Format::Bgra8Unorm | Format::R8Unorm | Format::Rg8Unorm => {
+                out.extend(self.to_rgba8().into_iter().map(|v| v as f32 / 255.0));
+            }
+            Format::Rgba8Srgb | Format::Bgra8Srgb => {
+                for (i, v) in self.to_rgba8().into_iter().enumerate() {
+                    let v = v as f32 / 255.0;
+                    out.push(if i % 4 == 3 { v } else { srgb_to_linear(v) });
+                }
+            }
+            format => panic!("Saving image data with format {:?} is not supported", format),
+        }
+
+        return out;
+    }
+
+    /// Writes the data as an 8 bit sRGB rgba PNG. HDR formats are clamped, nothing is tonemapped.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) {
+        let img = image::RgbaImage::from_raw(self.extent.width, self.extent.height, self.to_rgba8()).expect("Image data is smaller than its extent");
+        img.save_with_format(path, image::ImageFormat::Png).expect("Failed to write PNG file");
+    }
+
+    /// Writes the data as a 32 bit float rgba OpenEXR file, keeping HDR values intact
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) {
+        let img = image::Rgba32FImage::from_raw(self.extent.width, self.extent.height, self.to_rgba32f()).expect("Image data is smaller than its extent");
+        img.save_with_format(path, image::ImageFormat::OpenExr).expect("Failed to write EXR file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_data(format: Format, width: u32, data: Vec<u8>) -> ImageData {
+        return ImageData {
+            format: format,
+            extent: Extent3D { width: width, height: 1, depth: 1 },
+            data: data,
+        };
+    }
+
+    #[test]
+    fn half_to_f32_decodes_every_class() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert!(half_to_f32(0x8000).is_sign_negative());
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.33325195);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        // Smallest subnormal
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x03ff), 1023.0 * 2.0f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(half_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for i in 0..=255 {
+            let value = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn float_formats_are_srgb_encoded() {
+        let values = [0.0f32, 0.5, 2.0, 0.5];
+        let data = image_data(Format::Rgba32Float, 1, values.iter().flat_map(|value| value.to_le_bytes()).collect());
+
+        // Linear 0.5 is 188 in sRGB, values above 1 clamp and alpha stays linear
+        assert_eq!(data.to_rgba8(), [0, 188, 255, 128]);
+        assert_eq!(data.to_rgba32f(), values);
+    }
+
+    #[test]
+    fn half_formats_fill_missing_channels() {
+        let data = image_data(Format::R16Float, 2, [0x3c00u16, 0x0000].iter().flat_map(|value| value.to_le_bytes()).collect());
+
+        assert_eq!(data.to_rgba32f(), [1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(data.to_rgba8(), [255, 255, 255, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn eight_bit_formats_are_copied() {
+        assert_eq!(image_data(Format::Bgra8Srgb, 1, vec![1, 2, 3, 4]).to_rgba8(), [3, 2, 1, 4]);
+        assert_eq!(image_data(Format::Rg8Unorm, 1, vec![10, 20]).to_rgba8(), [10, 20, 0, 255]);
+
+        let decoded = image_data(Format::Rgba8Srgb, 1, vec![188, 0, 255, 128]).to_rgba32f();
+        assert!((decoded[0] - 0.5).abs() < 0.01);
+        assert_eq!(decoded[3], 128.0 / 255.0);
+    }
+
+    #[test]
+    fn depth_is_not_srgb_encoded() {
+        let data = image_data(Format::D16Unorm, 1, 32768u16.to_le_bytes().to_vec());
+        assert_eq!(data.to_rgba8(), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rgba8Uint holds integers rather than colors")]
+    fn integer_formats_are_rejected() {
+        image_data(Format::Rgba8Uint, 1, vec![1, 2, 3, 4]).to_rgba8();
+    }
+}
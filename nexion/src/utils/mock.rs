@@ -0,0 +1,1218 @@
+use ash::vk::{self, Handle};
+use smallvec::SmallVec;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    backend::{gpu_resources::ResourcePool, pipelines, recording_validator::RecordingValidator},
+    *,
+};
+
+/// Driver free stand in for `Device`, for testing code written against `DeviceApi` without a GPU.
+/// Buffers live in CPU memory and submitted copies, fills and updates are applied to them,
+/// everything else is only recorded. Can be shared between threads.
+pub struct MockDevice {
+    inner: Arc<MockInner>,
+}
+
+/// Stand in for `CommandRecorder` that records into an inspectable list, see `commands`.
+/// Misuse panics like with `DeviceDescription::validate_recording`.
+pub struct MockCommandRecorder {
+    device: Arc<MockInner>,
+    queue_type: QueueType,
+    // Stays the same across recordings like a vulkan command buffer, never null
+    handle: u64,
+    recording: Mutex<MockRecording>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedCommand {
+    BeginRendering { render_area: RenderArea },
+    EndRendering,
+    SetViewport(Viewport),
+    SetScissor(RenderArea),
+    /// Any of the dynamic state setters with the values it was called with
+    SetDynamicState(DynamicStateValue),
+    BindPipeline(Pipeline),
+    BindVertexBuffers { first_binding: u32, buffers: Vec<(BufferId, u64)> },
+    BindIndexBuffer { buffer: BufferId, offset: u64, index_type: IndexType },
+    PushConstants { pipeline: Pipeline, offset: u32, data: Vec<u8> },
+    Draw { pipeline: Option<Pipeline>, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32 },
+    DrawIndexed { pipeline: Option<Pipeline>, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32 },
+    DrawIndirect { pipeline: Option<Pipeline>, buffer: BufferId, offset: u64, draw_count: u32, stride: u32, indexed: bool },
+    DrawMeshTasks { pipeline: Option<Pipeline>, group_count_x: u32, group_count_y: u32, group_count_z: u32 },
+    Dispatch { pipeline: Option<Pipeline>, group_count_x: u32, group_count_y: u32, group_count_z: u32 },
+    DispatchIndirect { pipeline: Option<Pipeline>, buffer: BufferId, offset: u64 },
+    PipelineBarrier { count: usize },
+    UseImage { image: ImageId, subresources: Option<ImageSubresources>, usage: Usage },
+    UseBuffer { buffer: BufferId, usage: Usage },
+    ResetQueryPool { pool: QueryPool, first_query: u32, query_count: u32 },
+    BeginQuery { pool: QueryPool, query: u32, precise: bool },
+    EndQuery { pool: QueryPool, query: u32 },
+    WriteTimestamp { pool: QueryPool, query: u32, stage: PipelineStage },
+    CopyBuffer { src_buffer: BufferId, dst_buffer: BufferId, regions: Vec<CopyRegion> },
+    FillBuffer { buffer: BufferId, offset: u64, size: u64, data: u32 },
+    UpdateBuffer { buffer: BufferId, offset: u64, data: Vec<u8> },
+    CopyBufferToImage(BufferImageCopyInfo),
+    CopyImageToBuffer(BufferImageCopyInfo),
+    ClearColorImage(ImageClearInfo),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicStateValue {
+    CullMode(CullMode),
+    FrontFace(FrontFace),
+    DepthTestEnable(bool),
+    DepthWriteEnable(bool),
+    DepthCompareOp(CompareOp),
+    DepthBiasEnable(bool),
+    DepthBias { constant_factor: f32, clamp: f32, slope_factor: f32 },
+    StencilTestEnable(bool),
+    StencilOp { faces: StencilFaces, fail_op: StencilOp, pass_op: StencilOp, depth_fail_op: StencilOp, compare_op: CompareOp },
+    StencilReference { faces: StencilFaces, reference: u32 },
+    StencilMasks { faces: StencilFaces, compare_mask: u32, write_mask: u32 },
+    BlendConstants([f32; 4]),
+    LineWidth(f32),
+    PrimitiveTopology(InputTopology),
+}
+
+impl RecordedCommand {
+    /// Pipeline the draw or dispatch ran with, None for other commands
+    pub fn pipeline(&self) -> Option<Pipeline> {
+        return match self {
+            Self::Draw { pipeline, .. } | Self::DrawIndexed { pipeline, .. } | Self::DrawIndirect { pipeline, .. } | Self::DrawMeshTasks { pipeline, .. } => *pipeline,
+            Self::Dispatch { pipeline, .. } | Self::DispatchIndirect { pipeline, .. } => *pipeline,
+            _ => None,
+        };
+    }
+
+    pub fn is_draw(&self) -> bool {
+        return matches!(self, Self::Draw { .. } | Self::DrawIndexed { .. } | Self::DrawIndirect { .. } | Self::DrawMeshTasks { .. });
+    }
+}
+
+/// What the recorder checks against when a pipeline is bound
+struct MockPipeline {
+    vertex_bindings: SmallVec<[u32; 2]>,
+    mesh_shading: bool,
+    dynamic_states: DynamicStates,
+    // Non empty ranges, the first one is the pipeline's `push_constants` if that isnt empty
+    push_constant_ranges: SmallVec<[PushConstantsDescription; 2]>,
+}
+
+struct MockQueryPool {
+    kind: QueryKind,
+    // Set by submitted end_query and write_timestamp, cleared by resets
+    available: Vec<bool>,
+}
+
+#[derive(Default)]
+struct MockRecording {
+    commands: Vec<RecordedCommand>,
+    bound_graphics: Option<Pipeline>,
+    bound_compute: Option<Pipeline>,
+    mesh_pipeline_bound: bool,
+    bound_dynamic_states: DynamicStates,
+    vertex_strides_bound: bool,
+    one_time_submit: bool,
+    validator: RecordingValidator,
+}
+
+impl MockRecording {
+    #[track_caller]
+    fn check_vertex_strides(&self, command: &str) {
+        if self.bound_dynamic_states.contains(DynamicStates::VERTEX_STRIDE) {
+            assert!(self.vertex_strides_bound, "{} with a DynamicStates::VERTEX_STRIDE pipeline needs strides on the last bind_vertex_buffers", command);
+        }
+    }
+}
+
+struct MockInner {
+    buffers: Mutex<ResourcePool<Vec<u8>>>,
+    images: Mutex<ResourcePool<ImageDescription>>,
+    // Image each view was created from
+    image_views: Mutex<ResourcePool<ImageId>>,
+    samplers: Mutex<ResourcePool<SamplerDescription>>,
+    pipelines: Mutex<ResourcePool<MockPipeline>>,
+    query_pools: Mutex<HashMap<u64, MockQueryPool>>,
+    // Last recording of every command recorder, kept so command buffers can be submitted again
+    recordings: Mutex<HashMap<u64, Vec<RecordedCommand>>>,
+    submitted: Mutex<Vec<RecordedCommand>>,
+    // Source of the fake vulkan handles, 0 stays null
+    next_handle: AtomicU64,
+}
+
+/// A panicking recorder call poisons the lock, the state is still usable for inspecting it afterwards
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    return mutex.lock().unwrap_or_else(PoisonError::into_inner);
+}
+
+impl MockInner {
+    fn buffer_size(&self, id: BufferId) -> u64 {
+        return lock(&self.buffers).get_ref(id.id).len() as u64;
+    }
+
+    fn check_image(&self, id: ImageId) {
+        lock(&self.images).get_ref(id.id);
+    }
+
+    fn new_handle(&self) -> u64 {
+        return self.next_handle.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn execute(&self, command: &RecordedCommand) {
+        let mut buffers = lock(&self.buffers);
+        let mut query_pools = lock(&self.query_pools);
+
+        match command {
+            RecordedCommand::CopyBuffer { src_buffer, dst_buffer, regions } => {
+                for region in regions {
+                    let src = buffers.get_ref(src_buffer.id)[region.src_offset as usize..(region.src_offset + region.size) as usize].to_vec();
+                    buffers.get_mut(dst_buffer.id)[region.dst_offset as usize..(region.dst_offset + region.size) as usize].copy_from_slice(&src);
+                }
+            }
+            RecordedCommand::FillBuffer { buffer, offset, size, data } => {
+                let buffer = buffers.get_mut(buffer.id);
+                let end = if *size == vk::WHOLE_SIZE { buffer.len() } else { (offset + size) as usize };
+
+                for word in buffer[*offset as usize..end].chunks_exact_mut(4) {
+                    word.copy_from_slice(&data.to_ne_bytes());
+                }
+            }
+            RecordedCommand::UpdateBuffer { buffer, offset, data } => {
+                buffers.get_mut(buffer.id)[*offset as usize..*offset as usize + data.len()].copy_from_slice(data);
+            }
+            RecordedCommand::ResetQueryPool { pool, first_query, query_count } => {
+                let pool = query_pools.get_mut(&pool.handle.as_raw()).expect("Query pool was destroyed before the submit");
+                pool.available[*first_query as usize..(first_query + query_count) as usize].fill(false);
+            }
+            RecordedCommand::EndQuery { pool, query } | RecordedCommand::WriteTimestamp { pool, query, .. } => {
+                let pool = query_pools.get_mut(&pool.handle.as_raw()).expect("Query pool was destroyed before the submit");
+                pool.available[*query as usize] = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for MockDevice {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl MockDevice {
+    pub fn new() -> MockDevice {
+        return MockDevice {
+            inner: Arc::new(MockInner {
+                buffers: Mutex::new(ResourcePool::new()),
+                images: Mutex::new(ResourcePool::new()),
+                image_views: Mutex::new(ResourcePool::new()),
+                samplers: Mutex::new(ResourcePool::new()),
+                pipelines: Mutex::new(ResourcePool::new()),
+                query_pools: Mutex::new(HashMap::new()),
+                recordings: Mutex::new(HashMap::new()),
+                submitted: Mutex::new(Vec::new()),
+                next_handle: AtomicU64::new(1),
+            }),
+        };
+    }
+
+    /// Current bytes of the buffer, including the effect of everything submitted so far
+    pub fn buffer_contents(&self, buffer_id: BufferId) -> Vec<u8> {
+        return lock(&self.inner.buffers).get_ref(buffer_id.id).clone();
+    }
+
+    pub fn image_description(&self, image_id: ImageId) -> ImageDescription {
+        return *lock(&self.inner.images).get_ref(image_id.id);
+    }
+
+    /// Image the view was created from
+    pub fn view_image(&self, image_view_id: ImageViewId) -> ImageId {
+        return *lock(&self.inner.image_views).get_ref(image_view_id.id);
+    }
+
+    /// Every command submitted so far in submission order
+    pub fn submitted_commands(&self) -> Vec<RecordedCommand> {
+        return lock(&self.inner.submitted).clone();
+    }
+}
+
+impl DeviceApi for MockDevice {
+    type Recorder = MockCommandRecorder;
+
+    //// Buffers ////
+    fn create_buffer(&self, buffer_desc: &BufferDescription) -> BufferId {
+        let id = lock(&self.inner.buffers).add(vec![0; buffer_desc.size as usize]);
+        return BufferId { id: id };
+    }
+
+    fn destroy_buffer(&self, id: BufferId) {
+        lock(&self.inner.buffers).delete(id.id);
+    }
+
+    fn write_data_to_buffer<T: Copy>(&self, buffer_id: BufferId, data: &[T]) {
+        let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) };
+        lock(&self.inner.buffers).get_mut(buffer_id.id)[..bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Stays valid until the buffer is destroyed, the memory is never moved
+    fn get_raw_ptr(&self, buffer_id: BufferId) -> *mut u8 {
+        return lock(&self.inner.buffers).get_mut(buffer_id.id).as_mut_ptr();
+    }
+
+    //// Images ////
+    fn create_image(&self, image_desc: &ImageDescription) -> ImageId {
+        let id = lock(&self.inner.images).add(*image_desc);
+        return ImageId { id: id };
+    }
+
+    fn destroy_image(&self, image_id: ImageId) {
+        lock(&self.inner.images).delete(image_id.id);
+    }
+
+    fn create_image_view(&self, image_id: ImageId, _image_view_desc: &ImageViewDescription) -> ImageViewId {
+        self.inner.check_image(image_id);
+
+        let id = lock(&self.inner.image_views).add(image_id);
+        return ImageViewId { id: id };
+    }
+
+    fn destroy_image_view(&self, image_view_id: ImageViewId) {
+        lock(&self.inner.image_views).delete(image_view_id.id);
+    }
+
+    //// Samplers ////
+    fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId {
+        let id = lock(&self.inner.samplers).add(*sampler_desc);
+        return SamplerId { id: id };
+    }
+
+    fn destroy_sampler(&self, sampler_id: SamplerId) {
+        lock(&self.inner.samplers).delete(sampler_id.id);
+    }
+
+    //// Pipelines ////
+    fn create_rasterization_pipeline(&self, raster_pipeline_desc: &RasterizationPipelineDescription) -> Pipeline {
+        let pipeline = MockPipeline {
+            vertex_bindings: match &raster_pipeline_desc.geometry {
+                GeometryStage::Classic { vertex_input, .. } => vertex_input.bindings.iter().map(|binding| binding.binding).collect(),
+                GeometryStage::Mesh { .. } => SmallVec::new(),
+            },
+            mesh_shading: matches!(raster_pipeline_desc.geometry, GeometryStage::Mesh { .. }),
+            dynamic_states: raster_pipeline_desc.dynamic_states,
+            push_constant_ranges: push_constant_ranges(&raster_pipeline_desc.push_constants, raster_pipeline_desc.extra_push_constants),
+        };
+
+        return Pipeline::Rasterization(lock(&self.inner.pipelines).add(pipeline));
+    }
+
+    fn create_compute_pipeline(&self, compute_pipeline_desc: &ComputePipelineDescription) -> Pipeline {
+        let pipeline = MockPipeline {
+            vertex_bindings: SmallVec::new(),
+            mesh_shading: false,
+            dynamic_states: DynamicStates::NONE,
+            push_constant_ranges: push_constant_ranges(&compute_pipeline_desc.push_constants, compute_pipeline_desc.extra_push_constants),
+        };
+
+        return Pipeline::Compute(lock(&self.inner.pipelines).add(pipeline));
+    }
+
+    fn destroy_pipeline(&self, pipeline: Pipeline) {
+        lock(&self.inner.pipelines).delete(pipeline.get_raw());
+    }
+
+    //// Descriptors ////
+    // Only the ids are checked, nothing reads the descriptors
+    fn write_buffer(&self, buffer_write_info: &BufferWriteInfo) {
+        self.inner.buffer_size(buffer_write_info.buffer);
+    }
+
+    fn write_image(&self, image_write_info: &ImageWriteInfo) {
+        self.view_image(image_write_info.view);
+    }
+
+    fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo) {
+        lock(&self.inner.samplers).get_ref(sampler_write_info.sampler.id);
+    }
+
+    //// Queries ////
+    fn create_query_pool(&self, kind: QueryKind, count: u32) -> QueryPool {
+        let handle = self.inner.new_handle();
+        lock(&self.inner.query_pools).insert(handle, MockQueryPool { kind: kind, available: vec![false; count as usize] });
+
+        return QueryPool {
+            handle: vk::QueryPool::from_raw(handle),
+            kind: kind,
+            count: count,
+        };
+    }
+
+    fn destroy_query_pool(&self, pool: QueryPool) {
+        lock(&self.inner.query_pools).remove(&pool.handle.as_raw()).expect("Query pool wasnt created by this MockDevice");
+    }
+
+    /// Values are always 0, queries are available once a submitted command buffer ended or wrote them
+    fn get_query_results(&self, pool: QueryPool, first_query: u32, query_count: u32, _wait: bool) -> QueryResults {
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        let query_pools = lock(&self.inner.query_pools);
+        let mock_pool = query_pools.get(&pool.handle.as_raw()).expect("Query pool wasnt created by this MockDevice");
+        let values_per_query = mock_pool.kind.values_per_query();
+
+        return QueryResults {
+            values_per_query: values_per_query,
+            values: vec![0; (query_count * values_per_query) as usize],
+            available: mock_pool.available[first_query as usize..(first_query + query_count) as usize].to_vec(),
+        };
+    }
+
+    fn reset_query_pool(&self, pool: QueryPool, first_query: u32, query_count: u32) {
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        let mut query_pools = lock(&self.inner.query_pools);
+        let mock_pool = query_pools.get_mut(&pool.handle.as_raw()).expect("Query pool wasnt created by this MockDevice");
+        mock_pool.available[first_query as usize..(first_query + query_count) as usize].fill(false);
+    }
+
+    //// Commands ////
+    fn create_command_recorder(&self, queue_type: QueueType) -> MockCommandRecorder {
+        return MockCommandRecorder {
+            device: self.inner.clone(),
+            queue_type: queue_type,
+            handle: self.inner.new_handle(),
+            recording: Mutex::new(MockRecording {
+                validator: RecordingValidator::new(true),
+                ..Default::default()
+            }),
+        };
+    }
+
+    /// Applies the buffer writes and query results of the command buffers right away, semaphores are ignored.
+    /// Command buffers can be submitted again until their recorder records the next time.
+    #[track_caller]
+    fn submit(&self, submit_info: &QueueSubmitInfo) {
+        for command_buffer in submit_info.command_buffers {
+            assert!(command_buffer.handle != vk::CommandBuffer::null(), "Submitted a null command buffer");
+
+            let commands = lock(&self.inner.recordings)
+                .get(&command_buffer.handle.as_raw())
+                .cloned()
+                .expect("Submitted a command buffer that wasnt recorded by a MockCommandRecorder of this device");
+
+            for command in &commands {
+                self.inner.execute(command);
+            }
+
+            lock(&self.inner.submitted).extend(commands);
+        }
+    }
+
+    fn wait_idle(&self) {}
+
+    fn wait_queue(&self, _queue_type: QueueType) {}
+
+    //// Sync ////
+    fn create_fence(&self, _signaled: bool) -> Fence {
+        return Fence { handle: vk::Fence::null() };
+    }
+
+    fn wait_fence(&self, _fence: Fence) {}
+
+    fn reset_fence(&self, _fence: Fence) {}
+
+    fn destroy_fence(&self, _fence: Fence) {}
+
+    fn create_binary_semaphore(&self) -> Semaphore {
+        return Semaphore::Binary(BinarySemaphore { handle: vk::Semaphore::null() });
+    }
+
+    fn create_timeline_semaphore(&self) -> Semaphore {
+        return Semaphore::Timeline(TimelineSemaphore { handle: vk::Semaphore::null() });
+    }
+
+    fn destroy_semaphore(&self, _semaphore: Semaphore) {}
+}
+
+/// Same ranges the real pipeline layout gets, empty ones are dropped
+fn push_constant_ranges(primary: &PushConstantsDescription, extra: &[PushConstantsDescription]) -> SmallVec<[PushConstantsDescription; 2]> {
+    return std::iter::once(primary).chain(extra).filter(|range| range.size > 0).copied().collect();
+}
+
+impl MockCommandRecorder {
+    /// Commands of the current or last recording
+    pub fn commands(&self) -> Vec<RecordedCommand> {
+        return lock(&self.recording).commands.clone();
+    }
+
+    /// Draws recorded with `pipeline` bound
+    pub fn draw_count(&self, pipeline: Pipeline) -> usize {
+        return lock(&self.recording).commands.iter().filter(|command| command.is_draw() && command.pipeline() == Some(pipeline)).count();
+    }
+
+    fn push(&self, command: RecordedCommand) {
+        lock(&self.recording).commands.push(command);
+    }
+
+    #[track_caller]
+    fn set_dynamic_state(&self, value: DynamicStateValue, state: DynamicStates, command: &str, flag: &str) {
+        let recording = lock(&self.recording);
+        recording.validator.command(command);
+        assert!(recording.bound_dynamic_states.contains(state), "{} needs a bound graphics pipeline created with DynamicStates::{}", command, flag);
+
+        drop(recording);
+        self.push(RecordedCommand::SetDynamicState(value));
+    }
+}
+
+impl CommandRecorderApi for MockCommandRecorder {
+    fn reset(&mut self) {
+        lock(&self.recording).validator.reset();
+    }
+
+    #[track_caller]
+    fn begin_recording(&mut self, usage: CommandBufferUsage) {
+        let mut recording = lock(&self.recording);
+        recording.validator.begin(false);
+        recording.one_time_submit = matches!(usage, CommandBufferUsage::OneTimeSubmit);
+
+        recording.commands.clear();
+        recording.bound_graphics = None;
+        recording.bound_compute = None;
+        recording.mesh_pipeline_bound = false;
+        recording.bound_dynamic_states = DynamicStates::NONE;
+        recording.vertex_strides_bound = false;
+    }
+
+    #[track_caller]
+    fn end_recording(&mut self) -> ExecutableCommandBuffer {
+        let mut recording = lock(&self.recording);
+        recording.validator.end();
+
+        lock(&self.device.recordings).insert(self.handle, recording.commands.clone());
+
+        return ExecutableCommandBuffer {
+            handle: vk::CommandBuffer::from_raw(self.handle),
+            prologue: vk::CommandBuffer::null(),
+            queue_type: self.queue_type,
+        };
+    }
+
+    //// Queries ////
+    #[track_caller]
+    fn reset_query_pool(&mut self, pool: QueryPool, first_query: u32, query_count: u32) {
+        lock(&self.recording).validator.outside_rendering("reset_query_pool");
+        assert!(first_query + query_count <= pool.count, "Query range out of bounds");
+
+        self.push(RecordedCommand::ResetQueryPool { pool: pool, first_query: first_query, query_count: query_count });
+    }
+
+    #[track_caller]
+    fn begin_query(&mut self, pool: QueryPool, query: u32, precise: bool) {
+        lock(&self.recording).validator.command("begin_query");
+        assert!(pool.kind != QueryKind::Timestamp, "Timestamp queries are written with write_timestamp");
+        assert!(query < pool.count, "Query index out of bounds");
+
+        self.push(RecordedCommand::BeginQuery { pool: pool, query: query, precise: precise });
+    }
+
+    #[track_caller]
+    fn end_query(&mut self, pool: QueryPool, query: u32) {
+        lock(&self.recording).validator.command("end_query");
+        assert!(query < pool.count, "Query index out of bounds");
+
+        self.push(RecordedCommand::EndQuery { pool: pool, query: query });
+    }
+
+    #[track_caller]
+    fn write_timestamp(&mut self, pool: QueryPool, query: u32, stage: PipelineStage) {
+        lock(&self.recording).validator.command("write_timestamp");
+        assert!(pool.kind == QueryKind::Timestamp, "Timestamps need a QueryKind::Timestamp pool");
+        assert!(query < pool.count, "Query index out of bounds");
+
+        self.push(RecordedCommand::WriteTimestamp { pool: pool, query: query, stage: stage });
+    }
+
+    //// Rendering ////
+    #[track_caller]
+    fn begin_rendering(&mut self, rendering_begin_info: &RenderingBeginInfo) {
+        lock(&self.recording).validator.begin_rendering();
+        self.push(RecordedCommand::BeginRendering { render_area: rendering_begin_info.render_area });
+    }
+
+    #[track_caller]
+    fn end_rendering(&self) {
+        lock(&self.recording).validator.end_rendering();
+        self.push(RecordedCommand::EndRendering);
+    }
+
+    #[track_caller]
+    fn set_viewport_and_scissor(&self, width: u32, height: u32) {
+        lock(&self.recording).validator.command("set_viewport_and_scissor");
+
+        self.push(RecordedCommand::SetViewport(Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }));
+        self.push(RecordedCommand::SetScissor(RenderArea {
+            offset: Offset2D { x: 0, y: 0 },
+            extent: Extent2D { width: width, height: height },
+        }));
+    }
+
+    #[track_caller]
+    fn set_viewport(&self, viewport: &Viewport) {
+        lock(&self.recording).validator.command("set_viewport");
+        self.push(RecordedCommand::SetViewport(*viewport));
+    }
+
+    #[track_caller]
+    fn set_scissor(&self, scissor: &RenderArea) {
+        lock(&self.recording).validator.command("set_scissor");
+        self.push(RecordedCommand::SetScissor(*scissor));
+    }
+
+    //// Dynamic state ////
+    #[track_caller]
+    fn set_cull_mode(&self, cull_mode: CullMode) {
+        self.set_dynamic_state(DynamicStateValue::CullMode(cull_mode), DynamicStates::CULL_MODE, "set_cull_mode", "CULL_MODE");
+    }
+
+    #[track_caller]
+    fn set_front_face(&self, front_face: FrontFace) {
+        self.set_dynamic_state(DynamicStateValue::FrontFace(front_face), DynamicStates::FRONT_FACE, "set_front_face", "FRONT_FACE");
+    }
+
+    #[track_caller]
+    fn set_depth_test_enable(&self, enable: bool) {
+        self.set_dynamic_state(DynamicStateValue::DepthTestEnable(enable), DynamicStates::DEPTH_TEST, "set_depth_test_enable", "DEPTH_TEST");
+    }
+
+    #[track_caller]
+    fn set_depth_write_enable(&self, enable: bool) {
+        self.set_dynamic_state(DynamicStateValue::DepthWriteEnable(enable), DynamicStates::DEPTH_WRITE, "set_depth_write_enable", "DEPTH_WRITE");
+    }
+
+    #[track_caller]
+    fn set_depth_compare_op(&self, compare_op: CompareOp) {
+        self.set_dynamic_state(DynamicStateValue::DepthCompareOp(compare_op), DynamicStates::DEPTH_COMPARE, "set_depth_compare_op", "DEPTH_COMPARE");
+    }
+
+    #[track_caller]
+    fn set_depth_bias_enable(&self, enable: bool) {
+        self.set_dynamic_state(DynamicStateValue::DepthBiasEnable(enable), DynamicStates::DEPTH_BIAS, "set_depth_bias_enable", "DEPTH_BIAS");
+    }
+
+    #[track_caller]
+    fn set_depth_bias(&self, constant_factor: f32, clamp: f32, slope_factor: f32) {
+        self.set_dynamic_state(DynamicStateValue::DepthBias { constant_factor: constant_factor, clamp: clamp, slope_factor: slope_factor }, DynamicStates::DEPTH_BIAS, "set_depth_bias", "DEPTH_BIAS");
+    }
+
+    #[track_caller]
+    fn set_stencil_test_enable(&self, enable: bool) {
+        self.set_dynamic_state(DynamicStateValue::StencilTestEnable(enable), DynamicStates::STENCIL_OPS, "set_stencil_test_enable", "STENCIL_OPS");
+    }
+
+    #[track_caller]
+    fn set_stencil_op(&self, faces: StencilFaces, fail_op: StencilOp, pass_op: StencilOp, depth_fail_op: StencilOp, compare_op: CompareOp) {
+        self.set_dynamic_state(DynamicStateValue::StencilOp { faces: faces, fail_op: fail_op, pass_op: pass_op, depth_fail_op: depth_fail_op, compare_op: compare_op }, DynamicStates::STENCIL_OPS, "set_stencil_op", "STENCIL_OPS");
+    }
+
+    #[track_caller]
+    fn set_stencil_reference(&self, faces: StencilFaces, reference: u32) {
+        self.set_dynamic_state(DynamicStateValue::StencilReference { faces: faces, reference: reference }, DynamicStates::STENCIL_REFERENCE, "set_stencil_reference", "STENCIL_REFERENCE");
+    }
+
+    #[track_caller]
+    fn set_stencil_masks(&self, faces: StencilFaces, compare_mask: u32, write_mask: u32) {
+        self.set_dynamic_state(DynamicStateValue::StencilMasks { faces: faces, compare_mask: compare_mask, write_mask: write_mask }, DynamicStates::STENCIL_MASKS, "set_stencil_masks", "STENCIL_MASKS");
+    }
+
+    #[track_caller]
+    fn set_blend_constants(&self, constants: [f32; 4]) {
+        self.set_dynamic_state(DynamicStateValue::BlendConstants(constants), DynamicStates::BLEND_CONSTANTS, "set_blend_constants", "BLEND_CONSTANTS");
+    }
+
+    #[track_caller]
+    fn set_line_width(&self, width: f32) {
+        self.set_dynamic_state(DynamicStateValue::LineWidth(width), DynamicStates::LINE_WIDTH, "set_line_width", "LINE_WIDTH");
+    }
+
+    #[track_caller]
+    fn set_primitive_topology(&self, topology: InputTopology) {
+        self.set_dynamic_state(DynamicStateValue::PrimitiveTopology(topology), DynamicStates::PRIMITIVE_TOPOLOGY, "set_primitive_topology", "PRIMITIVE_TOPOLOGY");
+    }
+
+    //// Pipelines ////
+    /// Writes at the offset of the pipeline's `push_constants` range
+    #[track_caller]
+    fn set_push_constants<T>(&self, push_constants: &T, pipeline: Pipeline) {
+        let offset = lock(&self.device.pipelines).get_ref(pipeline.get_raw()).push_constant_ranges.first().map_or(0, |range| range.offset);
+
+        self.update_push_constants(pipeline, offset, push_constants);
+    }
+
+    #[track_caller]
+    fn push_constants<T>(&self, pipeline: Pipeline, range: &PushConstants<T>, data: &T) {
+        self.update_push_constants(pipeline, range.offset, data);
+    }
+
+    #[track_caller]
+    fn update_push_constants<T>(&self, pipeline: Pipeline, offset: u32, data: &T) {
+        lock(&self.recording).validator.push_constants(pipeline);
+
+        let size = size_of::<T>() as u32;
+        pipelines::push_constant_stages(&lock(&self.device.pipelines).get_ref(pipeline.get_raw()).push_constant_ranges, offset, size);
+
+        let bytes = unsafe { std::slice::from_raw_parts(data as *const T as *const u8, size as usize) };
+        self.push(RecordedCommand::PushConstants { pipeline: pipeline, offset: offset, data: bytes.to_vec() });
+    }
+
+    #[track_caller]
+    fn bind_pipeline(&self, pipeline: Pipeline) {
+        let pipelines = lock(&self.device.pipelines);
+        let slot = pipelines.get_ref(pipeline.get_raw());
+
+        let mut recording = lock(&self.recording);
+        recording.validator.bind_pipeline(pipeline, &slot.vertex_bindings);
+
+        match pipeline {
+            Pipeline::Rasterization(_) => {
+                recording.bound_graphics = Some(pipeline);
+                recording.mesh_pipeline_bound = slot.mesh_shading;
+                recording.bound_dynamic_states = slot.dynamic_states;
+            }
+            Pipeline::Compute(_) => recording.bound_compute = Some(pipeline),
+        }
+
+        recording.commands.push(RecordedCommand::BindPipeline(pipeline));
+    }
+
+    #[track_caller]
+    fn bind_vertex_buffer(&mut self, buffer_id: BufferId, offset: u64) {
+        self.bind_vertex_buffers(0, &[(buffer_id, offset)], None);
+    }
+
+    #[track_caller]
+    fn bind_vertex_buffers(&mut self, first_binding: u32, buffers: &[(BufferId, u64)], strides: Option<&[u64]>) {
+        let mut recording = lock(&self.recording);
+        recording.validator.bind_vertex_buffers(first_binding, buffers.len() as u32);
+
+        if let Some(strides) = strides {
+            assert!(strides.len() == buffers.len(), "Need one stride per vertex buffer");
+        }
+        recording.vertex_strides_bound = strides.is_some();
+
+        for (buffer, _) in buffers {
+            self.device.buffer_size(*buffer);
+        }
+
+        recording.commands.push(RecordedCommand::BindVertexBuffers { first_binding: first_binding, buffers: buffers.to_vec() });
+    }
+
+    #[track_caller]
+    fn bind_index_buffer(&mut self, buffer_id: BufferId, offset: u64, index_type: IndexType) {
+        lock(&self.recording).validator.bind_index_buffer();
+        self.device.buffer_size(buffer_id);
+
+        self.push(RecordedCommand::BindIndexBuffer { buffer: buffer_id, offset: offset, index_type: index_type });
+    }
+
+    //// Draw commands ////
+    #[track_caller]
+    fn draw(&self, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
+        let mut recording = lock(&self.recording);
+        recording.validator.draw("draw", false);
+        recording.check_vertex_strides("draw");
+
+        let pipeline = recording.bound_graphics;
+        recording.commands.push(RecordedCommand::Draw {
+            pipeline: pipeline,
+            vertex_count: vertex_count,
+            instance_count: instance_count,
+            first_vertex: first_vertex,
+            first_instance: first_instance,
+        });
+    }
+
+    #[track_caller]
+    fn draw_indexed(&self, index_count: u32, instance_count: u32, first_index: u32, vertex_offset: i32, first_instance: u32) {
+        let mut recording = lock(&self.recording);
+        recording.validator.draw("draw_indexed", true);
+        recording.check_vertex_strides("draw_indexed");
+
+        let pipeline = recording.bound_graphics;
+        recording.commands.push(RecordedCommand::DrawIndexed {
+            pipeline: pipeline,
+            index_count: index_count,
+            instance_count: instance_count,
+            first_index: first_index,
+            vertex_offset: vertex_offset,
+            first_instance: first_instance,
+        });
+    }
+
+    #[track_caller]
+    fn draw_indirect(&mut self, info: &DrawIndirectInfo) {
+        let mut recording = lock(&self.recording);
+        recording.validator.draw("draw_indirect", false);
+        recording.check_vertex_strides("draw_indirect");
+        self.device.buffer_size(info.buffer);
+
+        let pipeline = recording.bound_graphics;
+        recording.commands.push(RecordedCommand::DrawIndirect {
+            pipeline: pipeline,
+            buffer: info.buffer,
+            offset: info.offset,
+            draw_count: info.draw_count,
+            stride: info.stride,
+            indexed: false,
+        });
+    }
+
+    #[track_caller]
+    fn draw_indexed_indirect(&mut self, info: &DrawIndexedIndirectInfo) {
+        let mut recording = lock(&self.recording);
+        recording.validator.draw("draw_indexed_indirect", true);
+        recording.check_vertex_strides("draw_indexed_indirect");
+        self.device.buffer_size(info.buffer);
+
+        let pipeline = recording.bound_graphics;
+        recording.commands.push(RecordedCommand::DrawIndirect {
+            pipeline: pipeline,
+            buffer: info.buffer,
+            offset: info.offset,
+            draw_count: info.draw_count,
+            stride: info.stride,
+            indexed: true,
+        });
+    }
+
+    #[track_caller]
+    fn draw_mesh_tasks(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        let mut recording = lock(&self.recording);
+        recording.validator.graphics_work("draw_mesh_tasks");
+        assert!(recording.mesh_pipeline_bound, "Mesh task draws need a pipeline built with GeometryStage::Mesh");
+
+        let pipeline = recording.bound_graphics;
+        recording.commands.push(RecordedCommand::DrawMeshTasks {
+            pipeline: pipeline,
+            group_count_x: group_count_x,
+            group_count_y: group_count_y,
+            group_count_z: group_count_z,
+        });
+    }
+
+    #[track_caller]
+    fn dispatch(&self, info: &DispatchInfo) {
+        let mut recording = lock(&self.recording);
+        recording.validator.dispatch("dispatch");
+
+        let pipeline = recording.bound_compute;
+        recording.commands.push(RecordedCommand::Dispatch {
+            pipeline: pipeline,
+            group_count_x: info.group_count_x,
+            group_count_y: info.group_count_y,
+            group_count_z: info.group_count_z,
+        });
+    }
+
+    #[track_caller]
+    fn dispatch_indirect(&self, info: &DispatchIndirectInfo) {
+        let mut recording = lock(&self.recording);
+        recording.validator.dispatch("dispatch_indirect");
+        self.device.buffer_size(info.buffer);
+
+        let pipeline = recording.bound_compute;
+        recording.commands.push(RecordedCommand::DispatchIndirect { pipeline: pipeline, buffer: info.buffer, offset: info.offset });
+    }
+
+    //// Sync ////
+    #[track_caller]
+    fn pipeline_barrier(&mut self, barriers: &[Barrier]) {
+        lock(&self.recording).validator.command("pipeline_barrier");
+        self.push(RecordedCommand::PipelineBarrier { count: barriers.len() });
+    }
+
+    #[track_caller]
+    fn use_image(&mut self, image_id: ImageId, usage: Usage) {
+        self.use_image_subresources(image_id, None, usage);
+    }
+
+    #[track_caller]
+    fn use_image_subresources(&mut self, image_id: ImageId, subresources: Option<ImageSubresources>, usage: Usage) {
+        let recording = lock(&self.recording);
+        recording.validator.outside_rendering("use_image");
+        assert!(recording.one_time_submit, "Resource usage can only be tracked in recordings begun with CommandBufferUsage::OneTimeSubmit");
+        drop(recording);
+
+        if let Some(sub) = subresources {
+            let images = lock(&self.device.images);
+            let description = images.get_ref(image_id.id);
+            assert!(sub.mip_level < description.mip_levels, "Mip range is out of bounds for the image");
+            assert!(sub.base_array_layer < description.array_layers, "Layer range is out of bounds for the image");
+        } else {
+            self.device.check_image(image_id);
+        }
+
+        self.push(RecordedCommand::UseImage { image: image_id, subresources: subresources, usage: usage });
+    }
+
+    #[track_caller]
+    fn use_buffer(&mut self, buffer_id: BufferId, usage: Usage) {
+        let recording = lock(&self.recording);
+        recording.validator.outside_rendering("use_buffer");
+        assert!(recording.one_time_submit, "Resource usage can only be tracked in recordings begun with CommandBufferUsage::OneTimeSubmit");
+        drop(recording);
+        self.device.buffer_size(buffer_id);
+
+        self.push(RecordedCommand::UseBuffer { buffer: buffer_id, usage: usage });
+    }
+
+    /// Usages are recorded as they are declared, so there is nothing to flush
+    fn flush_barriers(&self) {}
+
+    //// Transfer commands ////
+    #[track_caller]
+    fn copy_buffer(&mut self, buffer_copy_info: &BufferCopyInfo) {
+        lock(&self.recording).validator.outside_rendering("copy_buffer");
+
+        for region in buffer_copy_info.regions {
+            assert!(region.src_offset + region.size <= self.device.buffer_size(buffer_copy_info.src_buffer), "Copy reads past the end of the source buffer");
+            assert!(region.dst_offset + region.size <= self.device.buffer_size(buffer_copy_info.dst_buffer), "Copy writes past the end of the destination buffer");
+        }
+
+        self.push(RecordedCommand::CopyBuffer {
+            src_buffer: buffer_copy_info.src_buffer,
+            dst_buffer: buffer_copy_info.dst_buffer,
+            regions: buffer_copy_info.regions.to_vec(),
+        });
+    }
+
+    #[track_caller]
+    fn fill_buffer(&mut self, info: &BufferFillInfo) {
+        lock(&self.recording).validator.outside_rendering("fill_buffer");
+        self.device.buffer_size(info.buffer);
+
+        self.push(RecordedCommand::FillBuffer {
+            buffer: info.buffer,
+            offset: info.offset,
+            size: info.size,
+            data: info.data,
+        });
+    }
+
+    #[track_caller]
+    fn update_buffer<T: Copy>(&mut self, info: &BufferUpdateInfo<T>) {
+        lock(&self.recording).validator.outside_rendering("update_buffer");
+
+        let bytes = unsafe { std::slice::from_raw_parts(info.data.as_ptr() as *const u8, std::mem::size_of_val(info.data)) };
+        assert!(bytes.len() <= 65536);
+        assert!(info.offset + bytes.len() as u64 <= self.device.buffer_size(info.buffer), "Update writes past the end of the buffer");
+
+        self.push(RecordedCommand::UpdateBuffer { buffer: info.buffer, offset: info.offset, data: bytes.to_vec() });
+    }
+
+    #[track_caller]
+    fn copy_buffer_to_image(&mut self, info: &BufferImageCopyInfo) {
+        lock(&self.recording).validator.outside_rendering("copy_buffer_to_image");
+        self.device.buffer_size(info.buffer);
+        self.device.check_image(info.image);
+
+        self.push(RecordedCommand::CopyBufferToImage(*info));
+    }
+
+    #[track_caller]
+    fn copy_image_to_buffer(&mut self, info: &BufferImageCopyInfo) {
+        lock(&self.recording).validator.outside_rendering("copy_image_to_buffer");
+        self.device.buffer_size(info.buffer);
+        self.device.check_image(info.image);
+
+        self.push(RecordedCommand::CopyImageToBuffer(*info));
+    }
+
+    #[track_caller]
+    fn clear_color_image(&mut self, info: &ImageClearInfo) {
+        lock(&self.recording).validator.outside_rendering("clear_color_image");
+        assert!(!matches!(info.value, ClearValue::DepthStencil { .. }), "Color images need a color clear value");
+        self.device.check_image(info.image);
+
+        self.push(RecordedCommand::ClearColorImage(*info));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written against the shared traits, the same function records with a real `Device`
+    fn record_pass<D: DeviceApi>(device: &D, pipeline: Pipeline, draws: u32) -> (D::Recorder, ExecutableCommandBuffer) {
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.begin_rendering(&RenderingBeginInfo::default());
+        recorder.bind_pipeline(pipeline);
+        recorder.set_viewport_and_scissor(64, 64);
+
+        for draw in 0..draws {
+            recorder.draw(3, 1, 3 * draw, 0);
+        }
+
+        recorder.end_rendering();
+        let executable = recorder.end_recording();
+
+        return (recorder, executable);
+    }
+
+    fn submit(device: &MockDevice, command_buffers: &[ExecutableCommandBuffer]) {
+        device.submit(&QueueSubmitInfo {
+            fence: None,
+            command_buffers: command_buffers,
+            wait_semaphores: &[],
+            signal_semaphores: &[],
+        });
+    }
+
+    #[test]
+    fn pass_issues_three_draws_with_pipeline() {
+        let device = MockDevice::new();
+        let pipeline = device.create_rasterization_pipeline(&RasterizationPipelineDescription::default());
+        let other = device.create_rasterization_pipeline(&RasterizationPipelineDescription::default());
+
+        let (recorder, executable) = record_pass(&device, pipeline, 3);
+
+        assert_eq!(recorder.draw_count(pipeline), 3);
+        assert_eq!(recorder.draw_count(other), 0);
+
+        submit(&device, &[executable]);
+        let draws: Vec<RecordedCommand> = device.submitted_commands().into_iter().filter(RecordedCommand::is_draw).collect();
+
+        assert_eq!(draws.len(), 3);
+        assert!(draws.iter().all(|draw| draw.pipeline() == Some(pipeline)));
+    }
+
+    #[test]
+    fn set_push_constants_writes_at_first_range_offset() {
+        let device = MockDevice::new();
+        let pipeline = device.create_rasterization_pipeline(&RasterizationPipelineDescription {
+            push_constants: PushConstantsDescription {
+                stage_flags: ShaderStages::ALL,
+                offset: 16,
+                size: 8,
+            },
+            ..Default::default()
+        });
+
+        let (mut recorder, _) = record_pass(&device, pipeline, 0);
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.bind_pipeline(pipeline);
+        recorder.set_push_constants(&[1u32, 2u32], pipeline);
+
+        let data = [1u32, 2u32].iter().flat_map(|value| value.to_ne_bytes()).collect();
+        assert_eq!(recorder.commands().last(), Some(&RecordedCommand::PushConstants { pipeline: pipeline, offset: 16, data: data }));
+    }
+
+    #[test]
+    fn resubmit_applies_the_recording_again() {
+        let device = MockDevice::new();
+        let buffer = device.create_buffer(&BufferDescription { size: 8, ..Default::default() });
+        let mut recorder = device.create_command_recorder(QueueType::Transfer);
+
+        recorder.begin_recording(CommandBufferUsage::SimultaneousUse);
+        recorder.fill_buffer(&BufferFillInfo {
+            buffer: buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+            data: 7,
+        });
+        let executable = recorder.end_recording();
+
+        submit(&device, &[executable]);
+        device.write_data_to_buffer(buffer, &[0u32, 0u32]);
+        submit(&device, &[executable]);
+
+        assert_eq!(device.buffer_contents(buffer), [7u32, 7u32].iter().flat_map(|value| value.to_ne_bytes()).collect::<Vec<u8>>());
+        assert_eq!(device.submitted_commands().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "only be tracked in recordings begun with CommandBufferUsage::OneTimeSubmit")]
+    fn use_buffer_rejects_resubmittable_recordings() {
+        let device = MockDevice::new();
+        let buffer = device.create_buffer(&BufferDescription { size: 8, ..Default::default() });
+        let mut recorder = device.create_command_recorder(QueueType::Transfer);
+
+        recorder.begin_recording(CommandBufferUsage::SimultaneousUse);
+        recorder.use_buffer(buffer, Usage::TransferDst);
+    }
+
+    #[test]
+    #[should_panic(expected = "Mip range is out of bounds for the image")]
+    fn use_image_subresources_rejects_missing_mips() {
+        let device = MockDevice::new();
+        let image = device.create_image(&ImageDescription { mip_levels: 2, ..Default::default() });
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.use_image_subresources(
+            image,
+            Some(ImageSubresources {
+                aspect: ImageAspect::Color,
+                mip_level: 2,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            }),
+            Usage::ShaderRead,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Submitted a null command buffer")]
+    fn submit_rejects_null_command_buffer() {
+        let device = MockDevice::new();
+        submit(&device, &[ExecutableCommandBuffer {
+            handle: vk::CommandBuffer::null(),
+            prologue: vk::CommandBuffer::null(),
+            queue_type: QueueType::Graphics,
+        }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "wasnt recorded by a MockCommandRecorder of this device")]
+    fn submit_rejects_foreign_command_buffer() {
+        let device = MockDevice::new();
+        let other = MockDevice::new();
+        let pipeline = other.create_rasterization_pipeline(&RasterizationPipelineDescription::default());
+
+        let (_, executable) = record_pass(&other, pipeline, 1);
+        submit(&device, &[executable]);
+    }
+
+    #[test]
+    #[should_panic(expected = "set_cull_mode needs a bound graphics pipeline created with DynamicStates::CULL_MODE")]
+    fn dynamic_state_needs_pipeline_flag() {
+        let device = MockDevice::new();
+        let pipeline = device.create_rasterization_pipeline(&RasterizationPipelineDescription::default());
+
+        let (mut recorder, _) = record_pass(&device, pipeline, 0);
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.bind_pipeline(pipeline);
+        recorder.set_cull_mode(CullMode::Back);
+    }
+
+    fn record_strided_draw(strides: Option<&[u64]>) {
+        let device = MockDevice::new();
+        let buffer = device.create_buffer(&BufferDescription { size: 64, ..Default::default() });
+        let pipeline = device.create_rasterization_pipeline(&RasterizationPipelineDescription {
+            dynamic_states: DynamicStates::VERTEX_STRIDE,
+            ..Default::default()
+        });
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.begin_rendering(&RenderingBeginInfo::default());
+        // Binding vertex buffers before the pipeline is fine, the strides are checked when drawing
+        recorder.bind_vertex_buffers(0, &[(buffer, 0)], strides);
+        recorder.bind_pipeline(pipeline);
+        recorder.draw(3, 1, 0, 0);
+        recorder.end_rendering();
+    }
+
+    #[test]
+    fn vertex_strides_bound_before_pipeline() {
+        record_strided_draw(Some(&[16]));
+    }
+
+    #[test]
+    #[should_panic(expected = "draw with a DynamicStates::VERTEX_STRIDE pipeline needs strides on the last bind_vertex_buffers")]
+    fn vertex_stride_pipeline_needs_strides() {
+        record_strided_draw(None);
+    }
+
+    #[test]
+    fn mesh_draws_and_dynamic_state_are_recorded() {
+        let device = MockDevice::new();
+        let pipeline = device.create_rasterization_pipeline(&RasterizationPipelineDescription {
+            geometry: GeometryStage::Mesh { task_shader: None, mesh_shader: " " },
+            dynamic_states: DynamicStates::CULL_MODE | DynamicStates::LINE_WIDTH,
+            ..Default::default()
+        });
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.begin_rendering(&RenderingBeginInfo::default());
+        recorder.bind_pipeline(pipeline);
+        recorder.set_cull_mode(CullMode::Back);
+        recorder.set_line_width(2.0);
+        recorder.draw_mesh_tasks(4, 1, 1);
+        recorder.end_rendering();
+
+        let commands = recorder.commands();
+        assert!(commands.contains(&RecordedCommand::SetDynamicState(DynamicStateValue::CullMode(CullMode::Back))));
+        assert!(commands.contains(&RecordedCommand::SetDynamicState(DynamicStateValue::LineWidth(2.0))));
+        assert_eq!(recorder.draw_count(pipeline), 1);
+    }
+
+    #[test]
+    fn views_samplers_and_usages_check_their_ids() {
+        let device = MockDevice::new();
+        let image = device.create_image(&ImageDescription::default());
+        let view = device.create_image_view(image, &ImageViewDescription::default());
+        let sampler = device.create_sampler(&SamplerDescription::default());
+        let buffer = device.create_buffer(&BufferDescription::default());
+
+        device.write_image(&ImageWriteInfo { view: view, ..Default::default() });
+        device.write_sampler(&SamplerWriteInfo { sampler: sampler, index: 0 });
+        assert_eq!(device.view_image(view), image);
+
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.use_image(image, Usage::ShaderRead);
+        recorder.use_buffer(buffer, Usage::ShaderWrite);
+
+        assert_eq!(
+            recorder.commands(),
+            vec![
+                RecordedCommand::UseImage { image: image, subresources: None, usage: Usage::ShaderRead },
+                RecordedCommand::UseBuffer { buffer: buffer, usage: Usage::ShaderWrite },
+            ]
+        );
+    }
+
+    #[test]
+    fn queries_become_available_after_submit() {
+        let device = MockDevice::new();
+        let pool = device.create_query_pool(QueryKind::Timestamp, 2);
+        let mut recorder = device.create_command_recorder(QueueType::Graphics);
+
+        recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+        recorder.reset_query_pool(pool, 0, 2);
+        recorder.write_timestamp(pool, 1, PipelineStage::BottomOfPipe);
+        let executable = recorder.end_recording();
+
+        assert_eq!(device.get_query_results(pool, 0, 2, false).available, vec![false, false]);
+        submit(&device, &[executable]);
+
+        let results = device.get_query_results(pool, 0, 2, false);
+        assert_eq!(results.available, vec![false, true]);
+        assert_eq!(results.get(1), Some(&[0u64][..]));
+    }
+
+    #[test]
+    fn mock_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<MockDevice>();
+        assert_send_sync::<MockCommandRecorder>();
+    }
+}
@@ -1,2 +1,9 @@
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod render_graph;
 pub mod texture;
+#[cfg(feature = "texture-containers")]
+pub(crate) mod texture_container;
 pub mod vulkan_context;
+#[cfg(feature = "image-writing")]
+mod image_writer;
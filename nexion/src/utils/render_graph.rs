@@ -0,0 +1,1172 @@
+use ash::vk;
+use smallvec::{SmallVec, smallvec};
+use std::{collections::HashSet, fmt::Write, ops::Range};
+
+use crate::{
+    backend::resource_tracker::{self, ResourceState},
+    *,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphImage(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphBuffer(usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum GraphResource {
+    Image(usize),
+    Buffer(usize),
+}
+
+enum ImageSource {
+    Transient(ImageDescription),
+    Imported { image: ImageId, view: Option<ImageViewId> },
+}
+
+struct GraphImageEntry {
+    name: String,
+    source: ImageSource,
+    final_usage: Option<Usage>,
+}
+
+enum BufferSource {
+    Transient(BufferDescription),
+    Imported(BufferId),
+}
+
+struct GraphBufferEntry {
+    name: String,
+    source: BufferSource,
+}
+
+type PassCallback<'a> = Box<dyn FnOnce(&mut CommandRecorder, &GraphResources) + 'a>;
+
+struct Pass<'a> {
+    name: String,
+    queue: QueueType,
+    images: SmallVec<[(GraphImage, Usage); 4]>,
+    buffers: SmallVec<[(GraphBuffer, Usage); 4]>,
+    side_effect: bool,
+    callback: Option<PassCallback<'a>>,
+}
+
+/// Physical resources behind the graph handles while passes execute
+pub struct GraphResources {
+    images: Vec<Option<ImageId>>,
+    image_views: Vec<Option<ImageViewId>>,
+    buffers: Vec<Option<BufferId>>,
+    buffer_addresses: Vec<u64>,
+}
+
+impl GraphResources {
+    pub fn image(&self, image: GraphImage) -> ImageId {
+        return self.images[image.0].expect("Image is not used by any pass that survived culling");
+    }
+
+    /// Transient images with a sampled, storage or attachment usage get a view of the whole image, imported ones only if a view was passed on import
+    pub fn image_view(&self, image: GraphImage) -> ImageViewId {
+        return self.image_views[image.0].expect("Image has no view, pass one to import_image or give the transient image a usage that allows views");
+    }
+
+    pub fn buffer(&self, buffer: GraphBuffer) -> BufferId {
+        return self.buffers[buffer.0].expect("Buffer is not used by any pass that survived culling");
+    }
+
+    pub fn buffer_address(&self, buffer: GraphBuffer) -> u64 {
+        return self.buffer_addresses[buffer.0];
+    }
+}
+
+/// Barrier planned before recording, `old` has one state for the whole resource or one per image subresource
+struct Transition {
+    resource: GraphResource,
+    old: SmallVec<[ResourceState; 1]>,
+    new: ResourceState,
+    src_family: u32,
+    dst_family: u32,
+}
+
+struct Batch {
+    queue: QueueType,
+    passes: Vec<usize>,
+    /// Earlier batches on the other queue this one waits on
+    waits: SmallVec<[usize; 2]>,
+    /// Recorded after the passes, releases to the other queue and final transitions of imported resources
+    end_transitions: Vec<Transition>,
+}
+
+struct ResourcePlan {
+    state: SmallVec<[ResourceState; 1]>,
+    owner: Option<(QueueType, usize)>,
+    exclusive: bool,
+}
+
+struct Plan {
+    batches: Vec<Batch>,
+    /// Indexed like `passes`
+    pre_transitions: Vec<Vec<Transition>>,
+    /// Last queue and state of every used resource
+    final_images: Vec<Option<(QueueType, SmallVec<[ResourceState; 1]>)>>,
+    final_buffers: Vec<Option<(QueueType, ResourceState)>>,
+}
+
+/// Objects created for one frame, destroyed once the graph is reused
+#[derive(Default)]
+struct TransientResources {
+    images: Vec<ImageId>,
+    views: Vec<ImageViewId>,
+    buffers: Vec<BufferId>,
+    memory: Vec<gpu_allocator::vulkan::Allocation>,
+}
+
+/// Owns the command recorders, queue semaphores and transient resources of a frame graph.
+/// Keep one per frame in flight, like command recorders.
+pub struct RenderGraph {
+    device: Device,
+    graphics_recorder: CommandRecorder,
+    compute_recorder: CommandRecorder,
+    graphics_timeline: Semaphore,
+    compute_timeline: Semaphore,
+    graphics_value: u64,
+    compute_value: u64,
+    transients: TransientResources,
+}
+
+impl RenderGraph {
+    pub fn new(device: &Device) -> RenderGraph {
+        return RenderGraph {
+            device: device.clone(),
+            graphics_recorder: device.create_command_recorder(QueueType::Graphics),
+            compute_recorder: device.create_command_recorder(QueueType::Compute),
+            graphics_timeline: device.create_timeline_semaphore(),
+            compute_timeline: device.create_timeline_semaphore(),
+            graphics_value: 0,
+            compute_value: 0,
+            transients: TransientResources::default(),
+        };
+    }
+
+    /// Starts declaring a new frame. The previous frame of this graph has to be done on the GPU,
+    /// its transient resources and command buffers get recycled here.
+    pub fn begin_frame(&mut self) -> FrameGraph<'_> {
+        self.free_transients();
+        self.graphics_recorder.reset();
+        self.compute_recorder.reset();
+
+        return FrameGraph {
+            graph: self,
+            images: Vec::new(),
+            buffers: Vec::new(),
+            passes: Vec::new(),
+            acquired_image: None,
+            fence: None,
+        };
+    }
+
+    fn free_transients(&mut self) {
+        let transients = std::mem::take(&mut self.transients);
+
+        for view in transients.views {
+            self.device.destroy_image_view(view);
+        }
+        for image in transients.images {
+            self.device.destroy_image(image);
+        }
+        for buffer in transients.buffers {
+            self.device.destroy_buffer(buffer);
+        }
+        for memory in transients.memory {
+            self.device.inner_device.free_aliased_memory(memory);
+        }
+    }
+}
+
+impl Drop for RenderGraph {
+    fn drop(&mut self) {
+        self.device.wait_idle();
+        self.free_transients();
+        self.device.destroy_semaphore(self.graphics_timeline);
+        self.device.destroy_semaphore(self.compute_timeline);
+    }
+}
+
+/// One frame worth of passes, see `RenderGraph::begin_frame`
+pub struct FrameGraph<'a> {
+    graph: &'a mut RenderGraph,
+    images: Vec<GraphImageEntry>,
+    buffers: Vec<GraphBufferEntry>,
+    passes: Vec<Pass<'a>>,
+    acquired_image: Option<(GraphImage, AcquiredImage)>,
+    fence: Option<Fence>,
+}
+
+/// Declares the resources a pass uses, the pass is only added once `execute` is called
+#[must_use]
+pub struct PassBuilder<'f, 'a> {
+    frame: &'f mut FrameGraph<'a>,
+    pass: Pass<'a>,
+}
+
+impl<'f, 'a> PassBuilder<'f, 'a> {
+    pub fn use_image(mut self, image: GraphImage, usage: Usage) -> Self {
+        self.pass.images.push((image, usage));
+        return self;
+    }
+
+    pub fn use_buffer(mut self, buffer: GraphBuffer, usage: Usage) -> Self {
+        self.pass.buffers.push((buffer, usage));
+        return self;
+    }
+
+    /// Keeps the pass even if nothing reads what it writes, for readbacks and other work outside the graph
+    pub fn side_effect(mut self) -> Self {
+        self.pass.side_effect = true;
+        return self;
+    }
+
+    /// The graph records the barriers for every declared use, the callback should not call `use_image` or `use_buffer` on them
+    pub fn execute<F: FnOnce(&mut CommandRecorder, &GraphResources) + 'a>(mut self, callback: F) {
+        self.pass.callback = Some(Box::new(callback));
+        self.frame.passes.push(self.pass);
+    }
+}
+
+impl<'a> FrameGraph<'a> {
+    /// Image that only lives during the frame, images with disjoint lifetimes share memory
+    pub fn create_image(&mut self, name: &str, image_desc: &ImageDescription) -> GraphImage {
+        assert!(image_desc.external_memory.is_none() && matches!(image_desc.tiling, ImageTiling::Optimal), "Transient images must be optimal tiled device memory");
+
+        self.images.push(GraphImageEntry {
+            name: name.to_string(),
+            source: ImageSource::Transient(*image_desc),
+            final_usage: None,
+        });
+
+        return GraphImage(self.images.len() - 1);
+    }
+
+    pub fn create_buffer(&mut self, name: &str, buffer_desc: &BufferDescription) -> GraphBuffer {
+        self.buffers.push(GraphBufferEntry {
+            name: name.to_string(),
+            source: BufferSource::Transient(*buffer_desc),
+        });
+
+        return GraphBuffer(self.buffers.len() - 1);
+    }
+
+    /// Image created outside the graph. Its current layout comes from the state `CommandRecorder::use_image`
+    /// tracks, `final_usage` is the state it is left in after the frame, None keeps whatever the last pass used.
+    pub fn import_image(&mut self, name: &str, image: ImageId, view: Option<ImageViewId>, final_usage: Option<Usage>) -> GraphImage {
+        self.images.push(GraphImageEntry {
+            name: name.to_string(),
+            source: ImageSource::Imported { image: image, view: view },
+            final_usage: final_usage,
+        });
+
+        return GraphImage(self.images.len() - 1);
+    }
+
+    pub fn import_buffer(&mut self, name: &str, buffer: BufferId) -> GraphBuffer {
+        self.buffers.push(GraphBufferEntry {
+            name: name.to_string(),
+            source: BufferSource::Imported(buffer),
+        });
+
+        return GraphBuffer(self.buffers.len() - 1);
+    }
+
+    /// Imports the acquired image, the frame waits on its image semaphore, ends in `Present`
+    /// and signals its present semaphore and fence. Call `Swapchain::present` after `execute`.
+    pub fn import_swapchain_image(&mut self, acquired_image: &AcquiredImage) -> GraphImage {
+        assert!(self.acquired_image.is_none(), "Only one swapchain image can be imported per frame");
+        assert!(self.fence.is_none(), "Swapchain frames signal the fence of the acquired image");
+
+        let image = self.import_image("swapchain", acquired_image.image, Some(acquired_image.view), Some(Usage::Present));
+        self.acquired_image = Some((image, *acquired_image));
+
+        return image;
+    }
+
+    /// Fence signaled once all work of the frame is done
+    pub fn signal_fence(&mut self, fence: Fence) {
+        assert!(self.acquired_image.is_none(), "Swapchain frames signal the fence of the acquired image");
+        self.fence = Some(fence);
+    }
+
+    /// `QueueType::Compute` passes run on the async compute queue if the device has a separate compute family
+    pub fn add_pass<'f>(&'f mut self, name: &str, queue: QueueType) -> PassBuilder<'f, 'a> {
+        assert!(queue == QueueType::Graphics || queue == QueueType::Compute, "Passes run on the graphics or compute queue");
+
+        return PassBuilder {
+            frame: self,
+            pass: Pass {
+                name: name.to_string(),
+                queue: queue,
+                images: SmallVec::new(),
+                buffers: SmallVec::new(),
+                side_effect: false,
+                callback: None,
+            },
+        };
+    }
+
+    /// Graphviz dot source of the frame, culled passes and resources are drawn dashed
+    pub fn to_graphviz(&self) -> String {
+        let live = self.live_passes();
+        let mut used = HashSet::new();
+
+        for (_, pass) in self.passes.iter().enumerate().filter(|(i, _)| live[*i]) {
+            used.extend(pass.images.iter().map(|(image, _)| GraphResource::Image(image.0)));
+            used.extend(pass.buffers.iter().map(|(buffer, _)| GraphResource::Buffer(buffer.0)));
+        }
+
+        let mut dot = String::from("digraph frame {\n    rankdir=LR;\n    node [fontname=\"Helvetica\"];\n");
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            let color = if self.pass_queue(pass) == QueueType::Compute { "orange" } else { "lightblue" };
+            let style = if live[i] { "filled" } else { "dashed" };
+            let _ = writeln!(dot, "    pass{} [label=\"{}\", shape=box, style={}, fillcolor={}];", i, pass.name, style, color);
+        }
+
+        for (i, image) in self.images.iter().enumerate() {
+            let style = if used.contains(&GraphResource::Image(i)) { "solid" } else { "dashed" };
+            let width = if matches!(image.source, ImageSource::Imported { .. }) { 2 } else { 1 };
+            let _ = writeln!(dot, "    image{} [label=\"{}\", shape=ellipse, style={}, penwidth={}];", i, image.name, style, width);
+        }
+
+        for (i, buffer) in self.buffers.iter().enumerate() {
+            let style = if used.contains(&GraphResource::Buffer(i)) { "solid" } else { "dashed" };
+            let width = if matches!(buffer.source, BufferSource::Imported(_)) { 2 } else { 1 };
+            let _ = writeln!(dot, "    buffer{} [label=\"{}\", shape=cylinder, style={}, penwidth={}];", i, buffer.name, style, width);
+        }
+
+        for (i, pass) in self.passes.iter().enumerate() {
+            let uses = pass
+                .images
+                .iter()
+                .map(|(image, usage)| (format!("image{}", image.0), *usage))
+                .chain(pass.buffers.iter().map(|(buffer, usage)| (format!("buffer{}", buffer.0), *usage)));
+
+            for (resource, usage) in uses {
+                if usage.is_write() {
+                    let _ = writeln!(dot, "    pass{} -> {} [label=\"{:?}\"];", i, resource, usage);
+                } else {
+                    let _ = writeln!(dot, "    {} -> pass{} [label=\"{:?}\"];", resource, i, usage);
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+
+        return dot;
+    }
+
+    /// Culls unused passes, allocates transient resources, records every pass with its barriers and submits the frame
+    pub fn execute(mut self) {
+        let live = self.live_passes();
+        let (resources, aliased) = self.create_transients(&live);
+        let plan = self.plan(&live, &resources, &aliased);
+
+        let mut passes: Vec<Option<Pass<'a>>> = std::mem::take(&mut self.passes).into_iter().map(Some).collect();
+        let mut pre_transitions = plan.pre_transitions;
+
+        let graph = &mut *self.graph;
+        let device = graph.device.clone();
+
+        let mut batch_values: Vec<u64> = Vec::with_capacity(plan.batches.len());
+        let last_graphics = plan.batches.iter().rposition(|batch| batch.queue == QueueType::Graphics);
+        let mut waited_acquire = false;
+
+        for (b, batch) in plan.batches.iter().enumerate() {
+            let recorder = if batch.queue == QueueType::Compute { &mut graph.compute_recorder } else { &mut graph.graphics_recorder };
+
+            recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+
+            for &p in &batch.passes {
+                push_transitions(recorder, &resources, &std::mem::take(&mut pre_transitions[p]));
+                recorder.flush_barriers();
+
+                let pass = passes[p].take().unwrap();
+                if let Some(callback) = pass.callback {
+                    callback(recorder, &resources);
+                }
+            }
+
+            push_transitions(recorder, &resources, &batch.end_transitions);
+            recorder.flush_barriers();
+
+            let command_buffer = recorder.end_recording();
+
+            let (timeline, value) = match batch.queue {
+                QueueType::Compute => {
+                    graph.compute_value += 1;
+                    (graph.compute_timeline, graph.compute_value)
+                }
+                _ => {
+                    graph.graphics_value += 1;
+                    (graph.graphics_timeline, graph.graphics_value)
+                }
+            };
+            batch_values.push(value);
+
+            let mut wait_semaphores: SmallVec<[SemaphoreInfo; 3]> = batch
+                .waits
+                .iter()
+                .map(|&wait| SemaphoreInfo {
+                    semaphore: if plan.batches[wait].queue == QueueType::Compute { graph.compute_timeline } else { graph.graphics_timeline },
+                    pipeline_stage: PipelineStage::AllCommands,
+                    value: Some(batch_values[wait]),
+                })
+                .collect();
+
+            let mut signal_semaphores: SmallVec<[SemaphoreInfo; 2]> = smallvec![SemaphoreInfo {
+                semaphore: timeline,
+                pipeline_stage: PipelineStage::AllCommands,
+                value: Some(value),
+            }];
+
+            let mut fence = None;
+
+            if let Some((_, acquired)) = &self.acquired_image {
+                if batch.queue == QueueType::Graphics && !waited_acquire {
+                    wait_semaphores.push(SemaphoreInfo {
+                        semaphore: acquired.image_semaphore,
+                        pipeline_stage: PipelineStage::AllCommands,
+                        value: None,
+                    });
+                    waited_acquire = true;
+                }
+
+                if Some(b) == last_graphics {
+                    signal_semaphores.push(SemaphoreInfo {
+                        semaphore: acquired.present_semaphore,
+                        pipeline_stage: PipelineStage::AllCommands,
+                        value: None,
+                    });
+                    fence = Some(acquired.fence);
+                }
+            } else if Some(b) == last_graphics {
+                fence = self.fence;
+            }
+
+            device.submit(&QueueSubmitInfo {
+                fence: fence,
+                command_buffers: &[command_buffer],
+                wait_semaphores: &wait_semaphores,
+                signal_semaphores: &signal_semaphores,
+            });
+        }
+
+        // Later use_image calls and graphs continue from where this frame left imported resources
+        let mut tracker = device.inner_device.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let tracker = &mut *tracker;
+
+        for (i, state) in plan.final_images.into_iter().enumerate() {
+            let (Some((queue, state)), ImageSource::Imported { image, .. }) = (state, &self.images[i].source) else {
+                continue;
+            };
+
+            let slot = unsafe { (&*device.inner_device.image_pool.get()).get_ref(image.id) };
+            let count = (slot.mip_levels * slot.array_layers) as usize;
+            let states = if state.len() == 1 { vec![state[0]; count] } else { state.to_vec() };
+            tracker.images.insert(*image, states);
+            tracker.image_queues.insert(*image, queue);
+        }
+
+        for (i, state) in plan.final_buffers.into_iter().enumerate() {
+            if let (Some((queue, state)), BufferSource::Imported(buffer)) = (state, &self.buffers[i].source) {
+                tracker.buffers.insert(*buffer, state);
+                tracker.buffer_queues.insert(*buffer, queue);
+            }
+        }
+    }
+
+    fn pass_queue(&self, pass: &Pass) -> QueueType {
+        let device = &self.graph.device.inner_device;
+        let async_compute = device.queue_family_index(QueueType::Compute) != device.queue_family_index(QueueType::Graphics);
+
+        if pass.queue == QueueType::Compute && async_compute {
+            return QueueType::Compute;
+        }
+
+        return QueueType::Graphics;
+    }
+
+    fn live_passes(&self) -> Vec<bool> {
+        let imported_images: Vec<bool> = self.images.iter().map(|entry| matches!(entry.source, ImageSource::Imported { .. })).collect();
+        let imported_buffers: Vec<bool> = self.buffers.iter().map(|entry| matches!(entry.source, BufferSource::Imported(_))).collect();
+
+        return live_passes(&self.passes, &imported_images, &imported_buffers);
+    }
+
+    /// Returns the physical resources and which images reuse memory of images that died earlier
+    fn create_transients(&mut self, live: &[bool]) -> (GraphResources, Vec<bool>) {
+        let device = self.graph.device.clone();
+
+        let mut resources = GraphResources {
+            images: vec![None; self.images.len()],
+            image_views: vec![None; self.images.len()],
+            buffers: vec![None; self.buffers.len()],
+            buffer_addresses: vec![0; self.buffers.len()],
+        };
+
+        // Lifetime in live pass order and the queues of every image
+        let mut lifetimes: Vec<Option<(Range<usize>, QueueType, bool)>> = vec![None; self.images.len()];
+        let mut used_buffers = vec![false; self.buffers.len()];
+
+        for (order, pass) in self.passes.iter().enumerate().filter(|(i, _)| live[*i]) {
+            let queue = self.pass_queue(pass);
+
+            for (image, _) in &pass.images {
+                lifetimes[image.0] = match lifetimes[image.0].take() {
+                    None => Some((order..order + 1, queue, false)),
+                    Some((range, first_queue, mixed)) => Some((range.start..order + 1, first_queue, mixed || first_queue != queue)),
+                };
+            }
+
+            for (buffer, _) in &pass.buffers {
+                used_buffers[buffer.0] = true;
+            }
+        }
+
+        for (i, entry) in self.images.iter().enumerate() {
+            if let ImageSource::Imported { image, view } = entry.source {
+                resources.images[i] = Some(image);
+                resources.image_views[i] = view;
+            }
+        }
+
+        for (i, entry) in self.buffers.iter().enumerate() {
+            let buffer = match entry.source {
+                BufferSource::Imported(buffer) => buffer,
+                BufferSource::Transient(desc) if used_buffers[i] => {
+                    let buffer = device.create_buffer(&desc);
+                    self.graph.transients.buffers.push(buffer);
+                    buffer
+                }
+                BufferSource::Transient(_) => continue,
+            };
+
+            resources.buffers[i] = Some(buffer);
+            resources.buffer_addresses[i] = device.get_buffer_address(buffer);
+        }
+
+        let mut unbound = Vec::new();
+
+        for (i, entry) in self.images.iter().enumerate() {
+            let (ImageSource::Transient(desc), Some((lifetime, queue, mixed))) = (&entry.source, lifetimes[i].clone()) else {
+                continue;
+            };
+
+            let (image, requirements) = device.inner_device.create_unbound_image(desc);
+            self.graph.transients.images.push(image);
+            resources.images[i] = Some(image);
+
+            unbound.push(UnboundImage {
+                image: i,
+                requirements: requirements,
+                lifetime: lifetime,
+                queue: if mixed { None } else { Some(queue) },
+            });
+        }
+
+        let heaps = place_images(unbound);
+        let memory: Vec<_> = heaps.iter().map(|heap| device.inner_device.allocate_aliased_memory(heap.requirements)).collect();
+
+        for (heap, memory) in heaps.iter().zip(&memory) {
+            for placed in &heap.placed {
+                device.inner_device.bind_image_memory(resources.images[placed.image].unwrap(), memory, placed.memory.start);
+            }
+        }
+
+        // Views need bound images, and only usages that read or write through a view get one
+        let view_usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::INPUT_ATTACHMENT;
+
+        for (i, entry) in self.images.iter().enumerate() {
+            let (ImageSource::Transient(desc), Some(image)) = (&entry.source, resources.images[i]) else {
+                continue;
+            };
+
+            if desc.usage.to_vk_flag().intersects(view_usage) {
+                let view = device.create_image_view(image, &whole_image_view(desc));
+                resources.image_views[i] = Some(view);
+                self.graph.transients.views.push(view);
+            }
+        }
+
+        let aliased = aliased_images(&heaps, self.images.len());
+        self.graph.transients.memory.extend(memory);
+
+        return (resources, aliased);
+    }
+
+    fn plan(&self, live: &[bool], resources: &GraphResources, aliased: &[bool]) -> Plan {
+        let device = &self.graph.device.inner_device;
+        let tracker = device.resource_tracker.lock().expect("Failed to lock the resource tracker");
+        let queue_family = |queue: QueueType| device.queue_family_index(queue);
+
+        let mut images: Vec<ResourcePlan> = self
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let state = match (&entry.source, &self.acquired_image) {
+                    (_, Some((swapchain, _))) if swapchain.0 == i => {
+                        // Chains with the acquire semaphore wait
+                        smallvec![ResourceState {
+                            stages: vk::PipelineStageFlags2::ALL_COMMANDS,
+                            ..ResourceState::UNKNOWN
+                        }]
+                    }
+                    (ImageSource::Imported { image, .. }, _) => match tracker.images.get(image) {
+                        Some(states) if states.iter().all(|state| *state == states[0]) => smallvec![states[0]],
+                        Some(states) => SmallVec::from_slice(states),
+                        None => smallvec![ResourceState::UNKNOWN],
+                    },
+                    (ImageSource::Transient(_), _) => smallvec![ResourceState::UNKNOWN],
+                };
+
+                let exclusive = match resources.images[i] {
+                    Some(image) => unsafe { (&*device.image_pool.get()).get_ref(image.id).sharing_mode == vk::SharingMode::EXCLUSIVE },
+                    None => true,
+                };
+
+                ResourcePlan { state: state, owner: None, exclusive: exclusive }
+            })
+            .collect();
+
+        let mut buffers: Vec<ResourcePlan> = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let state = match &entry.source {
+                    BufferSource::Imported(buffer) => *tracker.buffers.get(buffer).unwrap_or(&ResourceState::UNKNOWN),
+                    BufferSource::Transient(_) => ResourceState::UNKNOWN,
+                };
+
+                let exclusive = match resources.buffers[i] {
+                    Some(buffer) => unsafe { (&*device.buffer_pool.get()).get_ref(buffer.id).sharing_mode == vk::SharingMode::EXCLUSIVE },
+                    None => true,
+                };
+
+                ResourcePlan { state: smallvec![state], owner: None, exclusive: exclusive }
+            })
+            .collect();
+
+        // Aliased images wait on everything earlier on their queue, which covers the last use of the images that owned their memory before
+        for (image, _) in aliased.iter().enumerate().filter(|(_, aliased)| **aliased) {
+            images[image].state = smallvec![ResourceState {
+                stages: vk::PipelineStageFlags2::ALL_COMMANDS,
+                access: vk::AccessFlags2::MEMORY_WRITE,
+                ..ResourceState::UNKNOWN
+            }];
+        }
+
+        let mut plan = Plan {
+            batches: Vec::new(),
+            pre_transitions: (0..self.passes.len()).map(|_| Vec::new()).collect(),
+            final_images: vec![None; self.images.len()],
+            final_buffers: vec![None; self.buffers.len()],
+        };
+
+        for (p, pass) in self.passes.iter().enumerate().filter(|(i, _)| live[*i]) {
+            let queue = self.pass_queue(pass);
+
+            if plan.batches.last().is_none_or(|batch| batch.queue != queue) {
+                plan.batches.push(Batch {
+                    queue: queue,
+                    passes: Vec::new(),
+                    waits: SmallVec::new(),
+                    end_transitions: Vec::new(),
+                });
+            }
+
+            let b = plan.batches.len() - 1;
+            plan.batches[b].passes.push(p);
+
+            let uses = pass
+                .images
+                .iter()
+                .map(|(image, usage)| (GraphResource::Image(image.0), ResourceState::from_usage(*usage, true)))
+                .chain(pass.buffers.iter().map(|(buffer, usage)| (GraphResource::Buffer(buffer.0), ResourceState::from_usage(*usage, false))));
+
+            for (resource, next) in uses {
+                let state = match resource {
+                    GraphResource::Image(i) => &mut images[i],
+                    GraphResource::Buffer(i) => &mut buffers[i],
+                };
+
+                let mut transitions = Vec::new();
+                transition(&queue_family, &mut plan.batches, b, queue, resource, state, next, &mut transitions);
+                plan.pre_transitions[p].extend(transitions);
+            }
+        }
+
+        // Final states are reached on the graphics queue, which also waits on the compute queue so the fence covers everything
+        let finals: Vec<(usize, Usage)> = self.images.iter().enumerate().filter_map(|(i, entry)| entry.final_usage.map(|usage| (i, usage))).collect();
+        let needs_tail = !finals.is_empty() || self.fence.is_some() || self.acquired_image.is_some();
+
+        if needs_tail && plan.batches.last().is_none_or(|batch| batch.queue != QueueType::Graphics) {
+            let waits = plan.batches.len().checked_sub(1).into_iter().collect();
+
+            plan.batches.push(Batch {
+                queue: QueueType::Graphics,
+                passes: Vec::new(),
+                waits: waits,
+                end_transitions: Vec::new(),
+            });
+        }
+
+        if let Some(tail) = plan.batches.len().checked_sub(1) {
+            if let Some(last_compute) = plan.batches.iter().rposition(|batch| batch.queue == QueueType::Compute)
+                && last_compute < tail
+                && !plan.batches[tail].waits.contains(&last_compute)
+            {
+                plan.batches[tail].waits.push(last_compute);
+            }
+
+            for (i, usage) in finals {
+                let mut transitions = Vec::new();
+                let next = ResourceState::from_usage(usage, true);
+                transition(&queue_family, &mut plan.batches, tail, QueueType::Graphics, GraphResource::Image(i), &mut images[i], next, &mut transitions);
+                plan.batches[tail].end_transitions.extend(transitions);
+            }
+        }
+
+        for (i, state) in images.into_iter().enumerate() {
+            if let Some((queue, _)) = state.owner {
+                plan.final_images[i] = Some((queue, state.state));
+            }
+        }
+
+        for (i, state) in buffers.into_iter().enumerate() {
+            if let Some((queue, _)) = state.owner {
+                plan.final_buffers[i] = Some((queue, state.state[0]));
+            }
+        }
+
+        return plan;
+    }
+}
+
+/// A pass survives if it has side effects, writes an imported resource or writes something a surviving pass reads
+fn live_passes(passes: &[Pass], imported_images: &[bool], imported_buffers: &[bool]) -> Vec<bool> {
+    let mut live = vec![false; passes.len()];
+    let mut needed = HashSet::new();
+
+    for (i, pass) in passes.iter().enumerate().rev() {
+        let uses = || {
+            pass.images
+                .iter()
+                .map(|(image, usage)| (GraphResource::Image(image.0), *usage))
+                .chain(pass.buffers.iter().map(|(buffer, usage)| (GraphResource::Buffer(buffer.0), *usage)))
+        };
+
+        let imported = |resource: &GraphResource| match resource {
+            GraphResource::Image(image) => imported_images[*image],
+            GraphResource::Buffer(buffer) => imported_buffers[*buffer],
+        };
+
+        live[i] = pass.side_effect || uses().any(|(resource, usage)| usage.is_write() && (imported(&resource) || needed.contains(&resource)));
+
+        if live[i] {
+            needed.extend(uses().filter(|(_, usage)| usage.is_read()).map(|(resource, _)| resource));
+        }
+    }
+
+    return live;
+}
+
+/// Transient image waiting for memory, `queue` is None if passes on both queues use it
+struct UnboundImage {
+    image: usize,
+    requirements: vk::MemoryRequirements,
+    /// In live pass order
+    lifetime: Range<usize>,
+    queue: Option<QueueType>,
+}
+
+struct Placed {
+    image: usize,
+    lifetime: Range<usize>,
+    memory: Range<u64>,
+}
+
+/// One allocation shared by images whose lifetimes dont overlap
+struct TransientHeap {
+    queue: Option<QueueType>,
+    requirements: vk::MemoryRequirements,
+    placed: Vec<Placed>,
+}
+
+/// Transient images used on a single queue alias each other, mixed queue ones get memory of their own
+fn place_images(mut unbound: Vec<UnboundImage>) -> Vec<TransientHeap> {
+    // Biggest first keeps the heaps tight
+    unbound.sort_by_key(|image| std::cmp::Reverse(image.requirements.size));
+
+    let mut heaps: Vec<TransientHeap> = Vec::new();
+
+    for image in unbound {
+        let requirements = image.requirements;
+        let heap_index = heaps.iter().position(|heap| heap.requirements.memory_type_bits == requirements.memory_type_bits && heap.queue.is_some() && heap.queue == image.queue);
+
+        let heap = match heap_index {
+            Some(index) => &mut heaps[index],
+            None => {
+                heaps.push(TransientHeap {
+                    queue: image.queue,
+                    requirements: vk::MemoryRequirements {
+                        memory_type_bits: requirements.memory_type_bits,
+                        ..Default::default()
+                    },
+                    placed: Vec::new(),
+                });
+                heaps.last_mut().unwrap()
+            }
+        };
+
+        // Lowest offset that does not collide with an image alive at the same time
+        let mut offset = 0;
+        loop {
+            let candidate = offset..offset + requirements.size;
+            let collision = heap.placed.iter().find(|placed| {
+                placed.lifetime.start < image.lifetime.end && image.lifetime.start < placed.lifetime.end && placed.memory.start < candidate.end && candidate.start < placed.memory.end
+            });
+
+            match collision {
+                Some(placed) => offset = placed.memory.end.next_multiple_of(requirements.alignment),
+                None => break,
+            }
+        }
+
+        heap.requirements.size = heap.requirements.size.max(offset + requirements.size);
+        heap.requirements.alignment = heap.requirements.alignment.max(requirements.alignment);
+        heap.placed.push(Placed {
+            image: image.image,
+            lifetime: image.lifetime,
+            memory: offset..offset + requirements.size,
+        });
+    }
+
+    return heaps;
+}
+
+/// Images that take over memory from an image whose lifetime ended before theirs began
+fn aliased_images(heaps: &[TransientHeap], image_count: usize) -> Vec<bool> {
+    let mut aliased = vec![false; image_count];
+
+    for heap in heaps {
+        for placed in &heap.placed {
+            aliased[placed.image] = heap
+                .placed
+                .iter()
+                .any(|other| other.lifetime.end <= placed.lifetime.start && other.memory.start < placed.memory.end && placed.memory.start < other.memory.end);
+        }
+    }
+
+    return aliased;
+}
+
+/// Moves a resource to `next` for a use in batch `b`, with a queue ownership transfer if it was last used on the other queue
+#[allow(clippy::too_many_arguments)]
+fn transition(queue_family: &impl Fn(QueueType) -> u32, batches: &mut [Batch], b: usize, queue: QueueType, resource: GraphResource, state: &mut ResourcePlan, next: ResourceState, out: &mut Vec<Transition>) {
+    let needs_barrier = state.state.iter().any(|old| old.needs_barrier(&next));
+
+    match state.owner {
+        Some((owner_queue, owner_batch)) if owner_queue != queue => {
+            if !batches[b].waits.contains(&owner_batch) {
+                batches[b].waits.push(owner_batch);
+            }
+
+            let src_family = queue_family(owner_queue);
+            let dst_family = queue_family(queue);
+
+            if state.exclusive && src_family != dst_family {
+                let released = ResourceState {
+                    layout: next.layout,
+                    stages: vk::PipelineStageFlags2::NONE,
+                    access: vk::AccessFlags2::NONE,
+                };
+
+                batches[owner_batch].end_transitions.push(Transition {
+                    resource: resource,
+                    old: state.state.clone(),
+                    new: released,
+                    src_family: src_family,
+                    dst_family: dst_family,
+                });
+
+                out.push(Transition {
+                    resource: resource,
+                    old: state.state.iter().map(|old| ResourceState { layout: old.layout, ..ResourceState::UNKNOWN }).collect(),
+                    new: next,
+                    src_family: src_family,
+                    dst_family: dst_family,
+                });
+            } else if needs_barrier {
+                // The semaphore already waited on the other queue, only the layout has to change
+                out.push(Transition {
+                    resource: resource,
+                    old: state.state.iter().map(|old| ResourceState { layout: old.layout, ..ResourceState::UNKNOWN }).collect(),
+                    new: next,
+                    src_family: vk::QUEUE_FAMILY_IGNORED,
+                    dst_family: vk::QUEUE_FAMILY_IGNORED,
+                });
+            }
+
+            state.state = smallvec![next];
+        }
+        _ => {
+            if needs_barrier {
+                out.push(Transition {
+                    resource: resource,
+                    old: state.state.clone(),
+                    new: next,
+                    src_family: vk::QUEUE_FAMILY_IGNORED,
+                    dst_family: vk::QUEUE_FAMILY_IGNORED,
+                });
+
+                state.state = smallvec![next];
+            } else {
+                for old in state.state.iter_mut() {
+                    *old = old.merge_reads(&next);
+                }
+            }
+        }
+    }
+
+    state.owner = Some((queue, b));
+}
+
+fn push_transitions(recorder: &mut CommandRecorder, resources: &GraphResources, transitions: &[Transition]) {
+    for transition in transitions {
+        match transition.resource {
+            GraphResource::Image(i) => {
+                let image_id = resources.images[i].unwrap();
+                let slot = unsafe { (&*recorder.device.image_pool.get()).get_ref(image_id.id) };
+                let aspect = resource_tracker::format_aspect(slot.format);
+
+                for (index, old) in transition.old.iter().enumerate() {
+                    let range = if transition.old.len() == 1 {
+                        vk::ImageSubresourceRange {
+                            aspect_mask: aspect,
+                            base_mip_level: 0,
+                            level_count: vk::REMAINING_MIP_LEVELS,
+                            base_array_layer: 0,
+                            layer_count: vk::REMAINING_ARRAY_LAYERS,
+                        }
+                    } else {
+                        vk::ImageSubresourceRange {
+                            aspect_mask: aspect,
+                            base_mip_level: index as u32 / slot.array_layers,
+                            level_count: 1,
+                            base_array_layer: index as u32 % slot.array_layers,
+                            layer_count: 1,
+                        }
+                    };
+
+                    recorder.pending_image_barriers.borrow_mut().push(
+                        resource_tracker::image_barrier(slot.handle, range, old, &transition.new)
+                            .src_queue_family_index(transition.src_family)
+                            .dst_queue_family_index(transition.dst_family),
+                    );
+                }
+            }
+            GraphResource::Buffer(i) => {
+                let buffer_id = resources.buffers[i].unwrap();
+                let handle = unsafe { (&*recorder.device.buffer_pool.get()).get_ref(buffer_id.id).handle };
+
+                recorder.pending_buffer_barriers.borrow_mut().push(
+                    resource_tracker::buffer_barrier(handle, &transition.old[0], &transition.new)
+                        .src_queue_family_index(transition.src_family)
+                        .dst_queue_family_index(transition.dst_family),
+                );
+            }
+        }
+    }
+}
+
+fn whole_image_view(image_desc: &ImageDescription) -> ImageViewDescription {
+    let layered = image_desc.array_layers > 1;
+
+    let view_type = match image_desc.image_type {
+        ImageType::Type1D if layered => ImageViewType::Type1DArray,
+        ImageType::Type1D => ImageViewType::Type1D,
+        ImageType::Type2D if layered => ImageViewType::Type2DArray,
+        ImageType::Type2D => ImageViewType::Type2D,
+        ImageType::Type3D => ImageViewType::Type3D,
+    };
+
+    let aspect = resource_tracker::format_aspect(image_desc.format.to_vk_format());
+    let aspect = if aspect == vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL {
+        ImageAspect::DepthStencil
+    } else if aspect == vk::ImageAspectFlags::DEPTH {
+        ImageAspect::Depth
+    } else if aspect == vk::ImageAspectFlags::STENCIL {
+        ImageAspect::Stencil
+    } else {
+        ImageAspect::Color
+    };
+
+    return ImageViewDescription {
+        view_type: view_type,
+        subresources: ImageSubresources {
+            aspect: aspect,
+            mip_level: 0,
+            level_count: image_desc.mip_levels,
+            base_array_layer: 0,
+            layer_count: image_desc.array_layers,
+        },
+        ..Default::default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(queue: QueueType, images: &[(usize, Usage)], buffers: &[(usize, Usage)]) -> Pass<'static> {
+        return Pass {
+            name: String::new(),
+            queue: queue,
+            images: images.iter().map(|(image, usage)| (GraphImage(*image), *usage)).collect(),
+            buffers: buffers.iter().map(|(buffer, usage)| (GraphBuffer(*buffer), *usage)).collect(),
+            side_effect: false,
+            callback: None,
+        };
+    }
+
+    fn unbound(image: usize, size: u64, lifetime: Range<usize>, queue: Option<QueueType>) -> UnboundImage {
+        return UnboundImage {
+            image: image,
+            requirements: vk::MemoryRequirements { size: size, alignment: 256, memory_type_bits: 1 },
+            lifetime: lifetime,
+            queue: queue,
+        };
+    }
+
+    fn batch(queue: QueueType) -> Batch {
+        return Batch {
+            queue: queue,
+            passes: Vec::new(),
+            waits: SmallVec::new(),
+            end_transitions: Vec::new(),
+        };
+    }
+
+    fn separate_families(queue: QueueType) -> u32 {
+        return if queue == QueueType::Compute { 1 } else { 0 };
+    }
+
+    #[test]
+    fn passes_without_side_effects_are_culled() {
+        // Image 0 and 2 are transient, image 1 is imported
+        let passes = [
+            pass(QueueType::Graphics, &[(0, Usage::ColorAttachment)], &[]),
+            pass(QueueType::Graphics, &[(0, Usage::ShaderRead), (1, Usage::ColorAttachment)], &[]),
+            pass(QueueType::Graphics, &[(2, Usage::ColorAttachment)], &[]),
+            pass(QueueType::Compute, &[(0, Usage::ShaderRead)], &[(0, Usage::ShaderWrite)]),
+        ];
+
+        assert_eq!(live_passes(&passes, &[false, true, false], &[false]), [true, true, false, false]);
+    }
+
+    #[test]
+    fn side_effects_and_imported_buffers_keep_passes() {
+        let mut readback = pass(QueueType::Graphics, &[(0, Usage::TransferSrc)], &[(0, Usage::TransferDst)]);
+        readback.side_effect = true;
+
+        let passes = [
+            pass(QueueType::Graphics, &[(0, Usage::ColorAttachment)], &[]),
+            readback,
+            pass(QueueType::Compute, &[], &[(1, Usage::ShaderWrite)]),
+        ];
+
+        assert_eq!(live_passes(&passes, &[false], &[false, true]), [true, true, true]);
+    }
+
+    #[test]
+    fn disjoint_lifetimes_share_memory() {
+        let heaps = place_images(vec![
+            unbound(0, 1024, 0..2, Some(QueueType::Graphics)),
+            unbound(1, 512, 2..4, Some(QueueType::Graphics)),
+            unbound(2, 512, 1..3, Some(QueueType::Graphics)),
+        ]);
+
+        assert_eq!(heaps.len(), 1);
+
+        let offset = |image: usize| heaps[0].placed.iter().find(|placed| placed.image == image).unwrap().memory.start;
+        assert_eq!(offset(0), 0);
+        assert_eq!(offset(1), 0);
+        // Overlaps both of the others, so it goes behind the biggest one
+        assert_eq!(offset(2), 1024);
+        assert_eq!(heaps[0].requirements.size, 1536);
+
+        assert_eq!(aliased_images(&heaps, 3), [false, true, false]);
+    }
+
+    #[test]
+    fn mixed_queue_images_get_their_own_memory() {
+        let heaps = place_images(vec![
+            unbound(0, 1024, 0..1, Some(QueueType::Graphics)),
+            unbound(1, 1024, 1..2, None),
+            unbound(2, 1024, 2..3, Some(QueueType::Compute)),
+            unbound(3, 1024, 3..4, None),
+        ]);
+
+        assert_eq!(heaps.len(), 4);
+        assert!(heaps.iter().all(|heap| heap.placed.len() == 1 && heap.requirements.size == 1024));
+        assert_eq!(aliased_images(&heaps, 4), [false; 4]);
+    }
+
+    #[test]
+    fn exclusive_resources_are_released_and_acquired_between_families() {
+        let mut batches = vec![batch(QueueType::Graphics), batch(QueueType::Compute)];
+        let mut state = ResourcePlan { state: smallvec![ResourceState::UNKNOWN], owner: None, exclusive: true };
+        let written = ResourceState::from_usage(Usage::ColorAttachment, true);
+        let read = ResourceState::from_usage(Usage::ShaderRead, true);
+
+        let mut out = Vec::new();
+        transition(&separate_families, &mut batches, 0, QueueType::Graphics, GraphResource::Image(0), &mut state, written, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].src_family, vk::QUEUE_FAMILY_IGNORED);
+
+        let mut out = Vec::new();
+        transition(&separate_families, &mut batches, 1, QueueType::Compute, GraphResource::Image(0), &mut state, read, &mut out);
+
+        assert_eq!(batches[1].waits.as_slice(), [0]);
+
+        let release = &batches[0].end_transitions[0];
+        assert!(matches!(release.resource, GraphResource::Image(0)));
+        assert_eq!((release.src_family, release.dst_family), (0, 1));
+        assert_eq!(release.old.as_slice(), [written]);
+        assert_eq!(release.new.layout, read.layout);
+
+        let acquire = &out[0];
+        assert_eq!((acquire.src_family, acquire.dst_family), (0, 1));
+        assert_eq!(acquire.old[0].layout, written.layout);
+        assert_eq!(acquire.new, read);
+
+        assert_eq!(state.owner, Some((QueueType::Compute, 1)));
+        assert_eq!(state.state.as_slice(), [read]);
+    }
+
+    #[test]
+    fn concurrent_resources_only_wait_on_the_other_queue() {
+        let mut batches = vec![batch(QueueType::Compute), batch(QueueType::Graphics)];
+        let mut state = ResourcePlan { state: smallvec![ResourceState::UNKNOWN], owner: None, exclusive: false };
+
+        let mut out = Vec::new();
+        transition(&separate_families, &mut batches, 0, QueueType::Compute, GraphResource::Buffer(0), &mut state, ResourceState::from_usage(Usage::ShaderWrite, false), &mut out);
+
+        let mut out = Vec::new();
+        transition(&separate_families, &mut batches, 1, QueueType::Graphics, GraphResource::Buffer(0), &mut state, ResourceState::from_usage(Usage::VertexBuffer, false), &mut out);
+
+        assert_eq!(batches[1].waits.as_slice(), [0]);
+        assert!(batches[0].end_transitions.is_empty());
+        assert_eq!(out.len(), 1);
+        assert_eq!((out[0].src_family, out[0].dst_family), (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED));
+    }
+}
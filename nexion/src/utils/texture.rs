@@ -9,7 +9,28 @@ pub struct TextureWriteInfo {
     pub dst_queue: QueueType,
 }
 
-pub struct TextureDescription {}
+/// Options for textures loaded from image files
+pub struct TextureDescription {
+    /// Treat 8 bit color data as sRGB encoded, HDR and 16 bit images are always linear
+    pub srgb: bool,
+    /// Allocate the full mip chain and fill it after upload
+    pub generate_mips: bool,
+    /// Extra usage on top of `SAMPLED | TRANSFER_DST`
+    pub usage: ImageUsage,
+    /// Bindless sampled image index the texture gets registered at
+    pub index: u32,
+}
+
+impl Default for TextureDescription {
+    fn default() -> Self {
+        return TextureDescription {
+            srgb: true,
+            generate_mips: true,
+            usage: ImageUsage::default(),
+            index: 0,
+        };
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct Texture {
@@ -62,3 +83,143 @@ impl Texture {
         })]);
     }
 }
+
+// Loading from files //
+#[cfg(feature = "texture-loading")]
+pub(crate) fn create_texture_from_image(device: &Device, img: image::DynamicImage, texture_desc: &TextureDescription) -> Texture {
+    use image::DynamicImage;
+
+    let (width, height) = (img.width(), img.height());
+
+    // 16 bit and float images keep their precision, everything else becomes rgba8
+    match img {
+        DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => {
+            let data = img.to_rgba16().into_raw();
+            return upload_texture(device, &data, width, height, Format::Rgba16Unorm, texture_desc);
+        }
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let data = img.to_rgba32f().into_raw();
+            return upload_texture(device, &data, width, height, Format::Rgba32Float, texture_desc);
+        }
+        _ => {
+            let format = if texture_desc.srgb { Format::Rgba8Srgb } else { Format::Rgba8Unorm };
+            let data = img.to_rgba8().into_raw();
+            return upload_texture(device, &data, width, height, format, texture_desc);
+        }
+    }
+}
+
+#[cfg(feature = "texture-loading")]
+fn upload_texture<T: Copy>(device: &Device, data: &[T], width: u32, height: u32, format: Format, texture_desc: &TextureDescription) -> Texture {
+    let mip_levels = if texture_desc.generate_mips { 32 - width.max(height).leading_zeros() } else { 1 };
+
+    let mut usage = ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST | texture_desc.usage;
+
+    if mip_levels > 1 {
+        usage = usage | ImageUsage::TRANSFER_SRC;
+
+        // Formats that cant be blitted get their mips from a compute shader
+        let features = device.inner_device.get_format_features(format.to_vk_format());
+        let blit_features = ash::vk::FormatFeatureFlags::BLIT_SRC | ash::vk::FormatFeatureFlags::BLIT_DST | ash::vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+        if !features.contains(blit_features) {
+            assert!(
+                features.contains(ash::vk::FormatFeatureFlags::STORAGE_IMAGE),
+                "{:?} can neither be blitted with linear filtering nor used as a storage image, cant generate mips for it",
+                format
+            );
+            usage = usage | ImageUsage::STORAGE;
+        }
+    }
+
+    let texture = device.create_texture(
+        &ImageDescription {
+            usage: usage,
+            format: format,
+            extent: Extent3D { width: width, height: height, depth: 1 },
+            memory_type: MemoryType::DeviceLocal,
+            mip_levels: mip_levels,
+            ..Default::default()
+        },
+        &ImageViewDescription {
+            subresources: ImageSubresources {
+                level_count: mip_levels,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        texture_desc.index,
+    );
+
+    let staging_buffer = device.create_buffer(&BufferDescription {
+        usage: BufferUsage::TRANSFER_SRC,
+        size: std::mem::size_of_val(data) as u64,
+        memory_type: MemoryType::PreferHost,
+        create_mapped: true,
+        ..Default::default()
+    });
+    device.write_data_to_buffer(staging_buffer, data);
+
+    let mut recorder = device.create_command_recorder(QueueType::Graphics);
+    recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+
+    recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+        image: texture.image,
+        old_layout: ImageLayout::Undefined,
+        new_layout: ImageLayout::TransferDst,
+        src_stage: PipelineStage::TopOfPipe,
+        dst_stage: PipelineStage::Transfer,
+        src_access: AccessType::None,
+        dst_access: AccessType::TransferWrite,
+        ..Default::default()
+    })]);
+
+    recorder.copy_buffer_to_image(&BufferImageCopyInfo {
+        buffer: staging_buffer,
+        image: texture.image,
+        dst_image_layout: ImageLayout::TransferDst,
+        region: BufferImageCopyRegion {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: ImageSubresources::default(),
+            image_offset: Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: Extent3D { width: width, height: height, depth: 1 },
+        },
+    });
+
+    if mip_levels > 1 {
+        recorder.generate_mipmaps(
+            texture.image,
+            ImageSubresources {
+                level_count: mip_levels,
+                ..Default::default()
+            },
+            Filter::Linear,
+        );
+    } else {
+        recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+            image: texture.image,
+            old_layout: ImageLayout::TransferDst,
+            new_layout: ImageLayout::ShaderReadOnly,
+            src_stage: PipelineStage::Transfer,
+            dst_stage: PipelineStage::AllCommands,
+            src_access: AccessType::TransferWrite,
+            dst_access: AccessType::ShaderRead,
+            ..Default::default()
+        })]);
+    }
+
+    let exec_buffer = recorder.end_recording();
+
+    device.submit(&QueueSubmitInfo {
+        fence: None,
+        command_buffers: &[exec_buffer],
+        wait_semaphores: &[],
+        signal_semaphores: &[],
+    });
+    device.wait_queue(QueueType::Graphics);
+
+    device.destroy_buffer(staging_buffer);
+
+    return texture;
+}
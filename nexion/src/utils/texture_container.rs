@@ -0,0 +1,361 @@
+use crate::{utils::texture::Texture, *};
+use ash::vk;
+
+/// One copy region of a container, a single mip level of one or more consecutive layers
+struct ContainerRegion<'a> {
+    level: u32,
+    base_layer: u32,
+    layer_count: u32,
+    data: &'a [u8],
+}
+
+/// Common representation of KTX2 and DDS files, the data is borrowed from the parsed file
+struct ContainerImage<'a> {
+    format: Format,
+    image_type: ImageType,
+    view_type: ImageViewType,
+    extent: Extent3D,
+    mip_levels: u32,
+    array_layers: u32,
+    cube: bool,
+    regions: Vec<ContainerRegion<'a>>,
+}
+
+fn level_extent(extent: Extent3D, level: u32) -> Extent3D {
+    return Extent3D {
+        width: (extent.width >> level).max(1),
+        height: (extent.height >> level).max(1),
+        depth: (extent.depth >> level).max(1),
+    };
+}
+
+fn pick_types(extent: Extent3D, is_1d: bool, array_layers: u32, cube: bool) -> (ImageType, ImageViewType) {
+    if cube {
+        assert!(extent.width == extent.height, "Cubemap faces must be square");
+        assert!(array_layers.is_multiple_of(6), "Cubemap layer count must be a multiple of 6");
+
+        return (ImageType::Type2D, if array_layers == 6 { ImageViewType::Cube } else { ImageViewType::CubeArray });
+    }
+
+    if extent.depth > 1 {
+        return (ImageType::Type3D, ImageViewType::Type3D);
+    }
+
+    if is_1d {
+        return (ImageType::Type1D, if array_layers > 1 { ImageViewType::Type1DArray } else { ImageViewType::Type1D });
+    }
+
+    return (ImageType::Type2D, if array_layers > 1 { ImageViewType::Type2DArray } else { ImageViewType::Type2D });
+}
+
+//// KTX2 ////
+fn parse_ktx2<'a>(reader: &'a ktx2::Reader<&'a [u8]>) -> ContainerImage<'a> {
+    let header = reader.header();
+
+    assert!(header.supercompression_scheme.is_none(), "Supercompressed KTX2 files are not supported");
+
+    let ktx_format = header.format.expect("KTX2 files without a vulkan format (basis universal) are not supported");
+    let format = Format::from_vk_format(vk::Format::from_raw(ktx_format.value() as i32)).unwrap_or_else(|| panic!("KTX2 format {:?} has no matching nexion Format", ktx_format));
+
+    let extent = Extent3D {
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        depth: header.pixel_depth.max(1),
+    };
+
+    let cube = header.face_count == 6;
+    let array_layers = header.layer_count.max(1) * header.face_count;
+    let (image_type, view_type) = pick_types(extent, header.pixel_height == 0, array_layers, cube);
+
+    // KTX2 stores every layer and face of a level back to back, so each level is a single region
+    let regions = reader
+        .levels()
+        .enumerate()
+        .map(|(level, data)| {
+            let level_size = level_extent(extent, level as u32);
+            assert!(
+                data.data.len() as u64 == format.region_size(level_size.width, level_size.height, level_size.depth) * array_layers as u64,
+                "KTX2 level {} has an unexpected size",
+                level
+            );
+
+            ContainerRegion {
+                level: level as u32,
+                base_layer: 0,
+                layer_count: array_layers,
+                data: data.data,
+            }
+        })
+        .collect();
+
+    return ContainerImage {
+        format,
+        image_type,
+        view_type,
+        extent,
+        mip_levels: header.level_count.max(1),
+        array_layers,
+        cube,
+        regions,
+    };
+}
+
+//// DDS ////
+fn dds_format(dds: &ddsfile::Dds) -> Format {
+    use ddsfile::{D3DFormat, DxgiFormat};
+
+    if let Some(dxgi) = dds.get_dxgi_format() {
+        return match dxgi {
+            DxgiFormat::R8_UNorm => Format::R8Unorm,
+            DxgiFormat::R8G8_UNorm => Format::Rg8Unorm,
+            DxgiFormat::R8G8B8A8_UNorm => Format::Rgba8Unorm,
+            DxgiFormat::R8G8B8A8_UNorm_sRGB => Format::Rgba8Srgb,
+            DxgiFormat::B8G8R8A8_UNorm => Format::Bgra8Unorm,
+            DxgiFormat::B8G8R8A8_UNorm_sRGB => Format::Bgra8Srgb,
+            DxgiFormat::R16_Float => Format::R16Float,
+            DxgiFormat::R16G16_Float => Format::Rg16Float,
+            DxgiFormat::R16G16B16A16_Float => Format::Rgba16Float,
+            DxgiFormat::R32_Float => Format::R32Float,
+            DxgiFormat::R32G32_Float => Format::Rg32Float,
+            DxgiFormat::R32G32B32_Float => Format::Rgb32Float,
+            DxgiFormat::R32G32B32A32_Float => Format::Rgba32Float,
+            DxgiFormat::BC1_UNorm => Format::BC1RgbaUnorm,
+            DxgiFormat::BC1_UNorm_sRGB => Format::BC1RgbaSrgb,
+            DxgiFormat::BC2_UNorm => Format::BC2Unorm,
+            DxgiFormat::BC2_UNorm_sRGB => Format::BC2Srgb,
+            DxgiFormat::BC3_UNorm => Format::BC3Unorm,
+            DxgiFormat::BC3_UNorm_sRGB => Format::BC3Srgb,
+            DxgiFormat::BC4_UNorm => Format::BC4Unorm,
+            DxgiFormat::BC4_SNorm => Format::BC4Snorm,
+            DxgiFormat::BC5_UNorm => Format::BC5Unorm,
+            DxgiFormat::BC5_SNorm => Format::BC5Snorm,
+            DxgiFormat::BC6H_UF16 => Format::BC6HUfloat,
+            DxgiFormat::BC6H_SF16 => Format::BC6HSfloat,
+            DxgiFormat::BC7_UNorm => Format::BC7Unorm,
+            DxgiFormat::BC7_UNorm_sRGB => Format::BC7Srgb,
+            other => panic!("DXGI format {:?} has no matching nexion Format", other),
+        };
+    }
+
+    return match dds.get_d3d_format().expect("DDS file has no recognizable format") {
+        D3DFormat::A8B8G8R8 => Format::Rgba8Unorm,
+        D3DFormat::A8R8G8B8 => Format::Bgra8Unorm,
+        D3DFormat::DXT1 => Format::BC1RgbaUnorm,
+        D3DFormat::DXT3 => Format::BC2Unorm,
+        D3DFormat::DXT5 => Format::BC3Unorm,
+        other => panic!("D3D format {:?} has no matching nexion Format", other),
+    };
+}
+
+fn parse_dds(dds: &ddsfile::Dds) -> ContainerImage<'_> {
+    let format = dds_format(dds);
+
+    let extent = Extent3D {
+        width: dds.get_width(),
+        height: dds.get_height(),
+        depth: dds.get_depth().max(1),
+    };
+
+    let (cube, array_layers, is_1d) = match &dds.header10 {
+        Some(h10) => {
+            let cube = h10.misc_flag.contains(ddsfile::MiscFlag::TEXTURECUBE);
+            let layers = h10.array_size.max(1) * if cube { 6 } else { 1 };
+            (cube, layers, h10.resource_dimension == ddsfile::D3D10ResourceDimension::Texture1D)
+        }
+        None => {
+            let cube = dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP);
+            (cube, if cube { 6 } else { 1 }, false)
+        }
+    };
+
+    let (image_type, view_type) = pick_types(extent, is_1d, array_layers, cube);
+    let mip_levels = dds.get_num_mipmap_levels().max(1);
+
+    // DDS stores the full mip chain of a layer before moving on to the next one
+    let mut regions = Vec::with_capacity((array_layers * mip_levels) as usize);
+    let mut offset = 0usize;
+
+    for layer in 0..array_layers {
+        for level in 0..mip_levels {
+            let level_size = level_extent(extent, level);
+            let size = format.region_size(level_size.width, level_size.height, level_size.depth) as usize;
+
+            regions.push(ContainerRegion {
+                level: level,
+                base_layer: layer,
+                layer_count: 1,
+                data: dds.data.get(offset..offset + size).expect("DDS file is truncated"),
+            });
+
+            offset += size;
+        }
+    }
+
+    return ContainerImage {
+        format,
+        image_type,
+        view_type,
+        extent,
+        mip_levels,
+        array_layers,
+        cube,
+        regions,
+    };
+}
+
+//// Upload ////
+fn upload_container_image(device: &Device, container: &ContainerImage, index: u32) -> Texture {
+    let image_desc = ImageDescription {
+        usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+        format: container.format,
+        image_type: container.image_type,
+        extent: container.extent,
+        memory_type: MemoryType::DeviceLocal,
+        mip_levels: container.mip_levels,
+        array_layers: container.array_layers,
+        samples: SampleCount::Type1,
+        flags: if container.cube { ImageCreateFlags::CUBE_COMPATIBLE } else { ImageCreateFlags::empty() },
+        ..Default::default()
+    };
+
+    let image = device.create_image(&image_desc);
+
+    let all_subresources = ImageSubresources {
+        aspect: ImageAspect::Color,
+        mip_level: 0,
+        level_count: container.mip_levels,
+        base_array_layer: 0,
+        layer_count: container.array_layers,
+    };
+
+    let image_view = device.create_image_view(
+        image,
+        &ImageViewDescription {
+            view_type: container.view_type,
+            subresources: all_subresources,
+            ..Default::default()
+        },
+    );
+
+    device.write_image(&ImageWriteInfo {
+        view: image_view,
+        image_descriptor_type: ImageDescriptorType::SampledImage,
+        index: index,
+    });
+
+    // Every region goes to its own offset of a single staging buffer, copy offsets must be multiples of both the block size and 4
+    let (block_width, block_height, block_bytes) = container.format.texel_block_info();
+    let alignment = least_common_multiple(block_bytes as usize, 4);
+
+    let mut offsets = Vec::with_capacity(container.regions.len());
+    let mut staging_size = 0usize;
+    for region in &container.regions {
+        staging_size = staging_size.next_multiple_of(alignment);
+        offsets.push(staging_size);
+        staging_size += region.data.len();
+    }
+
+    let staging_buffer = device.create_buffer(&BufferDescription {
+        usage: BufferUsage::TRANSFER_SRC,
+        size: staging_size as u64,
+        memory_type: MemoryType::PreferHost,
+        create_mapped: true,
+        ..Default::default()
+    });
+
+    let staging_ptr = device.get_raw_ptr(staging_buffer);
+    for (region, &offset) in container.regions.iter().zip(offsets.iter()) {
+        unsafe {
+            std::ptr::copy_nonoverlapping(region.data.as_ptr(), staging_ptr.add(offset), region.data.len());
+        }
+    }
+
+    let mut recorder = device.create_command_recorder(QueueType::Graphics);
+    recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+
+    recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+        image: image,
+        old_layout: ImageLayout::Undefined,
+        new_layout: ImageLayout::TransferDst,
+        src_stage: PipelineStage::TopOfPipe,
+        dst_stage: PipelineStage::Transfer,
+        src_access: AccessType::None,
+        dst_access: AccessType::TransferWrite,
+        subresources: all_subresources,
+        ..Default::default()
+    })]);
+
+    for (region, &offset) in container.regions.iter().zip(offsets.iter()) {
+        let level_size = level_extent(container.extent, region.level);
+
+        // Row pitch is given in texels and has to cover whole blocks
+        recorder.copy_buffer_to_image(&BufferImageCopyInfo {
+            buffer: staging_buffer,
+            image: image,
+            dst_image_layout: ImageLayout::TransferDst,
+            region: BufferImageCopyRegion {
+                buffer_offset: offset as u64,
+                buffer_row_length: level_size.width.next_multiple_of(block_width),
+                buffer_image_height: level_size.height.next_multiple_of(block_height),
+                image_subresource: ImageSubresources {
+                    aspect: ImageAspect::Color,
+                    mip_level: region.level,
+                    level_count: 1,
+                    base_array_layer: region.base_layer,
+                    layer_count: region.layer_count,
+                },
+                image_offset: Offset3D { x: 0, y: 0, z: 0 },
+                image_extent: level_size,
+            },
+        });
+    }
+
+    recorder.pipeline_barrier(&[Barrier::Image(ImageBarrier {
+        image: image,
+        old_layout: ImageLayout::TransferDst,
+        new_layout: ImageLayout::ShaderReadOnly,
+        src_stage: PipelineStage::Transfer,
+        dst_stage: PipelineStage::AllCommands,
+        src_access: AccessType::TransferWrite,
+        dst_access: AccessType::ShaderRead,
+        subresources: all_subresources,
+        ..Default::default()
+    })]);
+
+    let exec_buffer = recorder.end_recording();
+
+    device.submit(&QueueSubmitInfo {
+        fence: None,
+        command_buffers: &[exec_buffer],
+        wait_semaphores: &[],
+        signal_semaphores: &[],
+    });
+    device.wait_queue(QueueType::Graphics);
+
+    device.destroy_buffer(staging_buffer);
+
+    return Texture { image, image_view };
+}
+
+pub(crate) fn create_texture_from_ktx2(device: &Device, bytes: &[u8], index: u32) -> Texture {
+    let reader = ktx2::Reader::new(bytes).expect("Failed to parse KTX2 file");
+    let container = parse_ktx2(&reader);
+
+    return upload_container_image(device, &container, index);
+}
+
+pub(crate) fn create_texture_from_dds(device: &Device, bytes: &[u8], index: u32) -> Texture {
+    let dds = ddsfile::Dds::read(bytes).expect("Failed to parse DDS file");
+    let container = parse_dds(&dds);
+
+    return upload_container_image(device, &container, index);
+}
+
+fn least_common_multiple(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+
+    return a / x * b;
+}
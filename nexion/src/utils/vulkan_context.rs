@@ -31,6 +31,10 @@ impl VulkanContext {
         self.device.wait_idle();
         self.swapchain.recreate_swapchain(width, height);
     }
+
+    pub fn capture_current_image(&self, layout: ImageLayout) -> ImageData {
+        return self.swapchain.capture_current_image(&self.device, layout);
+    }
 }
 
 impl VulkanContext {
@@ -41,14 +45,26 @@ impl VulkanContext {
             pub fn destroy_buffer(&self, id: BufferId);
             pub fn write_data_to_buffer<T: Copy>(&self, buffer_id: BufferId, data: &[T]);
             pub fn get_raw_ptr(&self, buffer_id: BufferId) -> *mut u8;
+            //Buffer slice
+            pub fn create_buffer_slice(&self, slice_desc: &BufferSliceDescription) -> BufferSlice;
+            pub fn destroy_buffer_slice(&self, slice: BufferSlice);
+            pub fn write_data_to_buffer_slice<T: Copy>(&self, slice: BufferSlice, data: &[T]);
+            //Buffer view
+            pub fn create_buffer_view(&self, buffer_id: BufferId, format: Format, range: std::ops::Range<u64>) -> BufferViewId;
+            pub fn destroy_buffer_view(&self, buffer_view_id: BufferViewId);
             //Image
             pub fn create_image(&self, image_desc: &ImageDescription) -> ImageId;
             pub fn destroy_image(&self, image_id: ImageId);
+            pub fn get_image_raw_ptr(&self, image_id: ImageId) -> *mut u8;
+            pub fn get_image_subresource_layout(&self, image_id: ImageId, aspect: ImageAspect, mip_level: u32, array_layer: u32) -> SubresourceLayout;
+            pub fn read_image(&self, image: ImageId, layout: ImageLayout, subresource: ImageSubresources) -> ImageData;
             //Image view
+            pub fn supported_sample_counts(&self, format: Format, usage: ImageUsage) -> Vec<SampleCount>;
             pub fn create_image_view(&self, image_id: ImageId, image_view_desc: &ImageViewDescription) -> ImageViewId;
             pub fn destroy_image_view(&self, image_view_id: ImageViewId);
             //Sampler
             pub fn create_sampler(&self, sampler_desc: &SamplerDescription) -> SamplerId;
+            pub fn create_bindless_sampler(&self, sampler_desc: &SamplerDescription) -> BindlessSampler;
             pub fn destroy_sampler(&self, sampler_id: SamplerId);
             //Texture
             pub fn create_texture(&self, image_desc: &ImageDescription, image_view_desc: &ImageViewDescription, index: u32) -> Texture;
@@ -60,9 +76,11 @@ impl VulkanContext {
             // Descriptors
             pub fn write_buffer(&self, buffer_write_info: &BufferWriteInfo);
             pub fn write_image(&self, image_write_info: &ImageWriteInfo);
+            pub fn write_buffer_view(&self, buffer_view_write_info: &BufferViewWriteInfo);
             pub fn write_sampler(&self, sampler_write_info: &SamplerWriteInfo);
             // Command buffer
             pub fn create_command_recorder(&self, queue_type: QueueType) -> CommandRecorder;
+            pub fn create_secondary_command_recorder(&self, queue_type: QueueType) -> CommandRecorder;
             // Sync
             pub fn create_fence(&self, signaled: bool) -> Fence;
             pub fn create_binary_semaphore(&self) -> Semaphore;
@@ -71,10 +89,18 @@ impl VulkanContext {
             pub fn reset_fence(&self, fence: Fence);
             pub fn destroy_fence(&self, fence: Fence);
             pub fn destroy_semaphore(&self, semaphore: Semaphore);
+            // Queries
+            pub fn create_query_pool(&self, kind: QueryKind, count: u32) -> QueryPool;
+            pub fn destroy_query_pool(&self, pool: QueryPool);
+            pub fn get_query_results(&self, pool: QueryPool, first_query: u32, query_count: u32, wait: bool) -> QueryResults;
+            pub fn reset_query_pool(&self, pool: QueryPool, first_query: u32, query_count: u32);
             // Queue submissions
             pub fn submit(&self, submit_info: &QueueSubmitInfo);
             pub fn wait_idle(&self);
             pub fn wait_queue(&self, queue_type: QueueType);
+            // Profiling
+            pub fn gpu_profile_results(&self) -> Vec<GpuScope>;
+            pub fn begin_gpu_profile_frame(&self, frame: usize);
         }
         to self.swapchain {
             pub fn acquire_image(&self) -> AcquiredImage;
@@ -82,3 +108,18 @@ impl VulkanContext {
         }
     }
 }
+
+#[cfg(unix)]
+impl VulkanContext {
+    delegate! {
+        to self.device {
+            // External memory
+            pub fn export_memory_fd(&self, resource: ExternalResource) -> ExternalMemoryFd;
+            pub fn import_buffer_from_fd(&self, buffer_desc: &BufferDescription, memory: ExternalMemoryFd) -> BufferId;
+            pub fn import_image_from_fd(&self, image_desc: &ImageDescription, memory: ExternalMemoryFd) -> ImageId;
+            pub fn create_exportable_semaphore(&self, timeline: bool) -> Semaphore;
+            pub fn export_semaphore_fd(&self, semaphore: Semaphore) -> std::os::fd::OwnedFd;
+            pub fn import_semaphore_fd(&self, semaphore: Semaphore, fd: std::os::fd::OwnedFd);
+        }
+    }
+}
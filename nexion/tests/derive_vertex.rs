@@ -0,0 +1,106 @@
+#![cfg(feature = "derive")]
+
+use nexion::{Format, Vertex, VertexInputDescription, VertexInputRate};
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct GlamVertex {
+    position: glam::Vec3,
+    uv: glam::Vec2,
+    ids: glam::UVec4,
+    offset: glam::IVec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+#[input_rate(Instance)]
+struct MintInstance {
+    translation: mint::Vector3<f32>,
+    anchor: mint::Point2<f32>,
+    tint: mint::Vector4<u32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct FormatVertex {
+    #[format(Rgba8Unorm)]
+    color: [u8; 4],
+    #[format(Rgb10A2Snorm)]
+    normal: u32,
+    weight: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct LocationVertex {
+    #[location(3)]
+    position: [f32; 3],
+    normal: [f32; 3],
+    #[location(0)]
+    uv: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct TupleVertex {
+    position: (f32, f32, f32),
+    uv: (f32, f32),
+    joints: (u32, u32, u32, u32),
+}
+
+fn formats<V: Vertex>() -> Vec<(u32, Format, u32)> {
+    return V::attributes().iter().map(|attribute| (attribute.location, attribute.format, attribute.offset)).collect();
+}
+
+#[test]
+fn glam_fields_pick_their_vector_format() {
+    assert!(matches!(GlamVertex::INPUT_RATE, VertexInputRate::Vertex));
+    assert_eq!(formats::<GlamVertex>(), vec![(0, Format::Rgb32Float, 0), (1, Format::Rg32Float, 12), (2, Format::Rgba32Uint, 20), (3, Format::Rg32Sint, 36)]);
+}
+
+#[test]
+fn mint_fields_pick_their_vector_format() {
+    assert!(matches!(MintInstance::INPUT_RATE, VertexInputRate::Instance));
+    assert_eq!(formats::<MintInstance>(), vec![(0, Format::Rgb32Float, 0), (1, Format::Rg32Float, 12), (2, Format::Rgba32Uint, 20)]);
+
+    let description = MintInstance::vertex_input_description();
+    assert!(matches!(description.bindings[0].input_rate, VertexInputRate::Instance));
+    assert_eq!(description.bindings[0].stride, 36);
+}
+
+#[test]
+fn format_attribute_overrides_the_field_type() {
+    assert_eq!(formats::<FormatVertex>(), vec![(0, Format::Rgba8Unorm, 0), (1, Format::Rgb10A2Snorm, 4), (2, Format::R32Float, 8)]);
+}
+
+#[test]
+fn tuple_fields_pick_their_vector_format() {
+    assert_eq!(formats::<TupleVertex>(), vec![(0, Format::Rgb32Float, 0), (1, Format::Rg32Float, 12), (2, Format::Rgba32Uint, 20)]);
+}
+
+#[test]
+fn location_attribute_sets_the_following_locations() {
+    const { assert!(LocationVertex::EXPLICIT_LOCATIONS) };
+    assert_eq!(formats::<LocationVertex>(), vec![(3, Format::Rgb32Float, 0), (4, Format::Rgb32Float, 12), (0, Format::Rg32Float, 24)]);
+}
+
+#[test]
+fn later_bindings_continue_the_locations() {
+    let description = VertexInputDescription::default().with_binding::<GlamVertex>(0).with_binding::<MintInstance>(1);
+    let locations: Vec<(u32, u32)> = description.attributes.iter().map(|attribute| (attribute.binding, attribute.location)).collect();
+
+    assert_eq!(locations, vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 4), (1, 5), (1, 6)]);
+}
+
+#[test]
+#[should_panic(expected = "Two vertex attributes share a location")]
+fn explicit_locations_are_not_shifted() {
+    let _ = VertexInputDescription::default().with_binding::<FormatVertex>(0).with_binding::<LocationVertex>(1);
+}
+
+#[test]
+fn layout_mismatch_is_a_compile_error() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/size_mismatch.rs");
+    tests.compile_fail("tests/ui/tuple_layout.rs");
+}
@@ -0,0 +1,10 @@
+use nexion::Vertex;
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct WrongSize {
+    #[format(Rgba32Float)]
+    color: [f32; 3],
+}
+
+fn main() {}
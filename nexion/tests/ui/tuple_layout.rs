@@ -0,0 +1,10 @@
+use nexion::Vertex;
+
+#[repr(C)]
+#[derive(Clone, Copy, Vertex)]
+struct ReorderedTuple {
+    #[format(Rgba8Unorm)]
+    color: (u8, u16, u8),
+}
+
+fn main() {}
@@ -0,0 +1,239 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, Index, LitInt, PathArguments, Type, parse_macro_input, spanned::Spanned};
+
+/// Implements `nexion::Vertex` for a `#[repr(C)]` struct with named fields.
+///
+/// - `#[input_rate(Instance)]` on the struct, `Vertex` when left out
+/// - `#[format(Rgb10A2Snorm)]` on a field picks any `nexion::Format` variant
+/// - `#[location(n)]` on a field sets its shader location, the fields after it count up from there
+///
+/// Without `#[format]` the format comes from the field type: glam and mint vectors, tuples of f32, u32 or i32
+/// or anything implementing `VertexFormat`. Every field is checked at compile time to be exactly as big as its format,
+/// tuple fields also to have their elements packed in order since rust doesn't guarantee that for tuples.
+#[proc_macro_derive(Vertex, attributes(input_rate, format, location))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    return match expand_vertex(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    };
+}
+
+fn expand_vertex(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "Vertex can't be derived for generic structs"));
+    }
+
+    if !has_repr_c(&input.attrs) {
+        return Err(Error::new_spanned(name, "Vertex structs need #[repr(C)] so the field offsets are stable"));
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(name, "Vertex can only be derived for structs"));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(&data.fields, "Vertex needs a struct with named fields"));
+    };
+
+    let mut input_rate = format_ident!("Vertex");
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("input_rate")) {
+        input_rate = attr.parse_args()?;
+
+        if input_rate != "Vertex" && input_rate != "Instance" {
+            return Err(Error::new_spanned(&input_rate, "Input rate has to be Vertex or Instance"));
+        }
+    }
+
+    let mut location = 0u32;
+    let mut explicit_locations = false;
+    let mut used_locations = Vec::new();
+    let mut attributes = Vec::new();
+    let mut size_checks = Vec::new();
+
+    for field in &fields.named {
+        let field_name = field.ident.as_ref().expect("Named fields always have a name");
+        let ty = &field.ty;
+        let mut format = None;
+
+        for attr in &field.attrs {
+            if attr.path().is_ident("format") {
+                let variant: Ident = attr.parse_args()?;
+                format = Some(quote_spanned!(variant.span()=> ::nexion::Format::#variant));
+            } else if attr.path().is_ident("location") {
+                let lit: LitInt = attr.parse_args()?;
+                location = lit.base10_parse()?;
+                explicit_locations = true;
+            }
+        }
+
+        if used_locations.contains(&location) {
+            return Err(Error::new_spanned(field, format!("Location {} is used by two fields", location)));
+        }
+        used_locations.push(location);
+
+        let format = match format {
+            Some(format) => format,
+            None => match known_format(ty) {
+                Some(variant) => quote!(::nexion::Format::#variant),
+                None => quote_spanned!(ty.span()=> <#ty as ::nexion::VertexFormat>::FORMAT),
+            },
+        };
+
+        let message = format!("Field `{}` doesnt have the size of its vertex format", field_name);
+        size_checks.push(quote_spanned! {ty.span()=>
+            assert!(::core::mem::size_of::<#ty>() as u32 == #format.texel_block_info().2, #message);
+        });
+
+        if let Type::Tuple(tuple) = ty {
+            let message = format!("Elements of the tuple field `{}` arent laid out in order", field_name);
+
+            for i in 0..tuple.elems.len() {
+                let index = Index::from(i);
+                let before = tuple.elems.iter().take(i);
+                let offset = if i == 0 { quote!(0) } else { quote!(#(::core::mem::size_of::<#before>())+*) };
+
+                size_checks.push(quote_spanned! {ty.span()=>
+                    assert!(::core::mem::offset_of!(#ty, #index) == #offset, #message);
+                });
+            }
+        }
+
+        attributes.push(quote! {
+            ::nexion::VertexAttribute {
+                location: #location,
+                binding: 0,
+                format: #format,
+                offset: ::core::mem::offset_of!(#name, #field_name) as u32,
+            }
+        });
+
+        location += 1;
+    }
+
+    return Ok(quote! {
+        impl ::nexion::Vertex for #name {
+            const INPUT_RATE: ::nexion::VertexInputRate = ::nexion::VertexInputRate::#input_rate;
+            const EXPLICIT_LOCATIONS: bool = #explicit_locations;
+
+            fn attributes() -> ::std::vec::Vec<::nexion::VertexAttribute> {
+                return ::std::vec![#(#attributes),*];
+            }
+        }
+
+        impl #name {
+            /// Only this struct at binding 0, use `VertexInputDescription::with_binding` to combine several
+            pub fn vertex_input_description() -> ::nexion::VertexInputDescription {
+                return ::nexion::VertexInputDescription::default().with_binding::<Self>(0);
+            }
+        }
+
+        const _: () = {
+            #(#size_checks)*
+        };
+    });
+}
+
+fn has_repr_c(attrs: &[Attribute]) -> bool {
+    let mut repr_c = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            repr_c |= meta.path.is_ident("C");
+            return Ok(());
+        });
+    }
+
+    return repr_c;
+}
+
+/// Format for vector types known by name, None leaves it to `VertexFormat`
+fn known_format(ty: &Type) -> Option<Ident> {
+    let path = match ty {
+        Type::Tuple(tuple) => {
+            let first = tuple.elems.first().and_then(scalar_name)?;
+
+            if tuple.elems.iter().any(|elem| scalar_name(elem).as_deref() != Some(first.as_str())) {
+                return None;
+            }
+
+            return vector_format(&first, tuple.elems.len());
+        }
+        Type::Path(path) => path,
+        _ => return None,
+    };
+
+    let segment = path.path.segments.last()?;
+    let name = segment.ident.to_string();
+
+    // glam
+    let glam = match name.as_str() {
+        "Vec2" => Some(("f32", 2)),
+        "Vec3" => Some(("f32", 3)),
+        "Vec4" => Some(("f32", 4)),
+        "IVec2" => Some(("i32", 2)),
+        "IVec3" => Some(("i32", 3)),
+        "IVec4" => Some(("i32", 4)),
+        "UVec2" => Some(("u32", 2)),
+        "UVec3" => Some(("u32", 3)),
+        "UVec4" => Some(("u32", 4)),
+        _ => None,
+    };
+
+    if let Some((scalar, count)) = glam {
+        return vector_format(scalar, count);
+    }
+
+    // mint
+    let count = match name.as_str() {
+        "Vector2" | "Point2" => 2,
+        "Vector3" | "Point3" => 3,
+        "Vector4" => 4,
+        _ => return None,
+    };
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    let Some(GenericArgument::Type(scalar)) = args.args.first() else {
+        return None;
+    };
+
+    return vector_format(&scalar_name(scalar)?, count);
+}
+
+fn scalar_name(ty: &Type) -> Option<String> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let name = path.path.get_ident()?.to_string();
+
+    return matches!(name.as_str(), "f32" | "u32" | "i32").then_some(name);
+}
+
+fn vector_format(scalar: &str, count: usize) -> Option<Ident> {
+    let channels = match count {
+        1 => "R",
+        2 => "Rg",
+        3 => "Rgb",
+        4 => "Rgba",
+        _ => return None,
+    };
+
+    let kind = match scalar {
+        "f32" => "Float",
+        "u32" => "Uint",
+        "i32" => "Sint",
+        _ => return None,
+    };
+
+    return Some(format_ident!("{}32{}", channels, kind));
+}
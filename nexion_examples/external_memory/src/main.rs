@@ -0,0 +1,116 @@
+//! Shares a buffer between two devices of the same process through an opaque fd.
+//! Device A fills an exported buffer, device B imports it and reads the data back.
+
+use nexion::*;
+use winit::{application::ApplicationHandler, event::WindowEvent, event_loop::EventLoop};
+
+const VALUES: u64 = 1024;
+const SIZE: u64 = VALUES * std::mem::size_of::<u32>() as u64;
+
+fn copy_and_wait(device: &Device, src_buffer: BufferId, dst_buffer: BufferId) {
+    let mut recorder = device.create_command_recorder(QueueType::Graphics);
+    recorder.begin_recording(CommandBufferUsage::OneTimeSubmit);
+    recorder.copy_buffer(&BufferCopyInfo {
+        src_buffer: src_buffer,
+        dst_buffer: dst_buffer,
+        regions: &[CopyRegion {
+            src_offset: 0,
+            dst_offset: 0,
+            size: SIZE,
+        }],
+    });
+    let exec_buffer = recorder.end_recording();
+
+    device.submit(&QueueSubmitInfo {
+        fence: None,
+        command_buffers: &[exec_buffer],
+        wait_semaphores: &[],
+        signal_semaphores: &[],
+    });
+    device.wait_queue(QueueType::Graphics);
+}
+
+fn share_buffer(instance: &Instance) {
+    let device_desc = DeviceDescription {
+        external_memory: true,
+        ..Default::default()
+    };
+    let device_a = instance.create_device(&device_desc);
+    let device_b = instance.create_device(&device_desc);
+
+    let shared_desc = BufferDescription {
+        usage: BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+        size: SIZE,
+        memory_type: MemoryType::DeviceLocal,
+        external_memory: Some(ExternalMemoryHandleType::OpaqueFd),
+        ..Default::default()
+    };
+    let staging_desc = BufferDescription {
+        usage: BufferUsage::TRANSFER_SRC | BufferUsage::TRANSFER_DST,
+        size: SIZE,
+        memory_type: MemoryType::PreferHost,
+        create_mapped: true,
+        ..Default::default()
+    };
+
+    // Device A writes through a staging buffer into the exported one
+    let values: Vec<u32> = (0..VALUES as u32).map(|i| i * 3 + 7).collect();
+
+    let exported = device_a.create_buffer(&shared_desc);
+    let upload = device_a.create_buffer(&staging_desc);
+    device_a.write_data_to_buffer(upload, &values);
+    copy_and_wait(&device_a, upload, exported);
+
+    let memory = device_a.export_memory_fd(ExternalResource::Buffer(exported));
+
+    // Device B sees the same memory through its own buffer
+    let imported = device_b.import_buffer_from_fd(&shared_desc, memory);
+    let readback = device_b.create_buffer(&staging_desc);
+    copy_and_wait(&device_b, imported, readback);
+
+    let read = unsafe {
+        std::slice::from_raw_parts(device_b.get_raw_ptr(readback) as *const u32, VALUES as usize)
+    };
+    assert_eq!(read, values.as_slice(), "Imported buffer doesnt hold what the exporting device wrote");
+
+    device_b.destroy_buffer(readback);
+    device_b.destroy_buffer(imported);
+    device_a.destroy_buffer(upload);
+    device_a.destroy_buffer(exported);
+
+    println!("{} values shared between two devices", VALUES);
+}
+
+struct App;
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let window = event_loop
+            .create_window(winit::window::Window::default_attributes().with_visible(false))
+            .expect("Failed to create window");
+
+        let instance = Instance::new(
+            &window,
+            &InstanceDescription {
+                api_version: ApiVersion::VkApi1_3,
+                enable_validation_layers: true,
+            },
+        );
+
+        share_buffer(&instance);
+        event_loop.exit();
+    }
+
+    fn window_event(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        _event: WindowEvent,
+    ) {
+    }
+}
+
+fn main() {
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    event_loop.run_app(&mut App).expect("Failed to run event loop");
+}
@@ -142,6 +142,7 @@ impl VulkanApp {
             size: bytes.len() as u64,
             memory_type: MemoryType::PreferHost,
             create_mapped: true,
+            ..Default::default()
         });
 
         device.write_data_to_buffer(staging_buffer, &vertex_data);
@@ -151,6 +152,7 @@ impl VulkanApp {
             size: 96,
             memory_type: MemoryType::DeviceLocal,
             create_mapped: false,
+            ..Default::default()
         });
 
         let mut recorder = device.create_command_recorder(QueueType::Graphics);
@@ -206,6 +208,7 @@ impl VulkanApp {
             size: 12,
             memory_type: MemoryType::PreferHost,
             create_mapped: true,
+            ..Default::default()
         });
         let color_data = [0.1, 0.8, 0.1];
         device.write_data_to_buffer(color_buffer, &color_data);